@@ -0,0 +1,672 @@
+//! Structural diff between two ASTs.
+//!
+//! Nodes are matched by `NodeId` first, and by structure second: identical subtrees, then
+//! parents of matched children, then the remaining children of matched parents in order.
+//! Matched nodes keep the ids of the old tree, so applying the edits preserves node identity.
+//! Attributes are not nodes, so attributed nodes are diffed as the nodes they wrap.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use dson::Dson;
+use ids::NodeId;
+
+use crate::{
+    expr::{Expr, Handler, Literal, MapElem, MatchCase},
+    meta::WithMeta,
+    ty::{Effect, EffectExpr, Type},
+};
+
+/// A node of an AST. Children are in the order of operands in the node graph.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeRef<'a> {
+    Expr(&'a WithMeta<Expr>),
+    Type(&'a WithMeta<Type>),
+    EffectExpr(&'a WithMeta<EffectExpr>),
+    Effect(&'a WithMeta<Effect>),
+    Handler(&'a WithMeta<Handler>),
+    MatchCase(&'a WithMeta<MatchCase>),
+    MapElem(&'a WithMeta<MapElem>),
+}
+
+/// An edit applied in order. `position` is an index in the operands at the time of the edit.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit<'a> {
+    /// Inserts a node without children. `parent` is `None` for the new root.
+    Insert {
+        node_id: NodeId,
+        node: NodeRef<'a>,
+        parent: Option<NodeId>,
+        position: usize,
+    },
+    /// Deletes a node having no children.
+    Delete {
+        node_id: NodeId,
+        parent: Option<NodeId>,
+    },
+    /// Moves a node from the operands of `from` to the operands of `parent`.
+    Move {
+        node_id: NodeId,
+        from: Option<NodeId>,
+        parent: Option<NodeId>,
+        position: usize,
+    },
+    UpdateLiteral {
+        node_id: NodeId,
+        old: &'a Literal,
+        new: &'a Literal,
+    },
+    UpdateType {
+        node_id: NodeId,
+        ty: &'a Type,
+    },
+    /// Replaces the attributes of an inserted or matched node, sorted in both lists.
+    UpdateAttributes {
+        node_id: NodeId,
+        old: Vec<&'a Dson>,
+        new: Vec<&'a Dson>,
+    },
+}
+
+impl<'a> NodeRef<'a> {
+    pub fn id(&self) -> NodeId {
+        match self {
+            NodeRef::Expr(node) => node.meta.id,
+            NodeRef::Type(node) => node.meta.id,
+            NodeRef::EffectExpr(node) => node.meta.id,
+            NodeRef::Effect(node) => node.meta.id,
+            NodeRef::Handler(node) => node.meta.id,
+            NodeRef::MatchCase(node) => node.meta.id,
+            NodeRef::MapElem(node) => node.meta.id,
+        }
+    }
+
+    pub fn children(&self) -> Vec<NodeRef<'a>> {
+        use NodeRef as N;
+        match *self {
+            N::Expr(expr) => match &expr.value {
+                Expr::Literal(_) | Expr::Hole => vec![],
+                Expr::Do { stmt, expr } => vec![N::Expr(stmt), N::Expr(expr)],
+                Expr::Let { definition, body } => vec![N::Expr(definition), N::Expr(body)],
+                Expr::Perform { input, output } | Expr::Continue { input, output } => {
+                    vec![N::Expr(input), N::Type(output)]
+                }
+                Expr::Handle { expr, handlers } => std::iter::once(N::Expr(expr))
+                    .chain(handlers.iter().map(N::Handler))
+                    .collect(),
+                Expr::Apply {
+                    function,
+                    arguments,
+                    ..
+                } => std::iter::once(N::Type(function))
+                    .chain(arguments.iter().map(N::Expr))
+                    .collect(),
+                Expr::Product(items) | Expr::Vector(items) => items.iter().map(N::Expr).collect(),
+                Expr::Match { of, cases } => std::iter::once(N::Expr(of))
+                    .chain(cases.iter().map(N::MatchCase))
+                    .collect(),
+                Expr::Typed { ty, item } => vec![N::Type(ty), N::Expr(item)],
+                Expr::Function { parameter, body } => vec![N::Type(parameter), N::Expr(body)],
+                Expr::Map(elems) => elems.iter().map(N::MapElem).collect(),
+                Expr::Attributed { item, .. }
+                | Expr::DeclareBrand { item, .. }
+                | Expr::Label { item, .. } => vec![N::Expr(item)],
+                Expr::NewType { ty, expr, .. } => vec![N::Type(ty), N::Expr(expr)],
+                Expr::Card { item, next, .. } => vec![N::Expr(item), N::Expr(next)],
+            },
+            N::Type(ty) => match &ty.value {
+                Type::Real
+                | Type::Rational
+                | Type::Integer
+                | Type::String
+                | Type::Infer
+                | Type::Variable(_) => vec![],
+                Type::Labeled { item, .. } | Type::Vector(item) => vec![N::Type(item)],
+                Type::Effectful { ty, effects } => vec![N::Type(ty), N::EffectExpr(effects)],
+                Type::Product(types) | Type::Sum(types) => types.iter().map(N::Type).collect(),
+                Type::Function(function) => {
+                    vec![N::Type(&function.parameter), N::Type(&function.body)]
+                }
+                Type::Map { key, value } => vec![N::Type(key), N::Type(value)],
+                Type::Let {
+                    definition, body, ..
+                } => vec![N::Type(definition), N::Type(body)],
+                Type::Attributed { ty, .. } => vec![N::Type(ty)],
+                Type::Forall { bound, body, .. } | Type::Exists { bound, body, .. } => bound
+                    .iter()
+                    .map(|bound| N::Type(bound))
+                    .chain(std::iter::once(N::Type(body)))
+                    .collect(),
+            },
+            N::EffectExpr(effects) => match &effects.value {
+                EffectExpr::Effects(effects) => effects.iter().map(N::Effect).collect(),
+                EffectExpr::Add(exprs) => exprs.iter().map(N::EffectExpr).collect(),
+                EffectExpr::Sub {
+                    minuend,
+                    subtrahend,
+                } => vec![N::EffectExpr(minuend), N::EffectExpr(subtrahend)],
+                EffectExpr::Apply {
+                    function,
+                    arguments,
+                } => std::iter::once(N::Type(function))
+                    .chain(arguments.iter().map(N::Type))
+                    .collect(),
+            },
+            N::Effect(effect) => vec![N::Type(&effect.value.input), N::Type(&effect.value.output)],
+            N::Handler(handler) => vec![
+                N::Effect(&handler.value.effect),
+                N::Expr(&handler.value.handler),
+            ],
+            N::MatchCase(case) => vec![N::Type(&case.value.ty), N::Expr(&case.value.expr)],
+            N::MapElem(elem) => vec![N::Expr(&elem.value.key), N::Expr(&elem.value.value)],
+        }
+    }
+
+    /// Compares the nodes themselves, ignoring ids and children.
+    pub fn shallow_eq(&self, other: &Self) -> bool {
+        use NodeRef as N;
+        match (self, other) {
+            (N::Expr(a), N::Expr(b)) => match (&a.value, &b.value) {
+                (Expr::Literal(a), Expr::Literal(b)) => a == b,
+                (Expr::Apply { link_name: a, .. }, Expr::Apply { link_name: b, .. }) => a == b,
+                (Expr::Attributed { attr: a, .. }, Expr::Attributed { attr: b, .. }) => a == b,
+                (Expr::DeclareBrand { brand: a, .. }, Expr::DeclareBrand { brand: b, .. }) => {
+                    a == b
+                }
+                (Expr::Label { label: a, .. }, Expr::Label { label: b, .. }) => a == b,
+                (Expr::NewType { ident: a, .. }, Expr::NewType { ident: b, .. }) => a == b,
+                (Expr::Card { id: a, .. }, Expr::Card { id: b, .. }) => a == b,
+                (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+            },
+            (N::Type(a), N::Type(b)) => match (&a.value, &b.value) {
+                (Type::Labeled { brand: a, .. }, Type::Labeled { brand: b, .. }) => a == b,
+                (Type::Let { variable: a, .. }, Type::Let { variable: b, .. }) => a == b,
+                (Type::Variable(a), Type::Variable(b)) => a == b,
+                (Type::Attributed { attr: a, .. }, Type::Attributed { attr: b, .. }) => a == b,
+                (
+                    Type::Forall {
+                        variable: a,
+                        bound: a_bound,
+                        ..
+                    },
+                    Type::Forall {
+                        variable: b,
+                        bound: b_bound,
+                        ..
+                    },
+                )
+                | (
+                    Type::Exists {
+                        variable: a,
+                        bound: a_bound,
+                        ..
+                    },
+                    Type::Exists {
+                        variable: b,
+                        bound: b_bound,
+                        ..
+                    },
+                ) => a == b && a_bound.is_some() == b_bound.is_some(),
+                (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+            },
+            (N::EffectExpr(a), N::EffectExpr(b)) => {
+                std::mem::discriminant(&a.value) == std::mem::discriminant(&b.value)
+            }
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+
+    /// Hashes what `shallow_eq` compares.
+    fn shallow_hash(&self, state: &mut impl Hasher) {
+        use NodeRef as N;
+        std::mem::discriminant(self).hash(state);
+        match self {
+            N::Expr(expr) => {
+                std::mem::discriminant(&expr.value).hash(state);
+                match &expr.value {
+                    Expr::Literal(Literal::String(string)) => string.hash(state),
+                    Expr::Literal(Literal::Integer(integer)) => integer.hash(state),
+                    Expr::Literal(Literal::Rational(a, b)) => (a, b).hash(state),
+                    Expr::Literal(literal) => std::mem::discriminant(literal).hash(state),
+                    Expr::Apply { link_name, .. } => link_name.hash(state),
+                    Expr::Attributed { attr, .. } => attr.hash(state),
+                    Expr::DeclareBrand { brand: ident, .. }
+                    | Expr::Label { label: ident, .. }
+                    | Expr::NewType { ident, .. } => ident.hash(state),
+                    Expr::Card { id, .. } => id.hash(state),
+                    _ => {}
+                }
+            }
+            N::Type(ty) => {
+                std::mem::discriminant(&ty.value).hash(state);
+                match &ty.value {
+                    Type::Labeled { brand: ident, .. }
+                    | Type::Let {
+                        variable: ident, ..
+                    }
+                    | Type::Variable(ident) => ident.hash(state),
+                    Type::Attributed { attr, .. } => attr.hash(state),
+                    Type::Forall {
+                        variable, bound, ..
+                    }
+                    | Type::Exists {
+                        variable, bound, ..
+                    } => (variable, bound.is_some()).hash(state),
+                    _ => {}
+                }
+            }
+            N::EffectExpr(effects) => std::mem::discriminant(&effects.value).hash(state),
+            _ => {}
+        }
+    }
+
+    /// The node wrapped by attributes, and the attributes sorted.
+    fn peel(self) -> (Self, Vec<&'a Dson>) {
+        let mut node = self;
+        let mut attributes = vec![];
+        loop {
+            match node {
+                NodeRef::Expr(WithMeta {
+                    value: Expr::Attributed { attr, item },
+                    ..
+                }) => {
+                    attributes.push(attr);
+                    node = NodeRef::Expr(item);
+                }
+                NodeRef::Type(WithMeta {
+                    value: Type::Attributed { attr, ty },
+                    ..
+                }) => {
+                    attributes.push(attr);
+                    node = NodeRef::Type(ty);
+                }
+                _ => break,
+            }
+        }
+        attributes.sort();
+        (node, attributes)
+    }
+
+    /// Whether the nodes can be matched, possibly with `UpdateLiteral` or `UpdateType`.
+    fn matchable(&self, other: &Self) -> bool {
+        match (self, other) {
+            (NodeRef::Expr(a), NodeRef::Expr(b))
+                if matches!((&a.value, &b.value), (Expr::Literal(_), Expr::Literal(_))) =>
+            {
+                true
+            }
+            (NodeRef::Type(_), NodeRef::Type(_)) => true,
+            (a, b) => a.shallow_eq(b),
+        }
+    }
+}
+
+/// Computes edits that turn `old` into `new`.
+pub fn diff<'a>(old: &'a WithMeta<Expr>, new: &'a WithMeta<Expr>) -> Vec<Edit<'a>> {
+    let old = Tree::new(NodeRef::Expr(old));
+    let new = Tree::new(NodeRef::Expr(new));
+    let mut matching = Matching::new(&old, &new);
+    matching.match_ids();
+    matching.match_identical_subtrees();
+    matching.match_parents();
+    matching.match_remaining_children();
+    matching.edits()
+}
+
+/// Nodes in pre-order.
+struct Tree<'a> {
+    nodes: Vec<Entry<'a>>,
+}
+
+struct Entry<'a> {
+    node: NodeRef<'a>,
+    attributes: Vec<&'a Dson>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Equal for identical subtrees.
+    hash: u64,
+}
+
+impl<'a> Tree<'a> {
+    fn new(root: NodeRef<'a>) -> Self {
+        let mut tree = Tree { nodes: vec![] };
+        tree.push(root, None);
+        tree
+    }
+
+    fn push(&mut self, node: NodeRef<'a>, parent: Option<usize>) -> usize {
+        let (node, attributes) = node.peel();
+        let index = self.nodes.len();
+        self.nodes.push(Entry {
+            node,
+            attributes,
+            parent,
+            children: vec![],
+            hash: 0,
+        });
+        let mut hasher = DefaultHasher::new();
+        node.shallow_hash(&mut hasher);
+        self.nodes[index].attributes.hash(&mut hasher);
+        for child in node.children() {
+            let child = self.push(child, Some(index));
+            self.nodes[index].children.push(child);
+            self.nodes[child].hash.hash(&mut hasher);
+        }
+        self.nodes[index].hash = hasher.finish();
+        index
+    }
+
+    fn identical(&self, a: usize, other: &Tree, b: usize) -> bool {
+        let (a, b) = (&self.nodes[a], &other.nodes[b]);
+        a.hash == b.hash
+            && a.node.shallow_eq(&b.node)
+            && a.attributes == b.attributes
+            && a.children.len() == b.children.len()
+            && a.children
+                .iter()
+                .zip(&b.children)
+                .all(|(a, b)| self.identical(*a, other, *b))
+    }
+}
+
+struct Matching<'t, 'a> {
+    old: &'t Tree<'a>,
+    new: &'t Tree<'a>,
+    old_to_new: Vec<Option<usize>>,
+    new_to_old: Vec<Option<usize>>,
+}
+
+impl<'t, 'a> Matching<'t, 'a> {
+    fn new(old: &'t Tree<'a>, new: &'t Tree<'a>) -> Self {
+        Self {
+            old,
+            new,
+            old_to_new: vec![None; old.nodes.len()],
+            new_to_old: vec![None; new.nodes.len()],
+        }
+    }
+
+    fn link(&mut self, old: usize, new: usize) {
+        self.old_to_new[old] = Some(new);
+        self.new_to_old[new] = Some(old);
+    }
+
+    fn can_link(&self, old: usize, new: usize) -> bool {
+        self.old_to_new[old].is_none()
+            && self.new_to_old[new].is_none()
+            && self.old.nodes[old]
+                .node
+                .matchable(&self.new.nodes[new].node)
+    }
+
+    /// Whether the parents of the nodes are matched with each other, or both are roots.
+    fn same_parent(&self, old: usize, new: usize) -> bool {
+        match (self.old.nodes[old].parent, self.new.nodes[new].parent) {
+            (Some(old), Some(new)) => self.new_to_old[new] == Some(old),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn match_ids(&mut self) {
+        let ids: HashMap<_, _> = self
+            .old
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (entry.node.id(), index))
+            .collect();
+        for new in 0..self.new.nodes.len() {
+            if let Some(&old) = ids.get(&self.new.nodes[new].node.id()) {
+                if self.can_link(old, new) {
+                    self.link(old, new);
+                }
+            }
+        }
+    }
+
+    /// Leaves are matched only under matched parents, since identical leaves are common.
+    fn match_identical_subtrees(&mut self) {
+        let mut subtrees: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (old, entry) in self.old.nodes.iter().enumerate() {
+            subtrees.entry(entry.hash).or_default().push(old);
+        }
+        for new in 0..self.new.nodes.len() {
+            if self.new_to_old[new].is_some() {
+                continue;
+            }
+            let is_leaf = self.new.nodes[new].children.is_empty();
+            let candidates: Vec<_> = subtrees
+                .get(&self.new.nodes[new].hash)
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|&old| {
+                    self.old_to_new[old].is_none() && self.old.identical(old, self.new, new)
+                })
+                .collect();
+            let old = candidates
+                .iter()
+                .find(|&&old| self.same_parent(old, new))
+                .or_else(|| candidates.first().filter(|_| !is_leaf));
+            if let Some(&old) = old {
+                self.link_subtrees(old, new);
+            }
+        }
+    }
+
+    fn link_subtrees(&mut self, old: usize, new: usize) {
+        if self.can_link(old, new) {
+            self.link(old, new);
+        }
+        for (&old, &new) in self.old.nodes[old]
+            .children
+            .iter()
+            .zip(&self.new.nodes[new].children)
+        {
+            self.link_subtrees(old, new);
+        }
+    }
+
+    /// Matches a node with the old node having the most of its matched children.
+    fn match_parents(&mut self) {
+        for new in (0..self.new.nodes.len()).rev() {
+            if self.new_to_old[new].is_some() {
+                continue;
+            }
+            let mut counts: HashMap<usize, usize> = HashMap::new();
+            for &child in &self.new.nodes[new].children {
+                if let Some(parent) =
+                    self.new_to_old[child].and_then(|old| self.old.nodes[old].parent)
+                {
+                    *counts.entry(parent).or_default() += 1;
+                }
+            }
+            let old = counts
+                .into_iter()
+                .filter(|&(old, _)| self.can_link(old, new))
+                .max_by_key(|&(old, count)| (count, std::cmp::Reverse(old)));
+            if let Some((old, _)) = old {
+                self.link(old, new);
+            }
+        }
+    }
+
+    /// Prefers identical subtrees, then equal nodes, to nodes only matchable with updates.
+    fn match_remaining_children(&mut self) {
+        if self.can_link(0, 0) {
+            self.link(0, 0);
+        }
+        for new in 0..self.new.nodes.len() {
+            let Some(old) = self.new_to_old[new] else {
+                continue;
+            };
+            let (old_children, new_children) =
+                (&self.old.nodes[old].children, &self.new.nodes[new].children);
+            let passes: [&dyn Fn(usize, usize) -> bool; 3] = [
+                &|old, new| self.old.identical(old, self.new, new),
+                &|old, new| {
+                    self.old.nodes[old]
+                        .node
+                        .shallow_eq(&self.new.nodes[new].node)
+                },
+                &|_, _| true,
+            ];
+            let mut links = vec![];
+            for (pass_index, pass) in passes.into_iter().enumerate() {
+                for &new_child in new_children {
+                    let old_child = old_children.iter().copied().find(|&old_child| {
+                        self.can_link(old_child, new_child)
+                            && !links
+                                .iter()
+                                .any(|&(old, new, _)| old == old_child || new == new_child)
+                            && pass(old_child, new_child)
+                    });
+                    if let Some(old_child) = old_child {
+                        links.push((old_child, new_child, pass_index == 0));
+                    }
+                }
+            }
+            for (old_child, new_child, identical) in links {
+                if identical {
+                    self.link_subtrees(old_child, new_child);
+                } else {
+                    self.link(old_child, new_child);
+                }
+            }
+        }
+    }
+
+    /// Places children of each new node in pre-order, then deletes unmatched old nodes from
+    /// the leaves. Nodes left behind by the placement are moved away or deleted later.
+    fn edits(&self) -> Vec<Edit<'a>> {
+        let mut edits = vec![];
+        let ids: Vec<_> = (0..self.new.nodes.len())
+            .map(|new| match self.new_to_old[new] {
+                Some(old) => self.old.nodes[old].node.id(),
+                None => self.new.nodes[new].node.id(),
+            })
+            .collect();
+        let mut parents: HashMap<NodeId, Option<NodeId>> = HashMap::new();
+        let mut operands: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for entry in &self.old.nodes {
+            parents.insert(
+                entry.node.id(),
+                entry.parent.map(|parent| self.old.nodes[parent].node.id()),
+            );
+            operands.insert(
+                entry.node.id(),
+                entry
+                    .children
+                    .iter()
+                    .map(|&child| self.old.nodes[child].node.id())
+                    .collect(),
+            );
+        }
+
+        let mut place = |new: usize, parent: Option<NodeId>, position: usize| {
+            let node_id = ids[new];
+            if let Some(parent) = parent {
+                if operands[&parent].get(position) == Some(&node_id) {
+                    return;
+                }
+            }
+            match self.new_to_old[new] {
+                Some(_) => {
+                    let from = parents[&node_id];
+                    if from.is_none() && parent.is_none() {
+                        return;
+                    }
+                    if let Some(from) = from {
+                        operands
+                            .get_mut(&from)
+                            .unwrap()
+                            .retain(|operand| *operand != node_id);
+                    }
+                    edits.push(Edit::Move {
+                        node_id,
+                        from,
+                        parent,
+                        position,
+                    });
+                }
+                None => {
+                    operands.insert(node_id, vec![]);
+                    edits.push(Edit::Insert {
+                        node_id,
+                        node: self.new.nodes[new].node,
+                        parent,
+                        position,
+                    });
+                }
+            }
+            if let Some(parent) = parent {
+                operands.get_mut(&parent).unwrap().insert(position, node_id);
+            }
+            parents.insert(node_id, parent);
+        };
+        place(0, None, 0);
+        for (new, entry) in self.new.nodes.iter().enumerate() {
+            for (position, &child) in entry.children.iter().enumerate() {
+                place(child, Some(ids[new]), position);
+            }
+        }
+
+        for (new, entry) in self.new.nodes.iter().enumerate() {
+            let node_id = ids[new];
+            let Some(old) = self.new_to_old[new] else {
+                if !entry.attributes.is_empty() {
+                    edits.push(Edit::UpdateAttributes {
+                        node_id,
+                        old: vec![],
+                        new: entry.attributes.clone(),
+                    });
+                }
+                continue;
+            };
+            if self.old.nodes[old].attributes != entry.attributes {
+                edits.push(Edit::UpdateAttributes {
+                    node_id,
+                    old: self.old.nodes[old].attributes.clone(),
+                    new: entry.attributes.clone(),
+                });
+            }
+            match (self.old.nodes[old].node, entry.node) {
+                (NodeRef::Expr(old), NodeRef::Expr(new)) => match (&old.value, &new.value) {
+                    (Expr::Literal(old), Expr::Literal(new)) if old != new => {
+                        edits.push(Edit::UpdateLiteral { node_id, old, new });
+                    }
+                    _ => {}
+                },
+                (old @ NodeRef::Type(_), NodeRef::Type(new))
+                    if !old.shallow_eq(&NodeRef::Type(new)) =>
+                {
+                    edits.push(Edit::UpdateType {
+                        node_id,
+                        ty: &new.value,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        for old in (0..self.old.nodes.len()).rev() {
+            if self.old_to_new[old].is_none() {
+                let node_id = self.old.nodes[old].node.id();
+                edits.push(Edit::Delete {
+                    node_id,
+                    parent: parents[&node_id],
+                });
+                if let Some(parent) = parents[&node_id] {
+                    operands
+                        .get_mut(&parent)
+                        .unwrap()
+                        .retain(|operand| *operand != node_id);
+                }
+            }
+        }
+        edits
+    }
+}
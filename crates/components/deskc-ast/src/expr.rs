@@ -1,9 +1,10 @@
+use deskc_derive::Visitor;
 use dson::Dson;
 use ids::CardId;
 pub use ids::LinkName;
 
 use crate::{
-    meta::WithMeta,
+    meta::{Meta, WithMeta},
     ty::{Effect, Type},
 };
 
@@ -19,13 +20,21 @@ pub enum Literal {
 // Literal::Real should not be NaN
 impl Eq for Literal {}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(visitor = ExprVisitor, visitor_mut = ExprVisitorMut, fold = ExprFold)]
 pub struct Handler {
     pub effect: WithMeta<Effect>,
     pub handler: WithMeta<Expr>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(
+    visitor = ExprVisitor,
+    visitor_mut = ExprVisitorMut,
+    fold = ExprFold,
+    nodes(Handler, MatchCase, MapElem, Effect),
+    leaves(Type)
+)]
 pub enum Expr {
     Literal(Literal),
     Do {
@@ -94,13 +103,15 @@ pub enum Expr {
     },
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(visitor = ExprVisitor, visitor_mut = ExprVisitorMut, fold = ExprFold)]
 pub struct MatchCase {
     pub ty: WithMeta<Type>,
     pub expr: WithMeta<Expr>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(visitor = ExprVisitor, visitor_mut = ExprVisitorMut, fold = ExprFold)]
 pub struct MapElem {
     pub key: WithMeta<Expr>,
     pub value: WithMeta<Expr>,
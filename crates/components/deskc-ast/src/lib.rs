@@ -1,3 +1,4 @@
+pub mod diff;
 pub mod dson;
 pub mod expr;
 pub mod meta;
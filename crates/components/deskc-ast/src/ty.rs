@@ -1,14 +1,26 @@
+use deskc_derive::Visitor;
 use dson::Dson;
 
-use crate::meta::WithMeta;
+use crate::{
+    expr::{ExprFold, ExprVisitor, ExprVisitorMut},
+    meta::{Meta, WithMeta},
+};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(visitor = TypeVisitor, visitor_mut = TypeVisitorMut, fold = TypeFold)]
+#[visitor(visitor = ExprVisitor, visitor_mut = ExprVisitorMut, fold = ExprFold)]
 pub struct Effect {
     pub input: WithMeta<Type>,
     pub output: WithMeta<Type>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(
+    visitor = TypeVisitor,
+    visitor_mut = TypeVisitorMut,
+    fold = TypeFold,
+    nodes(EffectExpr, Effect)
+)]
 pub enum Type {
     Labeled {
         brand: String,
@@ -25,7 +37,7 @@ pub enum Type {
     Infer,
     Product(Vec<WithMeta<Self>>),
     Sum(Vec<WithMeta<Self>>),
-    Function(Box<Function>),
+    Function(#[visitor(walk)] Box<Function>),
     Vector(Box<WithMeta<Self>>),
     Map {
         key: Box<WithMeta<Self>>,
@@ -53,13 +65,15 @@ pub enum Type {
     },
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(visitor = TypeVisitor, visitor_mut = TypeVisitorMut, fold = TypeFold)]
 pub struct Function {
     pub parameter: WithMeta<Type>,
     pub body: WithMeta<Type>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(visitor = TypeVisitor, visitor_mut = TypeVisitorMut, fold = TypeFold)]
 pub enum EffectExpr {
     Effects(Vec<WithMeta<Effect>>),
     Add(Vec<WithMeta<EffectExpr>>),
@@ -1,3 +1,4 @@
+use ast::parser::SpanStorage;
 use hir::meta::Meta;
 use thiserror::Error;
 use ty::Type;
@@ -18,43 +19,27 @@ pub enum GenMirError {
     FunctionInferredAsNonFunction { for_expr: hir::meta::Meta },
     #[error("effectful inferred as non-effectful {for_expr:?}")]
     EffectfulInferredAsNonEffectful { for_expr: hir::meta::Meta },
+    #[error("hole must be filled {for_expr:?}")]
+    Hole { for_expr: Meta },
 }
 
-impl From<&GenMirError> for TextualDiagnostics {
-    fn from(error: &GenMirError) -> TextualDiagnostics {
-        match error {
-            GenMirError::InvalidFunctionCall {
-                expr,
-                ty: _,
-                arguments: _,
-            } => TextualDiagnostics {
-                title: "MIR generation error".into(),
-                reports: vec![Report {
-                    span: todo!(),
-                    text: format!("{error}"),
-                }],
-            },
-            GenMirError::TypeNotFound { for_expr: meta } => TextualDiagnostics {
-                title: "MIR generation error".into(),
-                reports: vec![Report {
-                    span: todo!(),
-                    text: format!("{error}"),
-                }],
-            },
-            GenMirError::FunctionInferredAsNonFunction { for_expr: meta } => TextualDiagnostics {
-                title: "MIR generation error".into(),
-                reports: vec![Report {
-                    span: todo!(),
-                    text: format!("{error}"),
-                }],
-            },
-            GenMirError::EffectfulInferredAsNonEffectful { for_expr: meta } => TextualDiagnostics {
-                title: "MIR generation error".into(),
-                reports: vec![Report {
-                    span: todo!(),
-                    text: format!("{error}"),
-                }],
-            },
+impl GenMirError {
+    /// Reports the error at the span of its expression, or at the start if the syntax does not
+    /// store the span.
+    pub fn textual_diagnostics(&self, span_storage: &dyn SpanStorage) -> TextualDiagnostics {
+        let meta = match self {
+            GenMirError::InvalidFunctionCall { expr: meta, .. }
+            | GenMirError::TypeNotFound { for_expr: meta }
+            | GenMirError::FunctionInferredAsNonFunction { for_expr: meta }
+            | GenMirError::EffectfulInferredAsNonEffectful { for_expr: meta }
+            | GenMirError::Hole { for_expr: meta } => meta,
+        };
+        TextualDiagnostics {
+            title: "MIR generation error".into(),
+            reports: vec![Report {
+                span: span_storage.calculate_span(&meta.id).unwrap_or_default(),
+                text: format!("{self}"),
+            }],
         }
     }
 }
@@ -64,6 +64,8 @@ pub enum TypeError {
         sub_ty: Vec<TypeOrString>,
         super_ty: Vec<TypeOrString>,
     },
+    #[error("match does not cover {missing:?}")]
+    NonExhaustiveMatch { missing: Vec<TypeOrString> },
 }
 
 impl From<&ExprTypeError> for TextualDiagnostics {
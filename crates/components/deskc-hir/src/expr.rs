@@ -1,8 +1,8 @@
-use dson::Dson;
+use deskc_derive::Visitor;
 pub use ids::LinkName;
 
 use crate::{
-    meta::WithMeta,
+    meta::{Meta, WithMeta},
     ty::{Effect, Type},
 };
 
@@ -18,15 +18,25 @@ pub enum Literal {
 // Literal::Real should not be NaN
 impl Eq for Literal {}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(visitor = HirVisitor, visitor_mut = HirVisitorMut, fold = HirFold)]
 pub struct Handler {
+    #[visitor(walk)]
     pub effect: Effect,
     pub handler: WithMeta<Expr>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(
+    visitor = HirVisitor,
+    visitor_mut = HirVisitorMut,
+    fold = HirFold,
+    nodes(Handler, MatchCase, MapElem),
+    leaves(Type)
+)]
 pub enum Expr {
     Literal(Literal),
+    Hole,
     Do {
         stmt: Box<WithMeta<Self>>,
         expr: Box<WithMeta<Self>>,
@@ -77,13 +87,15 @@ pub enum Expr {
     },
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(visitor = HirVisitor, visitor_mut = HirVisitorMut, fold = HirFold)]
 pub struct MatchCase {
     pub ty: WithMeta<Type>,
     pub expr: WithMeta<Expr>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Visitor)]
+#[visitor(visitor = HirVisitor, visitor_mut = HirVisitorMut, fold = HirFold)]
 pub struct MapElem {
     pub key: WithMeta<Expr>,
     pub value: WithMeta<Expr>,
@@ -1,6 +1,9 @@
-use dson::Dson;
+use deskc_derive::Visitor;
 
-use crate::meta::WithMeta;
+use crate::{
+    expr::{HirFold, HirVisitor, HirVisitorMut},
+    meta::{Meta, WithMeta},
+};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Handler {
@@ -8,13 +11,21 @@ pub struct Handler {
     pub output: WithMeta<Type>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Visitor)]
+#[visitor(visitor = TypeVisitor, visitor_mut = TypeVisitorMut, fold = TypeFold)]
+#[visitor(visitor = HirVisitor, visitor_mut = HirVisitorMut, fold = HirFold)]
 pub struct Effect {
     pub input: WithMeta<Type>,
     pub output: WithMeta<Type>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Visitor)]
+#[visitor(
+    visitor = TypeVisitor,
+    visitor_mut = TypeVisitorMut,
+    fold = TypeFold,
+    nodes(EffectExpr, Effect)
+)]
 pub enum Type {
     Real,
     Rational,
@@ -27,7 +38,7 @@ pub enum Type {
     Infer,
     Product(Vec<WithMeta<Self>>),
     Sum(Vec<WithMeta<Self>>),
-    Function(Box<Function>),
+    Function(#[visitor(walk)] Box<Function>),
     Vector(Box<WithMeta<Self>>),
     Map {
         key: Box<WithMeta<Self>>,
@@ -61,13 +72,15 @@ pub enum Type {
     },
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Visitor)]
+#[visitor(visitor = TypeVisitor, visitor_mut = TypeVisitorMut, fold = TypeFold)]
 pub struct Function {
     pub parameter: WithMeta<Type>,
     pub body: WithMeta<Type>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Visitor)]
+#[visitor(visitor = TypeVisitor, visitor_mut = TypeVisitorMut, fold = TypeFold)]
 pub enum EffectExpr {
     Effects(Vec<WithMeta<Effect>>),
     Add(Vec<WithMeta<EffectExpr>>),
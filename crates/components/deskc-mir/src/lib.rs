@@ -2,4 +2,5 @@ pub mod block;
 pub mod mir;
 pub mod scope;
 pub mod stmt;
+pub mod text;
 pub mod var;
@@ -20,6 +20,8 @@ pub enum Stmt {
     Map(Vec<MapElem>),
     Fn(Closure),
     Perform(VarId),
+    /// The value of a match in its merge block.
+    /// Every arm assigns its value to the var before going to the merge block.
     MatchResult(VarId),
     Apply {
         function: VarId,
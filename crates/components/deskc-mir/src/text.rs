@@ -0,0 +1,273 @@
+//! A textual format of MIR for debugging.
+//!
+//! `Mir` implements `Display` to print the format and `FromStr` to parse it.
+mod parse;
+mod print;
+
+use thiserror::Error;
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("expected {expected} at {line}:{column}")]
+pub struct ParseError {
+    pub expected: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Same as the raw identifiers of the minimalist syntax.
+fn is_raw_ident_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '!'..='@' | '['..='`' | '{'..='~')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ids::{LinkId, LinkName};
+    use ty::{Effect, EffectExpr, Type};
+    use uuid::Uuid;
+
+    use crate::{
+        block::{BasicBlock, BlockId},
+        mir::{ControlFlowGraph, ControlFlowGraphId, Mir},
+        scope::{Scope, ScopeId},
+        stmt::{Closure, Const, MapElem, MatchCase, Stmt, StmtBind, Terminator},
+        var::{Var, VarId, Vars},
+    };
+
+    use super::*;
+
+    fn label(label: &str, item: Type) -> Type {
+        Type::Label {
+            label: label.into(),
+            item: Box::new(item),
+        }
+    }
+
+    fn vars(vars: Vec<(Type, usize)>) -> Vars {
+        Vars(
+            vars.into_iter()
+                .map(|(ty, scope)| Var {
+                    ty,
+                    scope: ScopeId(scope),
+                })
+                .collect(),
+        )
+    }
+
+    fn bind(var: usize, stmt: Stmt) -> StmtBind {
+        StmtBind {
+            var: VarId(var),
+            stmt,
+        }
+    }
+
+    fn mir() -> Mir {
+        let effect = Effect {
+            input: label("division by zero", Type::Integer),
+            output: Type::Integer,
+        };
+        let function = Type::Effectful {
+            ty: Box::new(Type::function(Type::Integer, Type::String)),
+            effects: EffectExpr::Effects(vec![effect.clone()]),
+        };
+        Mir {
+            entrypoint: ControlFlowGraphId(0),
+            cfgs: vec![
+                ControlFlowGraph {
+                    parameter: None,
+                    captured: vec![],
+                    output: Type::String,
+                    vars: vars(vec![
+                        (Type::Integer, 0),
+                        (function.clone(), 0),
+                        (Type::String, 0),
+                        (Type::Sum(vec![Type::Integer, Type::String]), 1),
+                        (Type::Real, 0),
+                    ]),
+                    scopes: vec![
+                        Scope { super_scope: None },
+                        Scope {
+                            super_scope: Some(ScopeId(0)),
+                        },
+                    ],
+                    blocks: vec![
+                        BasicBlock {
+                            stmts: vec![
+                                bind(0, Stmt::Const(Const::Rational(-1, 2))),
+                                bind(
+                                    1,
+                                    Stmt::Fn(Closure {
+                                        mir: ControlFlowGraphId(1),
+                                        captured: vec![VarId(0)],
+                                        handlers: HashMap::from([(effect, VarId(0))]),
+                                    }),
+                                ),
+                                bind(
+                                    2,
+                                    Stmt::Apply {
+                                        function: VarId(1),
+                                        arguments: vec![VarId(0)],
+                                    },
+                                ),
+                                bind(3, Stmt::Cast(VarId(2))),
+                            ],
+                            terminator: Terminator::Match {
+                                var: VarId(3),
+                                cases: vec![
+                                    MatchCase {
+                                        ty: Type::Integer,
+                                        next: BlockId(1),
+                                    },
+                                    MatchCase {
+                                        ty: Type::String,
+                                        next: BlockId(1),
+                                    },
+                                ],
+                            },
+                        },
+                        BasicBlock {
+                            stmts: vec![bind(2, Stmt::Const(Const::String("\"a\"\n".into())))],
+                            terminator: Terminator::Return(VarId(2)),
+                        },
+                    ],
+                    links: vec![],
+                },
+                ControlFlowGraph {
+                    parameter: Some(Type::Integer),
+                    captured: vec![Type::Integer],
+                    output: Type::String,
+                    vars: vars(vec![
+                        (Type::Integer, 0),
+                        (Type::Vector(Box::new(Type::Integer)), 0),
+                        (
+                            Type::Map {
+                                key: Box::new(Type::Integer),
+                                value: Box::new(Type::Integer),
+                            },
+                            0,
+                        ),
+                        (Type::String, 0),
+                    ]),
+                    scopes: vec![Scope { super_scope: None }],
+                    blocks: vec![BasicBlock {
+                        stmts: vec![
+                            bind(0, Stmt::Parameter),
+                            bind(1, Stmt::Vector(vec![VarId(0), VarId(0)])),
+                            bind(
+                                2,
+                                Stmt::Map(vec![MapElem {
+                                    key: VarId(0),
+                                    value: VarId(0),
+                                }]),
+                            ),
+                            bind(3, Stmt::Link(LinkName::Card(Uuid::from_u128(1)))),
+                        ],
+                        terminator: Terminator::Return(VarId(3)),
+                    }],
+                    links: vec![LinkId {
+                        ty: Type::String,
+                        name: LinkName::Card(Uuid::from_u128(1)),
+                    }],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn prints_mir() {
+        assert_eq!(
+            mir().to_string(),
+            r#"entrypoint cfg0
+
+cfg0 {
+  output 'string
+  scope0
+  scope1 in scope0
+  %4: 'real
+  bb0 {
+    %0: 'integer = const -1/2
+    %1: ! { @`division by zero` 'integer ~> 'integer } \ 'integer -> 'string = fn cfg1(%0) handle { @`division by zero` 'integer ~> 'integer => %0 }
+    %2: 'string = apply %1(%0)
+    %3 in scope1: +<'integer, 'string> = cast %2
+    match %3 { 'integer => bb1, 'string => bb1 }
+  }
+  bb1 {
+    %2: 'string = const "\"a\"\n"
+    return %2
+  }
+}
+
+cfg1 {
+  parameter 'integer
+  captured 'integer
+  output 'string
+  scope0
+  link 'card 00000000-0000-0000-0000-000000000001 'string
+  bb0 {
+    %0: 'integer = parameter
+    %1: ['integer] = vector(%0, %0)
+    %2: {'integer => 'integer} = map(%0 => %0)
+    %3: 'string = link 'card 00000000-0000-0000-0000-000000000001
+    return %3
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn parses_printed_mir() {
+        assert_eq!(mir().to_string().parse::<Mir>(), Ok(mir()));
+    }
+
+    #[test]
+    fn parses_types() {
+        let ty = Type::ForAll {
+            variable: "a".into(),
+            bound: Some(Box::new(Type::Brand {
+                brand: "nat".into(),
+                item: Box::new(Type::Integer),
+            })),
+            body: Box::new(Type::Effectful {
+                ty: Box::new(Type::Variable("a".into())),
+                effects: EffectExpr::Sub {
+                    minuend: Box::new(EffectExpr::Add(vec![EffectExpr::Apply {
+                        function: Box::new(Type::Variable("e".into())),
+                        arguments: vec![Type::Product(vec![]), Type::Rational],
+                    }])),
+                    subtrahend: Box::new(EffectExpr::Effects(vec![])),
+                },
+            }),
+        };
+        let mut mir = mir();
+        mir.cfgs[0].vars.0[4].ty = ty;
+        assert_eq!(
+            mir.to_string().lines().nth(6),
+            Some("  %4: 'forall a: 'brand nat 'integer, ! -<+<^e(*<>, 'rational)>, {}> a")
+        );
+        assert_eq!(mir.to_string().parse::<Mir>(), Ok(mir));
+    }
+
+    #[test]
+    fn reports_position_of_error() {
+        let text = "entrypoint cfg0\ncfg0 {\n  output 'integer\n  scope0\n  bb0 {\n    %0: 'integer = const x\n    return %0\n  }\n}\n";
+        assert_eq!(
+            text.parse::<Mir>(),
+            Err(ParseError {
+                expected: "number".into(),
+                line: 6,
+                column: 26,
+            })
+        );
+    }
+
+    #[test]
+    fn requires_declarations_of_all_vars() {
+        let text = "entrypoint cfg0\ncfg0 {\n  output 'integer\n  scope0\n  bb0 {\n    %1: 'integer = const 1\n    return %1\n  }\n}\n";
+        assert_eq!(
+            text.parse::<Mir>().unwrap_err().expected,
+            "declaration of %0"
+        );
+    }
+}
@@ -0,0 +1,555 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ids::{LinkId, LinkName};
+use ty::{Effect, EffectExpr, Function, Type};
+use uuid::Uuid;
+
+use crate::{
+    block::{BasicBlock, BlockId},
+    mir::{ControlFlowGraph, ControlFlowGraphId, Mir},
+    scope::{Scope, ScopeId},
+    stmt::{Closure, Const, MapElem, MatchCase, Stmt, StmtBind, Terminator},
+    var::{Var, VarId, Vars},
+};
+
+use super::{is_raw_ident_char, ParseError};
+
+impl FromStr for Mir {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { text, offset: 0 };
+        let mir = parser.mir()?;
+        parser.skip_whitespace();
+        if parser.offset != text.len() {
+            return Err(parser.error("end of text"));
+        }
+        Ok(mir)
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn mir(&mut self) -> Result<Mir, ParseError> {
+        self.expect("entrypoint")?;
+        let entrypoint = ControlFlowGraphId(self.id("cfg")?);
+        let mut cfgs = vec![];
+        while !self.at_end() {
+            self.expect_id("cfg", cfgs.len())?;
+            cfgs.push(self.cfg()?);
+        }
+        Ok(Mir { entrypoint, cfgs })
+    }
+
+    fn cfg(&mut self) -> Result<ControlFlowGraph, ParseError> {
+        self.expect("{")?;
+        let parameter = if self.eat_keyword("parameter") {
+            Some(self.ty()?)
+        } else {
+            None
+        };
+        let captured = if self.eat_keyword("captured") {
+            self.list(Self::ty)?
+        } else {
+            vec![]
+        };
+        self.expect("output")?;
+        let output = self.ty()?;
+        let mut scopes = vec![];
+        while self.peek("scope") {
+            self.expect_id("scope", scopes.len())?;
+            let super_scope = if self.eat_keyword("in") {
+                Some(ScopeId(self.id("scope")?))
+            } else {
+                None
+            };
+            scopes.push(Scope { super_scope });
+        }
+        let mut links = vec![];
+        while self.eat_keyword("link") {
+            let name = self.link_name()?;
+            let ty = self.ty()?;
+            links.push(LinkId { ty, name });
+        }
+        let mut vars = HashMap::new();
+        while self.peek("%") {
+            self.var_declaration(&mut vars)?;
+        }
+        let mut blocks = vec![];
+        while !self.eat("}") {
+            self.expect_id("bb", blocks.len())?;
+            self.expect("{")?;
+            let mut stmts = vec![];
+            while self.peek("%") {
+                let var = self.var_declaration(&mut vars)?;
+                self.expect("=")?;
+                let stmt = self.stmt()?;
+                stmts.push(StmtBind { var, stmt });
+            }
+            let terminator = self.terminator()?;
+            self.expect("}")?;
+            blocks.push(BasicBlock { stmts, terminator });
+        }
+        let vars = (0..vars.len())
+            .map(|id| {
+                vars.remove(&VarId(id))
+                    .ok_or_else(|| self.error(&format!("declaration of %{id}")))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ControlFlowGraph {
+            parameter,
+            captured,
+            output,
+            vars: Vars(vars),
+            scopes,
+            blocks,
+            links,
+        })
+    }
+
+    /// Vars assigned in several blocks are declared with the same type each time.
+    fn var_declaration(&mut self, vars: &mut HashMap<VarId, Var>) -> Result<VarId, ParseError> {
+        let var = self.var()?;
+        let scope = if self.eat_keyword("in") {
+            ScopeId(self.id("scope")?)
+        } else {
+            ScopeId(0)
+        };
+        self.expect(":")?;
+        let offset = self.offset;
+        let declaration = Var {
+            ty: self.ty()?,
+            scope,
+        };
+        match vars.get(&var) {
+            Some(declared) if *declared != declaration => {
+                self.offset = offset;
+                Err(self.error(&format!("the same declaration as the other %{}", var.0)))
+            }
+            _ => {
+                vars.insert(var, declaration);
+                Ok(var)
+            }
+        }
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, ParseError> {
+        let stmt = if self.eat_keyword("const") {
+            Stmt::Const(self.const_()?)
+        } else if self.eat_keyword("product") {
+            Stmt::Product(self.parenthesized(Self::var)?)
+        } else if self.eat_keyword("vector") {
+            Stmt::Vector(self.parenthesized(Self::var)?)
+        } else if self.eat_keyword("map") {
+            Stmt::Map(self.parenthesized(|parser| {
+                let key = parser.var()?;
+                parser.expect("=>")?;
+                let value = parser.var()?;
+                Ok(MapElem { key, value })
+            })?)
+        } else if self.eat_keyword("fn") {
+            let mir = ControlFlowGraphId(self.id("cfg")?);
+            let captured = self.parenthesized(Self::var)?;
+            let mut handlers = HashMap::new();
+            if self.eat_keyword("handle") {
+                self.expect("{")?;
+                for (effect, handler) in self.list(|parser| {
+                    let effect = parser.effect()?;
+                    parser.expect("=>")?;
+                    Ok((effect, parser.var()?))
+                })? {
+                    handlers.insert(effect, handler);
+                }
+                self.expect("}")?;
+            }
+            Stmt::Fn(Closure {
+                mir,
+                captured,
+                handlers,
+            })
+        } else if self.eat_keyword("perform") {
+            Stmt::Perform(self.var()?)
+        } else if self.eat_keyword("match_result") {
+            Stmt::MatchResult(self.var()?)
+        } else if self.eat_keyword("apply") {
+            let function = self.var()?;
+            let arguments = self.parenthesized(Self::var)?;
+            Stmt::Apply {
+                function,
+                arguments,
+            }
+        } else if self.eat_keyword("cast") {
+            Stmt::Cast(self.var()?)
+        } else if self.eat_keyword("parameter") {
+            Stmt::Parameter
+        } else if self.eat_keyword("recursion") {
+            Stmt::Recursion
+        } else if self.eat_keyword("link") {
+            Stmt::Link(self.link_name()?)
+        } else {
+            return Err(self.error("statement"));
+        };
+        Ok(stmt)
+    }
+
+    fn const_(&mut self) -> Result<Const, ParseError> {
+        self.skip_whitespace();
+        if self.rest().starts_with('"') {
+            return Ok(Const::String(self.string()?));
+        }
+        let start = self.offset;
+        let literal = self.take_while(|c| c.is_ascii_alphanumeric() || "+-./".contains(c));
+        let value = if let Some((a, b)) = literal.split_once('/') {
+            a.parse()
+                .ok()
+                .zip(b.parse().ok())
+                .map(|(a, b)| Const::Rational(a, b))
+        } else if literal.contains(['.', 'e', 'E', 'i', 'N']) {
+            literal.parse().ok().map(Const::Real)
+        } else {
+            literal.parse().ok().map(Const::Int)
+        };
+        value.ok_or_else(|| {
+            self.offset = start;
+            self.error("number")
+        })
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect("\"")?;
+        let mut string = String::new();
+        loop {
+            let Some(c) = self.next_char() else {
+                return Err(self.error("closing quote"));
+            };
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escaped = match self.next_char() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('0') => '\0',
+                        Some(c @ ('\\' | '"' | '\'')) => c,
+                        Some('u') => {
+                            self.expect("{")?;
+                            let code = self.take_while(|c| c.is_ascii_hexdigit());
+                            let c = u32::from_str_radix(code, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("unicode scalar value"))?;
+                            self.expect("}")?;
+                            c
+                        }
+                        _ => return Err(self.error("escape sequence")),
+                    };
+                    string.push(escaped);
+                }
+                c => string.push(c),
+            }
+        }
+    }
+
+    fn terminator(&mut self) -> Result<Terminator, ParseError> {
+        if self.eat_keyword("return") {
+            Ok(Terminator::Return(self.var()?))
+        } else if self.eat_keyword("goto") {
+            Ok(Terminator::Goto(BlockId(self.id("bb")?)))
+        } else if self.eat_keyword("match") {
+            let var = self.var()?;
+            self.expect("{")?;
+            let cases = self.list(|parser| {
+                let ty = parser.ty()?;
+                parser.expect("=>")?;
+                let next = BlockId(parser.id("bb")?);
+                Ok(MatchCase { ty, next })
+            })?;
+            self.expect("}")?;
+            Ok(Terminator::Match { var, cases })
+        } else {
+            Err(self.error("statement or terminator"))
+        }
+    }
+
+    fn link_name(&mut self) -> Result<LinkName, ParseError> {
+        if self.eat_keyword("none") {
+            Ok(LinkName::None)
+        } else if self.eat_keyword("'card") {
+            Ok(LinkName::Card(self.uuid()?))
+        } else if self.eat_keyword("'version") {
+            Ok(LinkName::Version(self.uuid()?))
+        } else {
+            Err(self.error("link name"))
+        }
+    }
+
+    fn uuid(&mut self) -> Result<Uuid, ParseError> {
+        self.skip_whitespace();
+        let start = self.offset;
+        let uuid = self.take_while(|c| c.is_ascii_hexdigit() || c == '-');
+        Uuid::parse_str(uuid).map_err(|_| {
+            self.offset = start;
+            self.error("uuid")
+        })
+    }
+
+    fn ty(&mut self) -> Result<Type, ParseError> {
+        let ty = if self.eat_keyword("'real") {
+            Type::Real
+        } else if self.eat_keyword("'rational") {
+            Type::Rational
+        } else if self.eat_keyword("'integer") {
+            Type::Integer
+        } else if self.eat_keyword("'string") {
+            Type::String
+        } else if self.eat("*<") {
+            let types = self.list(Self::ty)?;
+            self.expect(">")?;
+            Type::Product(types)
+        } else if self.eat("+<") {
+            let types = self.list(Self::ty)?;
+            self.expect(">")?;
+            Type::Sum(types)
+        } else if self.eat("\\") {
+            let parameter = self.ty()?;
+            self.expect("->")?;
+            let body = self.ty()?;
+            Type::Function(Box::new(Function { parameter, body }))
+        } else if self.eat("[") {
+            let item = self.ty()?;
+            self.expect("]")?;
+            Type::Vector(Box::new(item))
+        } else if self.eat("{") {
+            let key = self.ty()?;
+            self.expect("=>")?;
+            let value = self.ty()?;
+            self.expect("}")?;
+            Type::Map {
+                key: Box::new(key),
+                value: Box::new(value),
+            }
+        } else if self.eat_keyword("'forall") {
+            let variable = self.ident()?;
+            let bound = if self.eat(":") {
+                Some(Box::new(self.ty()?))
+            } else {
+                None
+            };
+            self.expect(",")?;
+            Type::ForAll {
+                variable,
+                bound,
+                body: Box::new(self.ty()?),
+            }
+        } else if self.eat("!") {
+            let effects = self.effect_expr()?;
+            Type::Effectful {
+                ty: Box::new(self.ty()?),
+                effects,
+            }
+        } else if self.eat_keyword("'brand") {
+            let brand = self.ident()?;
+            Type::Brand {
+                brand,
+                item: Box::new(self.ty()?),
+            }
+        } else if self.eat("@") {
+            let label = self.ident()?;
+            Type::Label {
+                label,
+                item: Box::new(self.ty()?),
+            }
+        } else {
+            Type::Variable(self.ident().map_err(|_| self.error("type"))?)
+        };
+        Ok(ty)
+    }
+
+    fn effect_expr(&mut self) -> Result<EffectExpr, ParseError> {
+        if self.eat("{") {
+            let effects = self.list(Self::effect)?;
+            self.expect("}")?;
+            Ok(EffectExpr::Effects(effects))
+        } else if self.eat("+<") {
+            let exprs = self.list(Self::effect_expr)?;
+            self.expect(">")?;
+            Ok(EffectExpr::Add(exprs))
+        } else if self.eat("-<") {
+            let minuend = self.effect_expr()?;
+            self.expect(",")?;
+            let subtrahend = self.effect_expr()?;
+            self.expect(">")?;
+            Ok(EffectExpr::Sub {
+                minuend: Box::new(minuend),
+                subtrahend: Box::new(subtrahend),
+            })
+        } else if self.eat("^") {
+            let function = self.ty()?;
+            let arguments = self.parenthesized(Self::ty)?;
+            Ok(EffectExpr::Apply {
+                function: Box::new(function),
+                arguments,
+            })
+        } else {
+            Err(self.error("effects"))
+        }
+    }
+
+    fn effect(&mut self) -> Result<Effect, ParseError> {
+        let input = self.ty()?;
+        self.expect("~>")?;
+        let output = self.ty()?;
+        Ok(Effect { input, output })
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        self.skip_whitespace();
+        if !self.eat("`") {
+            let ident = self.take_while(is_raw_ident_char);
+            if ident.is_empty() {
+                return Err(self.error("identifier"));
+            }
+            return Ok(ident.into());
+        }
+        let mut ident = String::new();
+        loop {
+            match self.next_char() {
+                Some('`') => return Ok(ident),
+                Some('\\') => match self.next_char() {
+                    Some(c @ ('`' | '\\')) => ident.push(c),
+                    _ => return Err(self.error("escaped backquote or backslash")),
+                },
+                Some(c) => ident.push(c),
+                None => return Err(self.error("closing backquote")),
+            }
+        }
+    }
+
+    fn var(&mut self) -> Result<VarId, ParseError> {
+        Ok(VarId(self.id("%")?))
+    }
+
+    /// An index with a prefix such as `bb0`.
+    fn id(&mut self, prefix: &str) -> Result<usize, ParseError> {
+        self.expect(prefix)?;
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        digits
+            .parse()
+            .map_err(|_| self.error(&format!("index of {prefix}")))
+    }
+
+    /// Definitions are numbered in order.
+    fn expect_id(&mut self, prefix: &str, id: usize) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        let start = self.offset;
+        if self.id(prefix).ok() != Some(id) {
+            self.offset = start;
+            return Err(self.error(&format!("{prefix}{id}")));
+        }
+        Ok(())
+    }
+
+    fn parenthesized<T>(
+        &mut self,
+        item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        self.expect("(")?;
+        let items = self.list(item)?;
+        self.expect(")")?;
+        Ok(items)
+    }
+
+    /// Comma separated items until a closing bracket.
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.rest().starts_with([')', '>', '}', ']']) {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if !self.eat(",") {
+                return Ok(items);
+            }
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.offset..]
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.rest().is_empty()
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.offset += len;
+        &rest[..len]
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.rest().chars().next()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn peek(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        self.rest().starts_with(token)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.peek(token) {
+            self.offset += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Unlike `eat`, does not match a prefix of a longer word.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if !self.peek(keyword) {
+            return false;
+        }
+        let next = self.rest()[keyword.len()..].chars().next();
+        if next.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            return false;
+        }
+        self.offset += keyword.len();
+        true
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{token}`")))
+        }
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let before = &self.text[..self.offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        ParseError {
+            expected: expected.into(),
+            line,
+            column,
+        }
+    }
+}
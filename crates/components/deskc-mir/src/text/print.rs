@@ -0,0 +1,326 @@
+use std::fmt::{Display, Formatter, Result};
+
+use ids::LinkName;
+use ty::{Effect, EffectExpr, Type};
+
+use crate::{
+    mir::{ControlFlowGraph, Mir},
+    stmt::{Const, Stmt, Terminator},
+    var::VarId,
+};
+
+impl Display for Mir {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "entrypoint cfg{}", self.entrypoint.0)?;
+        for (id, cfg) in self.cfgs.iter().enumerate() {
+            writeln!(f)?;
+            write_cfg(f, id, cfg)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_cfg(f: &mut Formatter<'_>, id: usize, cfg: &ControlFlowGraph) -> Result {
+    writeln!(f, "cfg{id} {{")?;
+    if let Some(parameter) = &cfg.parameter {
+        writeln!(f, "  parameter {}", DisplayType(parameter))?;
+    }
+    if !cfg.captured.is_empty() {
+        write!(f, "  captured ")?;
+        write_list(f, &cfg.captured, write_type)?;
+        writeln!(f)?;
+    }
+    writeln!(f, "  output {}", DisplayType(&cfg.output))?;
+    for (id, scope) in cfg.scopes.iter().enumerate() {
+        write!(f, "  scope{id}")?;
+        if let Some(super_scope) = scope.super_scope {
+            write!(f, " in scope{}", super_scope.0)?;
+        }
+        writeln!(f)?;
+    }
+    for link in &cfg.links {
+        writeln!(
+            f,
+            "  link {} {}",
+            DisplayLinkName(&link.name),
+            DisplayType(&link.ty)
+        )?;
+    }
+    // Vars without assignments are declared in the header.
+    let mut bound = vec![false; cfg.vars.0.len()];
+    for bind in cfg.blocks.iter().flat_map(|block| &block.stmts) {
+        bound[bind.var.0] = true;
+    }
+    for (id, _) in bound.iter().enumerate().filter(|(_, bound)| !**bound) {
+        write!(f, "  ")?;
+        write_var_declaration(f, cfg, &VarId(id))?;
+        writeln!(f)?;
+    }
+    for (id, block) in cfg.blocks.iter().enumerate() {
+        writeln!(f, "  bb{id} {{")?;
+        for bind in &block.stmts {
+            write!(f, "    ")?;
+            write_var_declaration(f, cfg, &bind.var)?;
+            write!(f, " = ")?;
+            write_stmt(f, &bind.stmt)?;
+            writeln!(f)?;
+        }
+        write!(f, "    ")?;
+        write_terminator(f, &block.terminator)?;
+        writeln!(f)?;
+        writeln!(f, "  }}")?;
+    }
+    writeln!(f, "}}")
+}
+
+fn write_var_declaration(f: &mut Formatter<'_>, cfg: &ControlFlowGraph, var: &VarId) -> Result {
+    let declaration = cfg.vars.get(var);
+    write!(f, "%{}", var.0)?;
+    if declaration.scope.0 != 0 {
+        write!(f, " in scope{}", declaration.scope.0)?;
+    }
+    write!(f, ": {}", DisplayType(&declaration.ty))
+}
+
+fn write_stmt(f: &mut Formatter<'_>, stmt: &Stmt) -> Result {
+    match stmt {
+        Stmt::Const(value) => {
+            write!(f, "const ")?;
+            match value {
+                Const::Int(value) => write!(f, "{value}"),
+                Const::Rational(a, b) => write!(f, "{a}/{b}"),
+                Const::Real(value) => write!(f, "{value:?}"),
+                Const::String(value) => write!(f, "{value:?}"),
+            }
+        }
+        Stmt::Product(vars) => {
+            write!(f, "product(")?;
+            write_list(f, vars, write_var)?;
+            write!(f, ")")
+        }
+        Stmt::Vector(vars) => {
+            write!(f, "vector(")?;
+            write_list(f, vars, write_var)?;
+            write!(f, ")")
+        }
+        Stmt::Map(elems) => {
+            write!(f, "map(")?;
+            write_list(f, elems, |f, elem| {
+                write!(f, "%{} => %{}", elem.key.0, elem.value.0)
+            })?;
+            write!(f, ")")
+        }
+        Stmt::Fn(closure) => {
+            write!(f, "fn cfg{}(", closure.mir.0)?;
+            write_list(f, &closure.captured, write_var)?;
+            write!(f, ")")?;
+            if !closure.handlers.is_empty() {
+                // Sorted to print the same text for the same closure.
+                let mut handlers: Vec<_> = closure.handlers.iter().collect();
+                handlers.sort_by_key(|(effect, _)| *effect);
+                write!(f, " handle {{ ")?;
+                write_list(f, &handlers, |f, (effect, handler)| {
+                    write_effect(f, effect)?;
+                    write!(f, " => %{}", handler.0)
+                })?;
+                write!(f, " }}")?;
+            }
+            Ok(())
+        }
+        Stmt::Perform(var) => write!(f, "perform %{}", var.0),
+        Stmt::MatchResult(var) => write!(f, "match_result %{}", var.0),
+        Stmt::Apply {
+            function,
+            arguments,
+        } => {
+            write!(f, "apply %{}(", function.0)?;
+            write_list(f, arguments, write_var)?;
+            write!(f, ")")
+        }
+        Stmt::Cast(var) => write!(f, "cast %{}", var.0),
+        Stmt::Parameter => write!(f, "parameter"),
+        Stmt::Recursion => write!(f, "recursion"),
+        Stmt::Link(name) => write!(f, "link {}", DisplayLinkName(name)),
+    }
+}
+
+fn write_terminator(f: &mut Formatter<'_>, terminator: &Terminator) -> Result {
+    match terminator {
+        Terminator::Return(var) => write!(f, "return %{}", var.0),
+        Terminator::Match { var, cases } => {
+            write!(f, "match %{} {{ ", var.0)?;
+            write_list(f, cases, |f, case| {
+                write!(f, "{} => bb{}", DisplayType(&case.ty), case.next.0)
+            })?;
+            write!(f, " }}")
+        }
+        Terminator::Goto(next) => write!(f, "goto bb{}", next.0),
+    }
+}
+
+fn write_var(f: &mut Formatter<'_>, var: &VarId) -> Result {
+    write!(f, "%{}", var.0)
+}
+
+fn write_list<T>(
+    f: &mut Formatter<'_>,
+    items: &[T],
+    mut write_item: impl FnMut(&mut Formatter<'_>, &T) -> Result,
+) -> Result {
+    for (i, item) in items.iter().enumerate() {
+        if i != 0 {
+            write!(f, ", ")?;
+        }
+        write_item(f, item)?;
+    }
+    Ok(())
+}
+
+struct DisplayType<'a>(&'a Type);
+
+impl Display for DisplayType<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write_type(f, self.0)
+    }
+}
+
+struct DisplayLinkName<'a>(&'a LinkName);
+
+impl Display for DisplayLinkName<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0 {
+            LinkName::None => write!(f, "none"),
+            LinkName::Version(uuid) => write!(f, "'version {uuid}"),
+            LinkName::Card(uuid) => write!(f, "'card {uuid}"),
+        }
+    }
+}
+
+/// Writes a type in the minimalist syntax, except that brands are written as `'brand name ty`.
+fn write_type(f: &mut Formatter<'_>, ty: &Type) -> Result {
+    match ty {
+        Type::Real => write!(f, "'real"),
+        Type::Rational => write!(f, "'rational"),
+        Type::Integer => write!(f, "'integer"),
+        Type::String => write!(f, "'string"),
+        Type::Product(types) => {
+            write!(f, "*<")?;
+            write_list(f, types, write_type)?;
+            write!(f, ">")
+        }
+        Type::Sum(types) => {
+            write!(f, "+<")?;
+            write_list(f, types, write_type)?;
+            write!(f, ">")
+        }
+        Type::Function(function) => {
+            write!(f, "\\ ")?;
+            write_type(f, &function.parameter)?;
+            write!(f, " -> ")?;
+            write_type(f, &function.body)
+        }
+        Type::Vector(item) => {
+            write!(f, "[")?;
+            write_type(f, item)?;
+            write!(f, "]")
+        }
+        Type::Map { key, value } => {
+            write!(f, "{{")?;
+            write_type(f, key)?;
+            write!(f, " => ")?;
+            write_type(f, value)?;
+            write!(f, "}}")
+        }
+        Type::Variable(ident) => write_ident(f, ident),
+        Type::ForAll {
+            variable,
+            bound,
+            body,
+        } => {
+            write!(f, "'forall ")?;
+            write_ident(f, variable)?;
+            if let Some(bound) = bound {
+                write!(f, ": ")?;
+                write_type(f, bound)?;
+            }
+            write!(f, ", ")?;
+            write_type(f, body)
+        }
+        Type::Effectful { ty, effects } => {
+            write!(f, "! ")?;
+            write_effect_expr(f, effects)?;
+            write!(f, " ")?;
+            write_type(f, ty)
+        }
+        Type::Brand { brand, item } => {
+            write!(f, "'brand ")?;
+            write_ident(f, brand)?;
+            write!(f, " ")?;
+            write_type(f, item)
+        }
+        Type::Label { label, item } => {
+            write!(f, "@")?;
+            write_ident(f, label)?;
+            write!(f, " ")?;
+            write_type(f, item)
+        }
+    }
+}
+
+fn write_effect_expr(f: &mut Formatter<'_>, effects: &EffectExpr) -> Result {
+    match effects {
+        EffectExpr::Effects(effects) if effects.is_empty() => write!(f, "{{}}"),
+        EffectExpr::Effects(effects) => {
+            write!(f, "{{ ")?;
+            write_list(f, effects, write_effect)?;
+            write!(f, " }}")
+        }
+        EffectExpr::Add(exprs) => {
+            write!(f, "+<")?;
+            write_list(f, exprs, write_effect_expr)?;
+            write!(f, ">")
+        }
+        EffectExpr::Sub {
+            minuend,
+            subtrahend,
+        } => {
+            write!(f, "-<")?;
+            write_effect_expr(f, minuend)?;
+            write!(f, ", ")?;
+            write_effect_expr(f, subtrahend)?;
+            write!(f, ">")
+        }
+        EffectExpr::Apply {
+            function,
+            arguments,
+        } => {
+            write!(f, "^")?;
+            write_type(f, function)?;
+            write!(f, "(")?;
+            write_list(f, arguments, write_type)?;
+            write!(f, ")")
+        }
+    }
+}
+
+fn write_effect(f: &mut Formatter<'_>, effect: &Effect) -> Result {
+    write_type(f, &effect.input)?;
+    write!(f, " ~> ")?;
+    write_type(f, &effect.output)
+}
+
+/// Identifiers that are not raw identifiers of the minimalist syntax are wrapped in backquotes.
+fn write_ident(f: &mut Formatter<'_>, ident: &str) -> Result {
+    if !ident.is_empty() && ident.chars().all(super::is_raw_ident_char) {
+        write!(f, "{ident}")
+    } else {
+        write!(f, "`")?;
+        for c in ident.chars() {
+            if c == '`' || c == '\\' {
+                write!(f, "\\")?;
+            }
+            write!(f, "{c}")?;
+        }
+        write!(f, "`")
+    }
+}
@@ -0,0 +1,143 @@
+use dson::canonical::{encode_seq, encode_set, encode_str, CanonicalEncode};
+
+use crate::{Effect, EffectExpr, Type};
+
+/// Tags do not overlap with the ones of DSON.
+impl CanonicalEncode for Type {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Type::Real => buf.push(0x40),
+            Type::Rational => buf.push(0x41),
+            Type::Integer => buf.push(0x42),
+            Type::String => buf.push(0x43),
+            Type::Product(types) => {
+                buf.push(0x44);
+                encode_set(types, buf);
+            }
+            Type::Sum(types) => {
+                buf.push(0x45);
+                encode_set(types, buf);
+            }
+            Type::Function(function) => {
+                buf.push(0x46);
+                function.parameter.encode(buf);
+                function.body.encode(buf);
+            }
+            Type::Vector(item) => {
+                buf.push(0x47);
+                item.encode(buf);
+            }
+            Type::Map { key, value } => {
+                buf.push(0x48);
+                key.encode(buf);
+                value.encode(buf);
+            }
+            Type::Variable(variable) => {
+                buf.push(0x49);
+                encode_str(variable, buf);
+            }
+            Type::ForAll {
+                variable,
+                bound,
+                body,
+            } => {
+                buf.push(0x4a);
+                encode_str(variable, buf);
+                match bound {
+                    Some(bound) => {
+                        buf.push(0x01);
+                        bound.encode(buf);
+                    }
+                    None => buf.push(0x00),
+                }
+                body.encode(buf);
+            }
+            Type::Effectful { ty, effects } => {
+                buf.push(0x4b);
+                ty.encode(buf);
+                effects.encode(buf);
+            }
+            Type::Brand { brand, item } => {
+                buf.push(0x4c);
+                encode_str(brand, buf);
+                item.encode(buf);
+            }
+            Type::Label { label, item } => {
+                buf.push(0x4d);
+                encode_str(label, buf);
+                item.encode(buf);
+            }
+        }
+    }
+}
+
+impl CanonicalEncode for Effect {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(0x50);
+        self.input.encode(buf);
+        self.output.encode(buf);
+    }
+}
+
+impl CanonicalEncode for EffectExpr {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            EffectExpr::Effects(effects) => {
+                buf.push(0x58);
+                encode_set(effects, buf);
+            }
+            EffectExpr::Add(exprs) => {
+                buf.push(0x59);
+                encode_set(exprs, buf);
+            }
+            EffectExpr::Sub {
+                minuend,
+                subtrahend,
+            } => {
+                buf.push(0x5a);
+                minuend.encode(buf);
+                subtrahend.encode(buf);
+            }
+            EffectExpr::Apply {
+                function,
+                arguments,
+            } => {
+                buf.push(0x5b);
+                function.encode(buf);
+                encode_seq(arguments, buf);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_members() {
+        let a = Type::Product(vec![Type::Integer, Type::String]);
+        let b = Type::Product(vec![Type::String, Type::Integer]);
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        let effect = |input: Type| Effect {
+            input,
+            output: Type::Integer,
+        };
+        let a = EffectExpr::Effects(vec![effect(Type::Real), effect(Type::String)]);
+        let b = EffectExpr::Effects(vec![effect(Type::String), effect(Type::Real)]);
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        let a = Type::function(Type::Integer, Type::String);
+        let b = Type::function(Type::String, Type::Integer);
+        assert_ne!(a.content_hash(), b.content_hash());
+    }
+
+    #[test]
+    fn differs_from_dson_types() {
+        assert_ne!(
+            Type::Integer.canonical_bytes(),
+            dson::Type::Integer.canonical_bytes()
+        );
+    }
+}
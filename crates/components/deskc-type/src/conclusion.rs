@@ -8,6 +8,27 @@ use crate::Type;
 pub struct TypeConclusions {
     pub types: HashMap<NodeId, Type>,
     pub cast_strategies: HashMap<TypeToType, CastStrategy>,
+    pub holes: HashMap<NodeId, HoleConclusion>,
+    pub warnings: Vec<TypeWarning>,
+}
+
+/// What a typed hole expects and what can fill it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HoleConclusion {
+    /// `None` if the expected type is not inferred.
+    pub expected: Option<Type>,
+    /// References in scope whose types are subtypes of the expected type, most similar first.
+    pub references: Vec<Type>,
+    /// Functions in scope whose outputs are subtypes of the expected type, most similar first.
+    pub functions: Vec<Type>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeWarning {
+    /// No variant of the matched value reaches the case.
+    UnreachableCase { case_id: NodeId },
+    /// The case has the same type as a former case.
+    DuplicateCase { case_id: NodeId, former_id: NodeId },
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -28,4 +49,8 @@ impl TypeConclusions {
     pub fn get_type(&self, id: &NodeId) -> Option<&Type> {
         self.types.get(id)
     }
+
+    pub fn get_hole(&self, id: &NodeId) -> Option<&HoleConclusion> {
+        self.holes.get(id)
+    }
 }
@@ -1,27 +1,186 @@
 use dson::Dson;
 
+use crate::Type;
+
 pub trait DsonTypeDeduction {
-    fn deduct_type(&self) -> crate::Type;
+    fn deduct_type(&self) -> Type;
+    /// Whether the value is a subtype of the type, in the same way as the type inference.
+    fn conforms_to(&self, ty: &Type) -> bool;
 }
 
 impl DsonTypeDeduction for Dson {
-    fn deduct_type(&self) -> crate::Type {
+    fn deduct_type(&self) -> Type {
         match self {
             Dson::Literal(literal) => match literal {
-                dson::Literal::String(_) => crate::Type::String,
-                dson::Literal::Integer(_) => crate::Type::Integer,
-                dson::Literal::Rational(_, _) => crate::Type::Rational,
-                dson::Literal::Real(_) => crate::Type::Real,
+                dson::Literal::String(_) => Type::String,
+                dson::Literal::Integer(_) => Type::Integer,
+                dson::Literal::Rational(_, _) => Type::Rational,
+                dson::Literal::Real(_) => Type::Real,
+            },
+            Dson::Product(dsons) => Type::product(dsons.iter().map(Dson::deduct_type).collect()),
+            Dson::Vector(dsons) => Type::Vector(Box::new(Type::sum(
+                dsons.iter().map(Dson::deduct_type).collect(),
+            ))),
+            Dson::Map(elems) => Type::Map {
+                key: Box::new(Type::sum(
+                    elems.iter().map(|elem| elem.key.deduct_type()).collect(),
+                )),
+                value: Box::new(Type::sum(
+                    elems.iter().map(|elem| elem.value.deduct_type()).collect(),
+                )),
+            },
+            Dson::Attributed { attr: _, expr } => expr.deduct_type(),
+            Dson::Labeled { label, expr } => Type::Label {
+                label: label.clone(),
+                item: Box::new(expr.deduct_type()),
             },
-            Dson::Product(dsons) => todo!(),
-            Dson::Vector(dsons) => todo!(),
-            Dson::Map(elems) => todo!(),
-            Dson::Attributed { attr, expr } => todo!(),
-            Dson::Labeled { label, expr } => todo!(),
-            Dson::Typed { ty, expr } => todo!(),
-            Dson::Comment { text, expr } => todo!(),
+            Dson::Typed { ty, expr: _ } => ty.clone().into(),
+            Dson::Comment { text: _, expr } => expr.deduct_type(),
+        }
+    }
+
+    fn conforms_to(&self, ty: &Type) -> bool {
+        match (self, ty) {
+            (Dson::Attributed { expr, .. } | Dson::Comment { expr, .. }, ty) => {
+                expr.conforms_to(ty)
+            }
+            (Dson::Typed { ty: typed, expr }, ty) => {
+                expr.conforms_to(&typed.clone().into()) && expr.conforms_to(ty)
+            }
+            (
+                Dson::Literal(literal),
+                ty @ (Type::Integer | Type::Rational | Type::Real | Type::String),
+            ) => {
+                matches!(
+                    (literal, ty),
+                    (dson::Literal::String(_), Type::String)
+                        | (
+                            dson::Literal::Integer(_),
+                            Type::Integer | Type::Rational | Type::Real
+                        )
+                        | (dson::Literal::Rational(..), Type::Rational | Type::Real)
+                        | (dson::Literal::Real(_), Type::Real)
+                )
+            }
+            // Type variables are not checked.
+            (_, Type::Variable(_)) => true,
+            (_, Type::ForAll { body, .. }) => self.conforms_to(body),
+            (_, Type::Effectful { ty, .. }) => self.conforms_to(ty),
+            (Dson::Product(dsons), ty) if dsons.iter().any(|dson| dson.conforms_to(ty)) => true,
+            // Each type needs an element of its own, and extra elements are allowed.
+            (Dson::Product(dsons), Type::Product(types)) => {
+                let candidates: Vec<Vec<usize>> = types
+                    .iter()
+                    .map(|ty| {
+                        (0..dsons.len())
+                            .filter(|&index| dsons[index].conforms_to(ty))
+                            .collect()
+                    })
+                    .collect();
+                let mut assigned = vec![None; dsons.len()];
+                (0..types.len()).all(|ty| {
+                    assign(
+                        ty,
+                        &candidates,
+                        &mut assigned,
+                        &mut vec![false; dsons.len()],
+                    )
+                })
+            }
+            (_, Type::Sum(types)) => types.iter().any(|ty| self.conforms_to(ty)),
+            (Dson::Vector(dsons), Type::Vector(ty)) => {
+                dsons.iter().all(|dson| dson.conforms_to(ty))
+            }
+            (Dson::Map(elems), Type::Map { key, value }) => elems
+                .iter()
+                .all(|elem| elem.key.conforms_to(key) && elem.value.conforms_to(value)),
+            (
+                Dson::Labeled { label, expr },
+                Type::Label {
+                    label: label2,
+                    item,
+                },
+            ) if label == label2 => expr.conforms_to(item),
+            (_, Type::Label { item, .. }) if self.conforms_to(item) => true,
+            (Dson::Labeled { expr, .. }, ty) => expr.conforms_to(ty),
+            // DSON has no brands, and a value without a brand is not a subtype of a branded type.
+            _ => false,
+        }
+    }
+}
+
+/// Assigns an element to the type, reassigning other types to other elements if needed.
+fn assign(
+    ty: usize,
+    candidates: &[Vec<usize>],
+    assigned: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    for &index in &candidates[ty] {
+        if visited[index] {
+            continue;
+        }
+        visited[index] = true;
+        if assigned[index].is_none_or(|other| assign(other, candidates, assigned, visited)) {
+            assigned[index] = Some(ty);
+            return true;
         }
     }
+    false
+}
+
+/// Labels of DSON types are labels, not brands, since DSON has no brands.
+impl From<dson::Type> for Type {
+    fn from(ty: dson::Type) -> Self {
+        match ty {
+            dson::Type::Brand { brand, item } => Type::Label {
+                label: brand,
+                item: Box::new((*item).into()),
+            },
+            dson::Type::Real => Type::Real,
+            dson::Type::Rational => Type::Rational,
+            dson::Type::Integer => Type::Integer,
+            dson::Type::String => Type::String,
+            dson::Type::Product(types) => {
+                Type::product(types.into_iter().map(Into::into).collect())
+            }
+            dson::Type::Sum(types) => Type::sum(types.into_iter().map(Into::into).collect()),
+            dson::Type::Vector(item) => Type::Vector(Box::new((*item).into())),
+            dson::Type::Map { key, value } => Type::Map {
+                key: Box::new((*key).into()),
+                value: Box::new((*value).into()),
+            },
+            dson::Type::Attributed { attr: _, ty } => (*ty).into(),
+            dson::Type::Comment { text: _, item } => (*item).into(),
+            // Recursive definitions are left as variables.
+            dson::Type::Let {
+                variable,
+                definition,
+                body,
+            } => substitute((*body).into(), &variable, &(*definition).into()),
+            dson::Type::Variable(variable) => Type::Variable(variable),
+        }
+    }
+}
+
+/// Only handles types converted from DSON types.
+fn substitute(ty: Type, variable: &str, by: &Type) -> Type {
+    let subst = |ty: Type| substitute(ty, variable, by);
+    match ty {
+        Type::Variable(name) if name == variable => by.clone(),
+        Type::Product(types) => Type::product(types.into_iter().map(subst).collect()),
+        Type::Sum(types) => Type::sum(types.into_iter().map(subst).collect()),
+        Type::Vector(item) => Type::Vector(Box::new(subst(*item))),
+        Type::Map { key, value } => Type::Map {
+            key: Box::new(subst(*key)),
+            value: Box::new(subst(*value)),
+        },
+        Type::Label { label, item } => Type::Label {
+            label,
+            item: Box::new(subst(*item)),
+        },
+        ty => ty,
+    }
 }
 
 #[cfg(test)]
@@ -53,4 +212,129 @@ mod tests {
         let dson = Dson::Literal(dson::Literal::Real(Real(3.14)));
         assert_eq!(dson.deduct_type(), crate::Type::Real);
     }
+
+    fn label(label: &str, item: Type) -> Type {
+        Type::Label {
+            label: label.into(),
+            item: Box::new(item),
+        }
+    }
+
+    fn labeled(label: &str, expr: Dson) -> Dson {
+        Dson::Labeled {
+            label: label.into(),
+            expr: Box::new(expr),
+        }
+    }
+
+    #[test]
+    fn test_dson_type_compounds() {
+        let dson = Dson::Comment {
+            text: "point".into(),
+            expr: Box::new(Dson::Product(vec![
+                labeled("y", 1.into()),
+                Dson::Attributed {
+                    attr: Box::new("attr".into()),
+                    expr: Box::new(labeled("x", Dson::Literal(dson::Literal::Real(Real(1.5))))),
+                },
+            ])),
+        };
+        assert_eq!(
+            dson.deduct_type(),
+            Type::product(vec![label("x", Type::Real), label("y", Type::Integer)])
+        );
+
+        let dson = Dson::Vector(vec![1.into(), "a".into(), 2.into()]);
+        assert_eq!(
+            dson.deduct_type(),
+            Type::Vector(Box::new(Type::Sum(vec![Type::Integer, Type::String])))
+        );
+        assert_eq!(
+            Dson::Vector(vec![]).deduct_type(),
+            Type::Vector(Box::new(Type::Sum(vec![])))
+        );
+
+        let dson = Dson::Map(vec![dson::MapElem {
+            key: "a".into(),
+            value: 1.into(),
+        }]);
+        assert_eq!(
+            dson.deduct_type(),
+            Type::Map {
+                key: Box::new(Type::String),
+                value: Box::new(Type::Integer),
+            }
+        );
+    }
+
+    #[test]
+    fn test_dson_type_typed() {
+        let dson = Dson::Typed {
+            ty: dson::Type::Let {
+                variable: "id".into(),
+                definition: Box::new(dson::Type::Brand {
+                    brand: "id".into(),
+                    item: Box::new(dson::Type::String),
+                }),
+                body: Box::new(dson::Type::Vector(Box::new(dson::Type::Variable(
+                    "id".into(),
+                )))),
+            },
+            expr: Box::new(Dson::Vector(vec![])),
+        };
+        assert_eq!(
+            dson.deduct_type(),
+            Type::Vector(Box::new(label("id", Type::String)))
+        );
+    }
+
+    #[test]
+    fn test_conforms_to() {
+        assert!(Dson::from(1).conforms_to(&Type::Real));
+        assert!(!Dson::Literal(dson::Literal::Real(Real(1.0))).conforms_to(&Type::Integer));
+        // product width
+        let dson = Dson::Product(vec![labeled("x", 1.into()), labeled("y", 2.into())]);
+        assert!(dson.conforms_to(&Type::product(vec![label("y", Type::Rational)])));
+        assert!(dson.conforms_to(&label("x", Type::Integer)));
+        assert!(!dson.conforms_to(&Type::product(vec![label("z", Type::String)])));
+        // an element for each type
+        let dson = Dson::Product(vec![1.into()]);
+        assert!(!dson.conforms_to(&Type::product(vec![Type::Integer, Type::Real])));
+        let dson = Dson::Product(vec![
+            Dson::Literal(dson::Literal::Real(Real(1.5))),
+            1.into(),
+        ]);
+        assert!(dson.conforms_to(&Type::product(vec![Type::Integer, Type::Real])));
+        // sum injection
+        let sum = Type::Sum(vec![Type::String, label("x", Type::Integer)]);
+        assert!(Dson::Vector(vec!["a".into(), labeled("x", 1.into())])
+            .conforms_to(&Type::Vector(Box::new(sum))));
+        // labels
+        assert!(labeled("x", 1.into()).conforms_to(&Type::Integer));
+        assert!(Dson::from(1).conforms_to(&label("x", Type::Integer)));
+        // brands
+        let brand = Type::Brand {
+            brand: "nat".into(),
+            item: Box::new(Type::Integer),
+        };
+        assert!(!Dson::from(1).conforms_to(&brand));
+        // typed values
+        let typed = Dson::Typed {
+            ty: dson::Type::Integer,
+            expr: Box::new("a".into()),
+        };
+        assert!(!typed.conforms_to(&Type::String));
+    }
+
+    #[test]
+    fn test_conforms_to_deducted_type() {
+        let dson = Dson::Product(vec![
+            Dson::Vector(vec![1.into(), labeled("a", "a".into())]),
+            Dson::Map(vec![dson::MapElem {
+                key: Dson::Literal(dson::Literal::Rational(1, 2)),
+                value: Dson::Product(vec![]),
+            }]),
+        ]);
+        assert!(dson.conforms_to(&dson.deduct_type()));
+    }
 }
@@ -1,3 +1,4 @@
+mod canonical;
 pub mod conclusion;
 mod conversions;
 
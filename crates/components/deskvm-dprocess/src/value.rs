@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 
+use dson::{Dson, Literal, Real};
 use serde::{Deserialize, Serialize};
-use ty::Type;
+use thiserror::Error;
+use ty::{DsonTypeDeduction, Type};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 /// Sendable value between processes.
@@ -36,3 +38,310 @@ pub enum Number {
 
 // A float of should not be NaN.
 impl Eq for Number {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum DsonConversionError {
+    #[error("trait objects cannot be DSON")]
+    TraitObject,
+    #[error("{dson:?} is not a value of {ty:?}")]
+    Mismatch { dson: Dson, ty: Type },
+    #[error("values of {0:?} cannot be DSON")]
+    UnsupportedType(Type),
+}
+
+/// Labels, which are not in values but in their types, are restored from the types of product fields and variants.
+impl TryFrom<Value> for Dson {
+    type Error = DsonConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let dson = match value {
+            Value::Unit => Dson::Product(vec![]),
+            Value::Number(Number::Integer(int)) => Dson::Literal(Literal::Integer(int)),
+            Value::Number(Number::Real(real)) => Dson::Literal(Literal::Real(Real(real))),
+            Value::Number(Number::Rational(a, b)) => Dson::Literal(Literal::Rational(a, b)),
+            Value::String(string) => Dson::Literal(Literal::String(string)),
+            Value::Product(values) => {
+                let mut values: Vec<_> = values.into_iter().collect();
+                // Sorted to be the same DSON for the same product.
+                values.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Dson::Product(
+                    values
+                        .into_iter()
+                        .map(|(ty, value)| Ok(with_labels(value.try_into()?, &ty)))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            Value::Variant { ty, value } => with_labels((*value).try_into()?, &ty),
+            Value::Vector(values) => Dson::Vector(
+                values
+                    .into_iter()
+                    .map(Dson::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::TraitObject { .. } => return Err(DsonConversionError::TraitObject),
+        };
+        Ok(dson)
+    }
+}
+
+fn with_labels(dson: Dson, ty: &Type) -> Dson {
+    match ty {
+        Type::Label { label, item } => Dson::Labeled {
+            label: label.clone(),
+            expr: Box::new(with_labels(dson, item)),
+        },
+        Type::Brand { item, .. } => with_labels(dson, item),
+        _ => dson,
+    }
+}
+
+impl Value {
+    /// Labels must match exactly, unlike the subtyping of DSON.
+    pub fn from_dson(dson: &Dson, ty: &Type) -> Result<Self, DsonConversionError> {
+        let mismatch = || DsonConversionError::Mismatch {
+            dson: dson.clone(),
+            ty: ty.clone(),
+        };
+        let value = match (dson, ty) {
+            (Dson::Comment { expr, .. } | Dson::Attributed { expr, .. }, ty) => {
+                return Value::from_dson(expr, ty)
+            }
+            (Dson::Typed { ty: typed, expr }, ty) => {
+                if !expr.conforms_to(&typed.clone().into()) {
+                    return Err(mismatch());
+                }
+                return Value::from_dson(expr, ty);
+            }
+            (
+                Dson::Labeled { label, expr },
+                Type::Label {
+                    label: label2,
+                    item,
+                },
+            ) if label == label2 => return Value::from_dson(expr, item),
+            (_, Type::Brand { item, .. }) => return Value::from_dson(dson, item),
+            (
+                Dson::Literal(literal),
+                Type::Integer | Type::Rational | Type::Real | Type::String,
+            ) => match (literal, ty) {
+                (Literal::Integer(int), Type::Integer) => Value::Number(Number::Integer(*int)),
+                (Literal::Integer(int), Type::Rational) => Value::Number(Number::Rational(*int, 1)),
+                (Literal::Rational(a, b), Type::Rational) => {
+                    Value::Number(Number::Rational(*a, *b))
+                }
+                (Literal::Integer(int), Type::Real) => Value::Number(Number::Real(*int as f64)),
+                (Literal::Rational(a, b), Type::Real) => {
+                    Value::Number(Number::Real(*a as f64 / *b as f64))
+                }
+                (Literal::Real(real), Type::Real) => Value::Number(Number::Real(real.0)),
+                (Literal::String(string), Type::String) => Value::String(string.clone()),
+                _ => return Err(mismatch()),
+            },
+            (Dson::Product(dsons), Type::Product(types)) if types.is_empty() => {
+                if !dsons.is_empty() {
+                    return Err(mismatch());
+                }
+                Value::Unit
+            }
+            (Dson::Product(dsons), Type::Product(types)) => {
+                if dsons.len() != types.len() {
+                    return Err(mismatch());
+                }
+                // Values of each type, with elements of the same type first as in sums.
+                let mut candidates: Vec<Vec<(usize, Value)>> = types
+                    .iter()
+                    .map(|ty| {
+                        dsons
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(index, dson)| {
+                                Some((index, Value::from_dson(dson, ty).ok()?))
+                            })
+                            .collect()
+                    })
+                    .collect();
+                for (candidates, ty) in candidates.iter_mut().zip(types) {
+                    candidates.sort_by_key(|(index, _)| dsons[*index].deduct_type() != *ty);
+                }
+                // A type of each element, such as `'integer` of `2` in `*<2, 1.5>` for `*<'real, 'integer>`.
+                let mut assigned = vec![None; dsons.len()];
+                for ty in 0..types.len() {
+                    if !assign(
+                        ty,
+                        &candidates,
+                        &mut assigned,
+                        &mut vec![false; dsons.len()],
+                    ) {
+                        return Err(mismatch());
+                    }
+                }
+                let mut values = HashMap::new();
+                for (index, ty) in assigned.into_iter().enumerate() {
+                    let ty = ty.expect("there are as many elements as types");
+                    let (_, value) = candidates[ty]
+                        .iter()
+                        .find(|(candidate, _)| *candidate == index)
+                        .expect("assigned elements are candidates");
+                    values.insert(types[ty].clone(), value.clone());
+                }
+                Value::Product(values)
+            }
+            (dson, Type::Sum(types)) => {
+                // A variant of the same type is preferred, for example `1` is an integer in `+<'real, 'integer>`.
+                let deducted = dson.deduct_type();
+                let (ty, value) = types
+                    .iter()
+                    .filter(|ty| **ty == deducted)
+                    .chain(types.iter())
+                    .find_map(|ty| Some((ty, Value::from_dson(dson, ty).ok()?)))
+                    .ok_or_else(mismatch)?;
+                Value::Variant {
+                    ty: ty.clone(),
+                    value: Box::new(value),
+                }
+            }
+            (Dson::Vector(dsons), Type::Vector(item)) => Value::Vector(
+                dsons
+                    .iter()
+                    .map(|dson| Value::from_dson(dson, item))
+                    .collect::<Result<_, _>>()?,
+            ),
+            (
+                _,
+                Type::Map { .. }
+                | Type::Function(_)
+                | Type::Variable(_)
+                | Type::ForAll { .. }
+                | Type::Effectful { .. },
+            ) => return Err(DsonConversionError::UnsupportedType(ty.clone())),
+            _ => return Err(mismatch()),
+        };
+        Ok(value)
+    }
+}
+
+/// Assigns an element to the type, reassigning other types to other elements if needed.
+fn assign(
+    ty: usize,
+    candidates: &[Vec<(usize, Value)>],
+    assigned: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    for (index, _) in &candidates[ty] {
+        if visited[*index] {
+            continue;
+        }
+        visited[*index] = true;
+        if assigned[*index].is_none_or(|other| assign(other, candidates, assigned, visited)) {
+            assigned[*index] = Some(ty);
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(label: &str, item: Type) -> Type {
+        Type::Label {
+            label: label.into(),
+            item: Box::new(item),
+        }
+    }
+
+    fn labeled(label: &str, expr: Dson) -> Dson {
+        Dson::Labeled {
+            label: label.into(),
+            expr: Box::new(expr),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_dson() {
+        let ordering = Type::Sum(vec![
+            label("less", Type::Product(vec![])),
+            label("equal", Type::Product(vec![])),
+        ]);
+        let ty = Type::product(vec![
+            label("x", Type::Integer),
+            label("y", Type::Integer),
+            label("ordering", ordering.clone()),
+            Type::Vector(Box::new(Type::Rational)),
+        ]);
+        let value = Value::Product(HashMap::from([
+            (label("x", Type::Integer), Value::Number(Number::Integer(1))),
+            (label("y", Type::Integer), Value::Number(Number::Integer(2))),
+            (
+                label("ordering", ordering),
+                Value::Variant {
+                    ty: label("equal", Type::Product(vec![])),
+                    value: Box::new(Value::Unit),
+                },
+            ),
+            (
+                Type::Vector(Box::new(Type::Rational)),
+                Value::Vector(vec![Value::Number(Number::Rational(-1, 2))]),
+            ),
+        ]));
+
+        let dson = Dson::try_from(value.clone()).unwrap();
+        assert_eq!(
+            dson,
+            Dson::Product(vec![
+                Dson::Vector(vec![Dson::Literal(Literal::Rational(-1, 2))]),
+                labeled("ordering", labeled("equal", Dson::Product(vec![]))),
+                labeled("x", 1.into()),
+                labeled("y", 2.into()),
+            ])
+        );
+        assert_eq!(Value::from_dson(&dson, &ty), Ok(value));
+    }
+
+    #[test]
+    fn prefers_variant_of_same_type() {
+        let ty = Type::Sum(vec![Type::Real, Type::Integer]);
+        assert_eq!(
+            Value::from_dson(&1.into(), &ty),
+            Ok(Value::Variant {
+                ty: Type::Integer,
+                value: Box::new(Value::Number(Number::Integer(1))),
+            })
+        );
+    }
+
+    #[test]
+    fn assigns_product_elements_to_their_types() {
+        let ty = Type::product(vec![Type::Real, Type::Integer]);
+        let dson = Dson::Product(vec![2.into(), Dson::Literal(Literal::Real(Real(1.5)))]);
+        assert_eq!(
+            Value::from_dson(&dson, &ty),
+            Ok(Value::Product(HashMap::from([
+                (Type::Real, Value::Number(Number::Real(1.5))),
+                (Type::Integer, Value::Number(Number::Integer(2))),
+            ])))
+        );
+    }
+
+    #[test]
+    fn requires_same_labels() {
+        let dson = labeled("y", 1.into());
+        assert_eq!(
+            Value::from_dson(&dson, &label("x", Type::Integer)),
+            Err(DsonConversionError::Mismatch {
+                dson,
+                ty: label("x", Type::Integer),
+            })
+        );
+    }
+
+    #[test]
+    fn trait_object_is_not_dson() {
+        let value = Value::TraitObject {
+            ty: Type::String,
+            value: Box::new(Value::Unit),
+        };
+        assert_eq!(Dson::try_from(value), Err(DsonConversionError::TraitObject));
+    }
+}
@@ -0,0 +1,308 @@
+//! A canonical binary encoding and content hashes.
+//!
+//! Every value starts with a tag byte that is stable across versions.
+//! Lengths are unsigned LEB128 varints, and integers are zigzag varints.
+//! Members of products, sums and maps are sorted by their encodings, and elements of vectors keep their order.
+//! Reals are big-endian IEEE 754 bits with `-0.0` as `0.0`, and rationals are reduced.
+use std::fmt::Display;
+
+use sha2::{Digest, Sha256};
+
+use crate::{Dson, Literal, MapElem, Type};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContentHash(pub [u8; 32]);
+
+/// Lowercase hex.
+impl Display for ContentHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+pub trait CanonicalEncode {
+    /// Appends the canonical encoding to `buf`.
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    fn canonical_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        self.encode(&mut buf);
+        buf
+    }
+
+    /// SHA-256 of the canonical encoding.
+    fn content_hash(&self) -> ContentHash {
+        ContentHash(Sha256::digest(self.canonical_bytes()).into())
+    }
+}
+
+pub fn encode_varint(mut value: u64, buf: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+pub fn encode_integer(value: i64, buf: &mut Vec<u8>) {
+    encode_varint(((value << 1) ^ (value >> 63)) as u64, buf)
+}
+
+pub fn encode_real(value: f64, buf: &mut Vec<u8>) {
+    let value = if value == 0.0 {
+        0.0
+    } else if value.is_nan() {
+        f64::NAN
+    } else {
+        value
+    };
+    buf.extend(value.to_bits().to_be_bytes())
+}
+
+pub fn encode_str(value: &str, buf: &mut Vec<u8>) {
+    encode_varint(value.len() as u64, buf);
+    buf.extend(value.as_bytes())
+}
+
+/// The length and the items in order.
+pub fn encode_seq<T: CanonicalEncode>(items: &[T], buf: &mut Vec<u8>) {
+    encode_varint(items.len() as u64, buf);
+    for item in items {
+        item.encode(buf);
+    }
+}
+
+/// The length and the items sorted by their encodings.
+pub fn encode_set<T: CanonicalEncode>(items: &[T], buf: &mut Vec<u8>) {
+    let mut items: Vec<_> = items.iter().map(CanonicalEncode::canonical_bytes).collect();
+    items.sort();
+    encode_varint(items.len() as u64, buf);
+    for item in items {
+        buf.extend(item);
+    }
+}
+
+impl CanonicalEncode for Dson {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Dson::Literal(Literal::String(string)) => {
+                buf.push(0x01);
+                encode_str(string, buf);
+            }
+            Dson::Literal(Literal::Integer(integer)) => {
+                buf.push(0x02);
+                encode_integer(*integer, buf);
+            }
+            Dson::Literal(Literal::Rational(a, b)) => {
+                let gcd = gcd(a.unsigned_abs(), *b);
+                // Divided in i128 because the gcd may be 2^63, which is out of the range of i64.
+                let (a, b) = if gcd > 1 {
+                    ((*a as i128 / gcd as i128) as i64, b / gcd)
+                } else {
+                    (*a, *b)
+                };
+                buf.push(0x03);
+                encode_integer(a, buf);
+                encode_varint(b, buf);
+            }
+            Dson::Literal(Literal::Real(real)) => {
+                buf.push(0x04);
+                encode_real(real.0, buf);
+            }
+            Dson::Product(dsons) => {
+                buf.push(0x05);
+                encode_set(dsons, buf);
+            }
+            Dson::Vector(dsons) => {
+                buf.push(0x06);
+                encode_seq(dsons, buf);
+            }
+            Dson::Map(elems) => {
+                buf.push(0x07);
+                encode_set(elems, buf);
+            }
+            Dson::Attributed { attr, expr } => {
+                buf.push(0x08);
+                attr.encode(buf);
+                expr.encode(buf);
+            }
+            Dson::Labeled { label, expr } => {
+                buf.push(0x09);
+                encode_str(label, buf);
+                expr.encode(buf);
+            }
+            Dson::Typed { ty, expr } => {
+                buf.push(0x0a);
+                ty.encode(buf);
+                expr.encode(buf);
+            }
+            Dson::Comment { text, expr } => {
+                buf.push(0x0b);
+                encode_str(text, buf);
+                expr.encode(buf);
+            }
+        }
+    }
+}
+
+impl CanonicalEncode for MapElem {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.key.encode(buf);
+        self.value.encode(buf);
+    }
+}
+
+impl CanonicalEncode for Type {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Type::Brand { brand, item } => {
+                buf.push(0x20);
+                encode_str(brand, buf);
+                item.encode(buf);
+            }
+            Type::Real => buf.push(0x21),
+            Type::Rational => buf.push(0x22),
+            Type::Integer => buf.push(0x23),
+            Type::String => buf.push(0x24),
+            Type::Product(types) => {
+                buf.push(0x25);
+                encode_set(types, buf);
+            }
+            Type::Sum(types) => {
+                buf.push(0x26);
+                encode_set(types, buf);
+            }
+            Type::Vector(item) => {
+                buf.push(0x27);
+                item.encode(buf);
+            }
+            Type::Map { key, value } => {
+                buf.push(0x28);
+                key.encode(buf);
+                value.encode(buf);
+            }
+            Type::Attributed { attr, ty } => {
+                buf.push(0x29);
+                attr.encode(buf);
+                ty.encode(buf);
+            }
+            Type::Comment { text, item } => {
+                buf.push(0x2a);
+                encode_str(text, buf);
+                item.encode(buf);
+            }
+            Type::Let {
+                variable,
+                definition,
+                body,
+            } => {
+                buf.push(0x2b);
+                encode_str(variable, buf);
+                definition.encode(buf);
+                body.encode(buf);
+            }
+            Type::Variable(variable) => {
+                buf.push(0x2c);
+                encode_str(variable, buf);
+            }
+        }
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Real;
+
+    use super::*;
+
+    fn real(real: f64) -> Dson {
+        Dson::Literal(Literal::Real(Real(real)))
+    }
+
+    #[test]
+    fn encodes_varints() {
+        let mut buf = vec![];
+        encode_varint(300, &mut buf);
+        encode_integer(-1, &mut buf);
+        encode_integer(i64::MIN, &mut buf);
+        assert_eq!(
+            buf,
+            [0xac, 0x02, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+    }
+
+    #[test]
+    fn encodes_dson() {
+        let dson = Dson::Labeled {
+            label: "a".into(),
+            expr: Box::new(Dson::Vector(vec![(-2).into(), "b".into()])),
+        };
+        assert_eq!(
+            dson.canonical_bytes(),
+            [0x09, 0x01, b'a', 0x06, 0x02, 0x02, 0x03, 0x01, 0x01, b'b']
+        );
+        assert_eq!(
+            Dson::Product(vec![]).content_hash().to_string(),
+            // sha256sum of 0x05 0x00
+            "2921a11f25dadaa24aa79a548e4e81508c2e5e56af2d833d65e2bcce448ce2f5"
+        );
+    }
+
+    #[test]
+    fn sorts_products_and_maps() {
+        let a = Dson::Product(vec![1.into(), "a".into()]);
+        let b = Dson::Product(vec!["a".into(), 1.into()]);
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        let map = |elems: Vec<(i64, i64)>| {
+            Dson::Map(
+                elems
+                    .into_iter()
+                    .map(|(key, value)| MapElem {
+                        key: key.into(),
+                        value: value.into(),
+                    })
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            map(vec![(1, 2), (3, 4)]).content_hash(),
+            map(vec![(3, 4), (1, 2)]).content_hash()
+        );
+
+        let a = Dson::Vector(vec![1.into(), 2.into()]);
+        let b = Dson::Vector(vec![2.into(), 1.into()]);
+        assert_ne!(a.content_hash(), b.content_hash());
+
+        let a = Type::Sum(vec![Type::Integer, Type::String]);
+        let b = Type::Sum(vec![Type::String, Type::Integer]);
+        assert_eq!(a.content_hash(), b.content_hash());
+    }
+
+    #[test]
+    fn normalizes_numbers() {
+        assert_eq!(real(-0.0).content_hash(), real(0.0).content_hash());
+        assert_eq!(
+            Dson::Literal(Literal::Rational(-2, 4)).canonical_bytes(),
+            Dson::Literal(Literal::Rational(-1, 2)).canonical_bytes()
+        );
+        assert_eq!(
+            Dson::Literal(Literal::Rational(i64::MIN, 1 << 63)).canonical_bytes(),
+            Dson::Literal(Literal::Rational(-1, 1)).canonical_bytes()
+        );
+        assert_ne!(
+            Dson::from(1).content_hash(),
+            Dson::Literal(Literal::Rational(1, 1)).content_hash()
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Conversions between JSON and DSON.
+//!
+//! | JSON | DSON |
+//! | --- | --- |
+//! | `null` | `*<>` |
+//! | `true`, `false` | `@true *<>`, `@false *<>` |
+//! | number | integer if it fits in `i64`, otherwise real |
+//! | string | string |
+//! | array | vector |
+//! | object | product of labeled fields |
+//!
+//! Other DSON values are objects with tags:
+//!
+//! | DSON | JSON |
+//! | --- | --- |
+//! | `1 / 2` | `{"$rational": [1, 2]}` |
+//! | `*<1, @a 2>` | `{"$product": [1, {"$label": "a", "$value": 2}]}` |
+//! | `{1 => 2}` | `{"$map": [[1, 2]]}` |
+//! | `@a 1` | `{"$label": "a", "$value": 1}` |
+//! | `<'integer> 1` | `{"$type": "'integer", "$value": 1}` |
+//! | `#1 2` | `{"$attr": 1, "$value": 2}` |
+//! | `~text` + newline + `1` | `{"$comment": "text", "$value": 1}` |
+//!
+//! Products become tagged objects unless they consist of labeled fields with distinct labels not starting with `$`.
+//! DSON to JSON to DSON is lossless except for the order of fields.
+use std::{collections::HashSet, fmt::Display};
+
+use serde_json::{Map, Number, Value};
+
+use crate::{Dson, Literal, MapElem, ParseError, Real, Type};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JsonError {
+    InvalidTag { tag: String, value: Value },
+    InvalidType(ParseError),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::InvalidTag { tag, value } => write!(f, "invalid {tag}: {value}"),
+            JsonError::InvalidType(err) => write!(f, "invalid type: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl From<Dson> for Value {
+    fn from(dson: Dson) -> Self {
+        match dson {
+            Dson::Literal(Literal::String(string)) => Value::String(string),
+            Dson::Literal(Literal::Integer(integer)) => Value::Number(integer.into()),
+            Dson::Literal(Literal::Rational(a, b)) => tagged("$rational", vec![a.into(), b.into()]),
+            // DSON reals are finite, so null is never used.
+            Dson::Literal(Literal::Real(real)) => {
+                Number::from_f64(real.0).map_or(Value::Null, Value::Number)
+            }
+            Dson::Product(dsons) if dsons.is_empty() => Value::Null,
+            Dson::Product(dsons) if is_object(&dsons) => Value::Object(
+                dsons
+                    .into_iter()
+                    .map(|dson| match dson {
+                        Dson::Labeled { label, expr } => (label, (*expr).into()),
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+            ),
+            Dson::Product(dsons) => tagged("$product", dsons.into_iter().map(Into::into).collect()),
+            Dson::Vector(dsons) => Value::Array(dsons.into_iter().map(Into::into).collect()),
+            Dson::Map(elems) => tagged(
+                "$map",
+                elems
+                    .into_iter()
+                    .map(|MapElem { key, value }| Value::Array(vec![key.into(), value.into()]))
+                    .collect(),
+            ),
+            Dson::Labeled { label, expr } => match (label.as_str(), expr.as_ref()) {
+                ("true", Dson::Product(dsons)) if dsons.is_empty() => Value::Bool(true),
+                ("false", Dson::Product(dsons)) if dsons.is_empty() => Value::Bool(false),
+                _ => with_value("$label", label.into(), *expr),
+            },
+            Dson::Typed { ty, expr } => with_value("$type", ty.to_string().into(), *expr),
+            Dson::Attributed { attr, expr } => with_value("$attr", (*attr).into(), *expr),
+            Dson::Comment { text, expr } => with_value("$comment", text.into(), *expr),
+        }
+    }
+}
+
+impl TryFrom<Value> for Dson {
+    type Error = JsonError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let dson = match value {
+            Value::Null => Dson::Product(vec![]),
+            Value::Bool(bool) => Dson::Labeled {
+                label: bool.to_string(),
+                expr: Box::new(Dson::Product(vec![])),
+            },
+            Value::Number(number) => match number.as_i64() {
+                Some(integer) => Dson::Literal(Literal::Integer(integer)),
+                None => Dson::Literal(Literal::Real(Real(number.as_f64().unwrap()))),
+            },
+            Value::String(string) => Dson::Literal(Literal::String(string)),
+            Value::Array(values) => Dson::Vector(try_from_all(values)?),
+            Value::Object(object) => from_object(object)?,
+        };
+        Ok(dson)
+    }
+}
+
+fn is_object(dsons: &[Dson]) -> bool {
+    let mut labels = HashSet::new();
+    dsons.iter().all(|dson| {
+        matches!(dson, Dson::Labeled { label, .. } if !label.starts_with('$') && labels.insert(label))
+    })
+}
+
+fn tagged(tag: &str, values: Vec<Value>) -> Value {
+    Value::Object(Map::from_iter([(tag.into(), Value::Array(values))]))
+}
+
+fn with_value(tag: &str, payload: Value, expr: Dson) -> Value {
+    Value::Object(Map::from_iter([
+        (tag.into(), payload),
+        ("$value".into(), expr.into()),
+    ]))
+}
+
+fn try_from_all(values: Vec<Value>) -> Result<Vec<Dson>, JsonError> {
+    values.into_iter().map(Dson::try_from).collect()
+}
+
+fn from_object(mut object: Map<String, Value>) -> Result<Dson, JsonError> {
+    let mut keys: Vec<_> = object.keys().map(String::as_str).collect();
+    keys.sort();
+    let tag = match keys[..] {
+        [tag @ ("$rational" | "$product" | "$map")] => tag.to_string(),
+        [tag @ ("$attr" | "$comment" | "$label" | "$type"), "$value"] => tag.to_string(),
+        _ => {
+            return Ok(Dson::Product(
+                object
+                    .into_iter()
+                    .map(|(label, value)| {
+                        Ok(Dson::Labeled {
+                            label,
+                            expr: Box::new(value.try_into()?),
+                        })
+                    })
+                    .collect::<Result<_, JsonError>>()?,
+            ))
+        }
+    };
+    let payload = object.remove(&tag).unwrap();
+    let invalid = || JsonError::InvalidTag {
+        tag: tag.clone(),
+        value: payload.clone(),
+    };
+    let expr = match object.remove("$value") {
+        Some(value) => Some(Box::new(Dson::try_from(value)?)),
+        None => None,
+    };
+    let dson = match (tag.as_str(), &payload, expr) {
+        ("$rational", Value::Array(values), None) => match &values[..] {
+            [a, b] => Dson::Literal(Literal::Rational(
+                a.as_i64().ok_or_else(invalid)?,
+                b.as_u64().ok_or_else(invalid)?,
+            )),
+            _ => return Err(invalid()),
+        },
+        ("$product", Value::Array(values), None) => Dson::Product(try_from_all(values.clone())?),
+        ("$map", Value::Array(values), None) => Dson::Map(
+            values
+                .iter()
+                .map(|elem| match elem {
+                    Value::Array(elem) if elem.len() == 2 => Ok(MapElem {
+                        key: elem[0].clone().try_into()?,
+                        value: elem[1].clone().try_into()?,
+                    }),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        ("$label", Value::String(label), Some(expr)) => Dson::Labeled {
+            label: label.clone(),
+            expr,
+        },
+        ("$type", Value::String(ty), Some(expr)) => Dson::Typed {
+            ty: ty.parse::<Type>().map_err(JsonError::InvalidType)?,
+            expr,
+        },
+        ("$attr", attr, Some(expr)) => Dson::Attributed {
+            attr: Box::new(attr.clone().try_into()?),
+            expr,
+        },
+        ("$comment", Value::String(text), Some(expr)) => Dson::Comment {
+            text: text.clone(),
+            expr,
+        },
+        _ => return Err(invalid()),
+    };
+    Ok(dson)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn labeled(label: &str, expr: Dson) -> Dson {
+        Dson::Labeled {
+            label: label.into(),
+            expr: Box::new(expr),
+        }
+    }
+
+    #[test]
+    fn converts_json() {
+        let json = json!({
+            "name": "desk",
+            "tags": [1, 1.5, null, true],
+            "nested": {"big": u64::MAX},
+        });
+        assert_eq!(
+            Dson::try_from(json),
+            Ok(Dson::Product(vec![
+                labeled("name", "desk".into()),
+                labeled(
+                    "nested",
+                    Dson::Product(vec![labeled(
+                        "big",
+                        Dson::Literal(Literal::Real(Real(u64::MAX as f64)))
+                    )])
+                ),
+                labeled(
+                    "tags",
+                    Dson::Vector(vec![
+                        1.into(),
+                        Dson::Literal(Literal::Real(Real(1.5))),
+                        Dson::Product(vec![]),
+                        labeled("true", Dson::Product(vec![])),
+                    ])
+                ),
+            ]))
+        );
+    }
+
+    #[test]
+    fn converts_dson_with_tags() {
+        let dson = Dson::Product(vec![
+            Dson::Literal(Literal::Rational(1, 2)),
+            Dson::Typed {
+                ty: Type::Integer,
+                expr: Box::new(labeled("a", 1.into())),
+            },
+        ]);
+        let json = json!({"$product": [
+            {"$rational": [1, 2]},
+            {"$type": "'integer", "$value": {"$label": "a", "$value": 1}},
+        ]});
+        assert_eq!(Value::from(dson.clone()), json);
+        assert_eq!(Dson::try_from(json), Ok(dson));
+    }
+
+    #[test]
+    fn round_trips() {
+        let dson = Dson::Comment {
+            text: "config".into(),
+            expr: Box::new(Dson::Product(vec![
+                labeled(
+                    "a",
+                    Dson::Map(vec![MapElem {
+                        key: Dson::Literal(Literal::Real(Real(2.0))),
+                        value: Dson::Attributed {
+                            attr: Box::new("attr".into()),
+                            expr: Box::new(labeled("false", Dson::Product(vec![]))),
+                        },
+                    }]),
+                ),
+                labeled("b", Dson::Vector(vec![labeled("$c", 1.into())])),
+            ])),
+        };
+        assert_eq!(Dson::try_from(Value::from(dson.clone())), Ok(dson));
+    }
+
+    #[test]
+    fn rejects_invalid_tags() {
+        assert_eq!(
+            Dson::try_from(json!({"$rational": [1, -2]})),
+            Err(JsonError::InvalidTag {
+                tag: "$rational".into(),
+                value: json!([1, -2]),
+            })
+        );
+        assert!(matches!(
+            Dson::try_from(json!({"$type": "'unknown", "$value": 1})),
+            Err(JsonError::InvalidType(_))
+        ));
+    }
+}
@@ -1,4 +1,10 @@
-use std::fmt::Display;
+pub mod canonical;
+#[cfg(feature = "json")]
+pub mod json;
+mod parse;
+mod print;
+
+pub use parse::ParseError;
 
 use serde::{Deserialize, Serialize};
 
@@ -104,22 +110,115 @@ impl From<i64> for Dson {
     }
 }
 
-impl Display for Dson {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Dson::Literal(literal) => match literal {
-                Literal::String(string) => write!(f, "{:?}", string),
-                Literal::Integer(i) => write!(f, "{}", i),
-                Literal::Rational(a, b) => write!(f, "{} / {}", a, b),
-                Literal::Real(real) => write!(f, "{}", real.0),
-            },
-            Dson::Product(_) => todo!(),
-            Dson::Vector(_) => todo!(),
-            Dson::Map(_) => todo!(),
-            Dson::Attributed { attr, expr } => todo!(),
-            Dson::Labeled { label, expr } => todo!(),
-            Dson::Typed { ty, expr } => todo!(),
-            Dson::Comment { text, expr } => todo!(),
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn literal() -> impl Strategy<Value = Literal> {
+        prop_oneof![
+            any::<String>().prop_map(Literal::String),
+            any::<i64>().prop_map(Literal::Integer),
+            (any::<i64>(), any::<u64>()).prop_map(|(a, b)| Literal::Rational(a, b)),
+            any::<f64>()
+                .prop_filter("real must be finite", |real| real.is_finite())
+                .prop_map(|real| Literal::Real(Real(real))),
+        ]
+    }
+
+    fn comment() -> impl Strategy<Value = String> {
+        any::<String>().prop_filter("block comment must not be closed", |text| {
+            !text.contains(")~")
+        })
+    }
+
+    fn ty() -> impl Strategy<Value = Type> {
+        let leaf = prop_oneof![
+            Just(Type::Real),
+            Just(Type::Rational),
+            Just(Type::Integer),
+            Just(Type::String),
+            any::<String>().prop_map(Type::Variable),
+        ];
+        leaf.prop_recursive(4, 32, 4, |ty| {
+            prop_oneof![
+                (any::<String>(), ty.clone()).prop_map(|(brand, item)| Type::Brand {
+                    brand,
+                    item: Box::new(item),
+                }),
+                prop::collection::vec(ty.clone(), 0..4).prop_map(Type::Product),
+                prop::collection::vec(ty.clone(), 0..4).prop_map(Type::Sum),
+                ty.clone().prop_map(|item| Type::Vector(Box::new(item))),
+                (ty.clone(), ty.clone()).prop_map(|(key, value)| Type::Map {
+                    key: Box::new(key),
+                    value: Box::new(value),
+                }),
+                (literal(), ty.clone()).prop_map(|(attr, ty)| Type::Attributed {
+                    attr: Box::new(Dson::Literal(attr)),
+                    ty: Box::new(ty),
+                }),
+                (comment(), ty.clone()).prop_map(|(text, item)| Type::Comment {
+                    text,
+                    item: Box::new(item),
+                }),
+                (any::<String>(), ty.clone(), ty).prop_map(|(variable, definition, body)| {
+                    Type::Let {
+                        variable,
+                        definition: Box::new(definition),
+                        body: Box::new(body),
+                    }
+                }),
+            ]
+        })
+    }
+
+    fn dson() -> impl Strategy<Value = Dson> {
+        literal()
+            .prop_map(Dson::Literal)
+            .prop_recursive(4, 32, 4, |dson| {
+                prop_oneof![
+                    prop::collection::vec(dson.clone(), 0..4).prop_map(Dson::Product),
+                    prop::collection::vec(dson.clone(), 0..4).prop_map(Dson::Vector),
+                    prop::collection::vec((dson.clone(), dson.clone()), 0..4).prop_map(|elems| {
+                        Dson::Map(
+                            elems
+                                .into_iter()
+                                .map(|(key, value)| MapElem { key, value })
+                                .collect(),
+                        )
+                    }),
+                    (dson.clone(), dson.clone()).prop_map(|(attr, expr)| Dson::Attributed {
+                        attr: Box::new(attr),
+                        expr: Box::new(expr),
+                    }),
+                    (any::<String>(), dson.clone()).prop_map(|(label, expr)| Dson::Labeled {
+                        label,
+                        expr: Box::new(expr),
+                    }),
+                    (ty(), dson.clone()).prop_map(|(ty, expr)| Dson::Typed {
+                        ty,
+                        expr: Box::new(expr),
+                    }),
+                    (comment(), dson).prop_map(|(text, expr)| Dson::Comment {
+                        text,
+                        expr: Box::new(expr),
+                    }),
+                ]
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips(dson in dson()) {
+            prop_assert_eq!(dson.to_string().parse::<Dson>(), Ok(dson.clone()));
+            prop_assert_eq!(format!("{dson:#}").parse::<Dson>(), Ok(dson));
+        }
+
+        #[test]
+        fn round_trips_types(ty in ty()) {
+            prop_assert_eq!(ty.to_string().parse::<Type>(), Ok(ty.clone()));
+            prop_assert_eq!(format!("{ty:#}").parse::<Type>(), Ok(ty));
         }
     }
 }
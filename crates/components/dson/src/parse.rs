@@ -0,0 +1,469 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{Dson, Literal, MapElem, Real, Type};
+
+/// An error of parsing DSON text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub expected: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected {} at {}:{}",
+            self.expected, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses the DSON subset of the minimalist syntax.
+impl FromStr for Dson {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Parser { text, offset: 0 }.parse_all(Parser::dson)
+    }
+}
+
+/// Parses the DSON subset of the minimalist syntax.
+impl FromStr for Type {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Parser { text, offset: 0 }.parse_all(Parser::ty)
+    }
+}
+
+/// Same as the raw identifiers of the minimalist syntax.
+pub(crate) fn is_raw_ident_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '!'..='@' | '['..='`' | '{'..='~')
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_all<T>(
+        mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let parsed = parse(&mut self)?;
+        self.skip_whitespace();
+        if !self.rest().is_empty() {
+            return Err(self.error("end of text"));
+        }
+        Ok(parsed)
+    }
+
+    fn dson(&mut self) -> Result<Dson, ParseError> {
+        if let Some(text) = self.comment()? {
+            return Ok(Dson::Comment {
+                text,
+                expr: Box::new(self.dson()?),
+            });
+        }
+        let dson = if self.eat("'(") {
+            let dson = self.dson()?;
+            self.expect(")'")?;
+            dson
+        } else if self.eat("*<") {
+            let dsons = self.items(">", Self::dson)?;
+            Dson::Product(dsons)
+        } else if self.eat("[") {
+            Dson::Vector(self.items("]", Self::dson)?)
+        } else if self.eat("{") {
+            Dson::Map(self.items("}", |parser| {
+                let key = parser.dson()?;
+                parser.expect("=>")?;
+                let value = parser.dson()?;
+                Ok(MapElem { key, value })
+            })?)
+        } else if self.eat("#") {
+            let attr = self.dson()?;
+            Dson::Attributed {
+                attr: Box::new(attr),
+                expr: Box::new(self.dson()?),
+            }
+        } else if self.eat("@") {
+            let label = self.ident()?;
+            Dson::Labeled {
+                label,
+                expr: Box::new(self.dson()?),
+            }
+        } else if self.eat("<") {
+            let ty = self.ty()?;
+            self.expect(">")?;
+            Dson::Typed {
+                ty,
+                expr: Box::new(self.dson()?),
+            }
+        } else {
+            Dson::Literal(self.literal()?)
+        };
+        Ok(dson)
+    }
+
+    fn literal(&mut self) -> Result<Literal, ParseError> {
+        if self.eat("\"") {
+            return self.string().map(Literal::String);
+        }
+        for (begin, end) in [("«", '»'), ("‹", '›')] {
+            if self.eat(begin) {
+                let string = self.take_while(|c| c != end).to_string();
+                self.expect(&end.to_string())?;
+                return Ok(Literal::String(string));
+            }
+        }
+        let start = self.offset;
+        let literal = if let Some(radix) = self.radix_prefix() {
+            let digits = self.take_while(|c| c.is_ascii_alphanumeric());
+            i64::from_str_radix(digits, radix)
+                .ok()
+                .map(Literal::Integer)
+        } else {
+            let integer = self.decimal();
+            if self.rest().starts_with('.') {
+                self.offset += 1;
+                let fraction = self.take_while(|c| c.is_ascii_digit());
+                format!("{integer}.{fraction}")
+                    .parse()
+                    .ok()
+                    .filter(|_| !integer.is_empty() && !fraction.is_empty())
+                    .map(|real| Literal::Real(Real(real)))
+            } else if self.eat("/") {
+                self.skip_whitespace();
+                let denominator = self.take_while(|c| c.is_ascii_digit());
+                integer
+                    .parse()
+                    .ok()
+                    .zip(denominator.parse().ok())
+                    .map(|(a, b)| Literal::Rational(a, b))
+            } else {
+                integer.parse().ok().map(Literal::Integer)
+            }
+        };
+        literal.ok_or_else(|| {
+            self.offset = start;
+            self.error("literal")
+        })
+    }
+
+    /// An optional minus sign and digits.
+    fn decimal(&mut self) -> String {
+        let minus = if self.rest().starts_with('-') {
+            self.offset += 1;
+            "-"
+        } else {
+            ""
+        };
+        format!("{minus}{}", self.take_while(|c| c.is_ascii_digit()))
+    }
+
+    fn radix_prefix(&mut self) -> Option<u32> {
+        let radix = match self.rest().get(..2)? {
+            "0x" => 16,
+            "0o" => 8,
+            "0b" => 2,
+            _ => return None,
+        };
+        self.offset += 2;
+        Some(radix)
+    }
+
+    /// The rest of a string after the opening quote.
+    fn string(&mut self) -> Result<String, ParseError> {
+        let mut string = String::new();
+        loop {
+            match self.next_char() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(match self.next_char() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(c @ ('"' | '\\')) => c,
+                    _ => return Err(self.error("escape sequence")),
+                }),
+                Some(c) => string.push(c),
+                None => return Err(self.error("closing quote")),
+            }
+        }
+    }
+
+    fn ty(&mut self) -> Result<Type, ParseError> {
+        if let Some(text) = self.comment()? {
+            return Ok(Type::Comment {
+                text,
+                item: Box::new(self.ty()?),
+            });
+        }
+        let ty = if self.eat("'(") {
+            let ty = self.ty()?;
+            self.expect(")'")?;
+            ty
+        } else if self.eat_keyword("'real") {
+            Type::Real
+        } else if self.eat_keyword("'rational") {
+            Type::Rational
+        } else if self.eat_keyword("'integer") {
+            Type::Integer
+        } else if self.eat_keyword("'string") {
+            Type::String
+        } else if self.eat("*<") {
+            Type::Product(self.items(">", Self::ty)?)
+        } else if self.eat("+<") {
+            Type::Sum(self.items(">", Self::ty)?)
+        } else if self.eat("[") {
+            let item = self.ty()?;
+            self.expect("]")?;
+            Type::Vector(Box::new(item))
+        } else if self.eat("{") {
+            let key = self.ty()?;
+            self.expect("=>")?;
+            let value = self.ty()?;
+            self.expect("}")?;
+            Type::Map {
+                key: Box::new(key),
+                value: Box::new(value),
+            }
+        } else if self.eat("#") {
+            let attr = self.dson()?;
+            Type::Attributed {
+                attr: Box::new(attr),
+                ty: Box::new(self.ty()?),
+            }
+        } else if self.eat("@") {
+            let brand = self.ident()?;
+            Type::Brand {
+                brand,
+                item: Box::new(self.ty()?),
+            }
+        } else if self.eat("$") {
+            let variable = self.ident()?;
+            let definition = self.ty()?;
+            self.expect(";")?;
+            Type::Let {
+                variable,
+                definition: Box::new(definition),
+                body: Box::new(self.ty()?),
+            }
+        } else {
+            Type::Variable(self.ident().map_err(|_| self.error("type"))?)
+        };
+        Ok(ty)
+    }
+
+    fn comment(&mut self) -> Result<Option<String>, ParseError> {
+        if self.eat("~(") {
+            let Some(len) = self.rest().find(")~") else {
+                return Err(self.error("`)~`"));
+            };
+            let text = self.rest()[..len].to_string();
+            self.offset += len + 2;
+            Ok(Some(text))
+        } else if self.eat("~") {
+            let text = self.take_while(|c| c != '\n').to_string();
+            Ok(Some(text))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        self.skip_whitespace();
+        if !self.eat("`") {
+            let ident = self.take_while(is_raw_ident_char);
+            if ident.is_empty() {
+                return Err(self.error("identifier"));
+            }
+            return Ok(ident.into());
+        }
+        let mut ident = String::new();
+        loop {
+            match self.next_char() {
+                Some('`') => return Ok(ident),
+                Some('\\') => match self.next_char() {
+                    Some(c @ ('`' | '\\')) => ident.push(c),
+                    _ => return Err(self.error("escaped backquote or backslash")),
+                },
+                Some(c) => ident.push(c),
+                None => return Err(self.error("closing backquote")),
+            }
+        }
+    }
+
+    /// Items separated by commas until `end`, allowing a trailing comma.
+    fn items<T>(
+        &mut self,
+        end: &str,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![];
+        while !self.eat(end) {
+            items.push(item(self)?);
+            if !self.eat(",") {
+                self.expect(end)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.offset..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.offset += len;
+        &rest[..len]
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.rest().chars().next()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.offset += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Unlike `eat`, does not match a prefix of a longer keyword.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        let rest = self.rest();
+        if rest.starts_with(keyword)
+            && !rest[keyword.len()..]
+                .chars()
+                .next()
+                .is_some_and(is_raw_ident_char)
+        {
+            self.offset += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{token}`")))
+        }
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let before = &self.text[..self.offset];
+        ParseError {
+            expected: expected.into(),
+            line: before.matches('\n').count() + 1,
+            column: before.chars().rev().take_while(|c| *c != '\n').count() + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_minimalist_syntax() {
+        let text = r#"
+            ~ settings
+            #"version" *<
+                @`window size` [0x10, 0b11, -3,],
+                <@meter 'real> 1.5,
+                {«raw» => 1/ 2, "a\n" => '(‹›)'},
+                ~( block )~ <$ x 'integer; *<x, #1 'string>> *<>,
+            >
+        "#;
+        let label = |label: &str, expr| Dson::Labeled {
+            label: label.into(),
+            expr: Box::new(expr),
+        };
+        assert_eq!(
+            text.parse(),
+            Ok(Dson::Comment {
+                text: " settings".into(),
+                expr: Box::new(Dson::Attributed {
+                    attr: Box::new("version".into()),
+                    expr: Box::new(Dson::Product(vec![
+                        label(
+                            "window size",
+                            Dson::Vector(vec![16.into(), 3.into(), (-3).into()])
+                        ),
+                        Dson::Typed {
+                            ty: Type::Brand {
+                                brand: "meter".into(),
+                                item: Box::new(Type::Real),
+                            },
+                            expr: Box::new(Dson::Literal(Literal::Real(Real(1.5)))),
+                        },
+                        Dson::Map(vec![
+                            MapElem {
+                                key: "raw".into(),
+                                value: Dson::Literal(Literal::Rational(1, 2)),
+                            },
+                            MapElem {
+                                key: "a\n".into(),
+                                value: "".into(),
+                            },
+                        ]),
+                        Dson::Comment {
+                            text: " block ".into(),
+                            expr: Box::new(Dson::Typed {
+                                ty: Type::Let {
+                                    variable: "x".into(),
+                                    definition: Box::new(Type::Integer),
+                                    body: Box::new(Type::Product(vec![
+                                        Type::Variable("x".into()),
+                                        Type::Attributed {
+                                            attr: Box::new(1.into()),
+                                            ty: Box::new(Type::String),
+                                        },
+                                    ])),
+                                },
+                                expr: Box::new(Dson::Product(vec![])),
+                            }),
+                        },
+                    ])),
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_position_of_error() {
+        assert_eq!(
+            "*<\n  1,\n  2 3>".parse::<Dson>(),
+            Err(ParseError {
+                expected: "`>`".into(),
+                line: 3,
+                column: 5,
+            })
+        );
+        assert_eq!(
+            "1.".parse::<Dson>().unwrap_err().expected,
+            "literal".to_string()
+        );
+    }
+}
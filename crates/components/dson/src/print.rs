@@ -0,0 +1,248 @@
+use std::fmt::{Display, Formatter, Result};
+
+use crate::{Dson, Literal, MapElem, Type};
+
+/// Prints in the minimalist syntax; the alternate flag (`{:#}`) puts each item on its own line.
+impl Display for Dson {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let indent = f.alternate().then_some(0);
+        write_dson(f, self, indent)
+    }
+}
+
+/// Prints in the minimalist syntax; the alternate flag (`{:#}`) puts each item on its own line.
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let indent = f.alternate().then_some(0);
+        write_type(f, self, indent)
+    }
+}
+
+/// `indent` is `None` for a single line.
+fn write_dson(f: &mut Formatter<'_>, dson: &Dson, indent: Option<usize>) -> Result {
+    match dson {
+        Dson::Literal(literal) => write_literal(f, literal),
+        Dson::Product(dsons) => write_items(f, "*<", ">", dsons, indent, write_dson),
+        Dson::Vector(dsons) => write_items(f, "[", "]", dsons, indent, write_dson),
+        Dson::Map(elems) => write_items(
+            f,
+            "{",
+            "}",
+            elems,
+            indent,
+            |f, MapElem { key, value }, indent| {
+                write_dson(f, key, indent)?;
+                write!(f, " => ")?;
+                write_dson(f, value, indent)
+            },
+        ),
+        Dson::Attributed { attr, expr } => {
+            write!(f, "#")?;
+            write_dson(f, attr, indent)?;
+            write!(f, " ")?;
+            write_dson(f, expr, indent)
+        }
+        Dson::Labeled { label, expr } => {
+            write!(f, "@")?;
+            write_ident(f, label)?;
+            write!(f, " ")?;
+            write_dson(f, expr, indent)
+        }
+        Dson::Typed { ty, expr } => {
+            write!(f, "<")?;
+            write_type(f, ty, indent)?;
+            write!(f, "> ")?;
+            write_dson(f, expr, indent)
+        }
+        Dson::Comment { text, expr } => {
+            write_comment(f, text, indent)?;
+            write_dson(f, expr, indent)
+        }
+    }
+}
+
+fn write_literal(f: &mut Formatter<'_>, literal: &Literal) -> Result {
+    match literal {
+        Literal::String(string) => {
+            write!(f, "\"")?;
+            for c in string.chars() {
+                match c {
+                    '"' => write!(f, "\\\"")?,
+                    '\\' => write!(f, "\\\\")?,
+                    '\n' => write!(f, "\\n")?,
+                    '\t' => write!(f, "\\t")?,
+                    c => write!(f, "{c}")?,
+                }
+            }
+            write!(f, "\"")
+        }
+        Literal::Integer(integer) => write!(f, "{integer}"),
+        Literal::Rational(a, b) => write!(f, "{a} / {b}"),
+        // A real always has a fractional part to be distinguished from an integer.
+        Literal::Real(real) if real.0.fract() == 0.0 => write!(f, "{:.1}", real.0),
+        Literal::Real(real) => write!(f, "{}", real.0),
+    }
+}
+
+fn write_type(f: &mut Formatter<'_>, ty: &Type, indent: Option<usize>) -> Result {
+    match ty {
+        Type::Brand { brand, item } => {
+            write!(f, "@")?;
+            write_ident(f, brand)?;
+            write!(f, " ")?;
+            write_type(f, item, indent)
+        }
+        Type::Real => write!(f, "'real"),
+        Type::Rational => write!(f, "'rational"),
+        Type::Integer => write!(f, "'integer"),
+        Type::String => write!(f, "'string"),
+        Type::Product(types) => write_items(f, "*<", ">", types, indent, write_type),
+        Type::Sum(types) => write_items(f, "+<", ">", types, indent, write_type),
+        Type::Vector(item) => {
+            write!(f, "[")?;
+            write_type(f, item, indent)?;
+            write!(f, "]")
+        }
+        Type::Map { key, value } => {
+            write!(f, "{{")?;
+            write_type(f, key, indent)?;
+            write!(f, " => ")?;
+            write_type(f, value, indent)?;
+            write!(f, "}}")
+        }
+        Type::Attributed { attr, ty } => {
+            write!(f, "#")?;
+            write_dson(f, attr, indent)?;
+            write!(f, " ")?;
+            write_type(f, ty, indent)
+        }
+        Type::Comment { text, item } => {
+            write_comment(f, text, indent)?;
+            write_type(f, item, indent)
+        }
+        Type::Let {
+            variable,
+            definition,
+            body,
+        } => {
+            write!(f, "$ ")?;
+            write_ident(f, variable)?;
+            write!(f, " ")?;
+            write_type(f, definition, indent)?;
+            write!(f, "; ")?;
+            write_type(f, body, indent)
+        }
+        Type::Variable(ident) => write_ident(f, ident),
+    }
+}
+
+fn write_items<T>(
+    f: &mut Formatter<'_>,
+    begin: &str,
+    end: &str,
+    items: &[T],
+    indent: Option<usize>,
+    mut write_item: impl FnMut(&mut Formatter<'_>, &T, Option<usize>) -> Result,
+) -> Result {
+    write!(f, "{begin}")?;
+    match indent {
+        Some(indent) if !items.is_empty() => {
+            for item in items {
+                write!(f, "\n{:1$}", "", indent + 2)?;
+                write_item(f, item, Some(indent + 2))?;
+                write!(f, ",")?;
+            }
+            write!(f, "\n{:1$}", "", indent)?;
+        }
+        _ => {
+            for (i, item) in items.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                write_item(f, item, indent)?;
+            }
+        }
+    }
+    write!(f, "{end}")
+}
+
+/// A line comment is used unless the text spans lines or looks like a block comment.
+fn write_comment(f: &mut Formatter<'_>, text: &str, indent: Option<usize>) -> Result {
+    if text.contains('\n') || text.starts_with('(') {
+        write!(f, "~({text})~ ")
+    } else {
+        write!(f, "~{text}\n{:1$}", "", indent.unwrap_or(0))
+    }
+}
+
+/// Identifiers that are not raw identifiers of the minimalist syntax are wrapped in backquotes.
+fn write_ident(f: &mut Formatter<'_>, ident: &str) -> Result {
+    if !ident.is_empty() && ident.chars().all(crate::parse::is_raw_ident_char) {
+        write!(f, "{ident}")
+    } else {
+        write!(f, "`")?;
+        for c in ident.chars() {
+            if c == '`' || c == '\\' {
+                write!(f, "\\")?;
+            }
+            write!(f, "{c}")?;
+        }
+        write!(f, "`")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Real;
+
+    use super::*;
+
+    fn dson() -> Dson {
+        Dson::Comment {
+            text: "config".into(),
+            expr: Box::new(Dson::Product(vec![
+                Dson::Labeled {
+                    label: "a b".into(),
+                    expr: Box::new(Dson::Vector(vec![1.into(), "\"x\"".into()])),
+                },
+                Dson::Map(vec![MapElem {
+                    key: Dson::Literal(Literal::Rational(-1, 2)),
+                    value: Dson::Typed {
+                        ty: Type::Brand {
+                            brand: "meter".into(),
+                            item: Box::new(Type::Real),
+                        },
+                        expr: Box::new(Dson::Literal(Literal::Real(Real(2.0)))),
+                    },
+                }]),
+                Dson::Vector(vec![]),
+            ])),
+        }
+    }
+
+    #[test]
+    fn prints_single_line() {
+        assert_eq!(
+            dson().to_string(),
+            "~config\n*<@`a b` [1, \"\\\"x\\\"\"], {-1 / 2 => <@meter 'real> 2.0}, []>"
+        );
+    }
+
+    #[test]
+    fn prints_pretty() {
+        assert_eq!(
+            format!("{:#}", dson()),
+            r#"~config
+*<
+  @`a b` [
+    1,
+    "\"x\"",
+  ],
+  {
+    -1 / 2 => <@meter 'real> 2.0,
+  },
+  [],
+>"#
+        );
+    }
+}
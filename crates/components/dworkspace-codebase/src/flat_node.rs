@@ -33,10 +33,36 @@ impl FlatNode {
         }
     }
 
+    /// Patches the content. Patches for another kind of content are ignored, since they are rejected by the audit.
     pub fn patch_content(&mut self, patch: &ContentPatch) {
-        match patch {
-            ContentPatch::Replace(content) => self.content = content.clone(),
-            _ => todo!(),
+        match (patch, &mut self.content) {
+            (ContentPatch::Replace(content), _) => self.content = content.clone(),
+            (
+                ContentPatch::ChangeSourceCodeSyntax { syntax, source },
+                Content::SourceCode {
+                    syntax: old_syntax,
+                    source: old_source,
+                },
+            ) => {
+                *old_syntax = syntax.clone();
+                *old_source = source.clone();
+            }
+            (ContentPatch::PatchSourceCode(patch), Content::SourceCode { source, .. }) => {
+                *source = patch.apply(source);
+            }
+            (ContentPatch::PatchString(patch), Content::String(string)) => {
+                *string = patch.apply(string);
+            }
+            (ContentPatch::UpdateInteger(value), Content::Integer(integer)) => *integer = *value,
+            (ContentPatch::UpdateReal(value), Content::Real(real)) => *real = *value,
+            (ContentPatch::UpdateRational(a, b), Content::Rational(old_a, old_b)) => {
+                *old_a = *a;
+                *old_b = *b;
+            }
+            (ContentPatch::UpdateApply { link_name }, Content::Apply { link_name: old }) => {
+                *old = *link_name;
+            }
+            _ => {}
         }
     }
 
@@ -100,6 +126,10 @@ impl FlatNode {
 
 #[cfg(test)]
 mod tests {
+    use deskc_ids::LinkName;
+
+    use crate::{code::SyntaxKind, patch::StringPatch};
+
     use super::*;
 
     #[test]
@@ -180,4 +210,100 @@ mod tests {
         });
         assert_eq!(flat_node.operands, vec![node_b, node_c, node_a]);
     }
+
+    #[test]
+    fn patch_source_code() {
+        let mut flat_node = FlatNode::new(Content::SourceCode {
+            syntax: SyntaxKind::Minimalist,
+            source: "^add *<@l 1, @r 2>".into(),
+        });
+        flat_node.patch_content(&ContentPatch::PatchSourceCode(StringPatch::diff(
+            0,
+            "^add *<@l 1, @r 2>",
+            "^sub *<@l 1, @r 3>",
+        )));
+        assert_eq!(
+            flat_node.content,
+            Content::SourceCode {
+                syntax: SyntaxKind::Minimalist,
+                source: "^sub *<@l 1, @r 3>".into(),
+            }
+        );
+        flat_node.patch_content(&ContentPatch::ChangeSourceCodeSyntax {
+            syntax: SyntaxKind::Minimalist,
+            source: "1".into(),
+        });
+        assert_eq!(
+            flat_node.content,
+            Content::SourceCode {
+                syntax: SyntaxKind::Minimalist,
+                source: "1".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn patch_string() {
+        let mut flat_node = FlatNode::new(Content::String("hello world".into()));
+        flat_node.patch_content(&ContentPatch::PatchString(StringPatch::diff(
+            0,
+            "hello world",
+            "hello, world!",
+        )));
+        assert_eq!(flat_node.content, Content::String("hello, world!".into()));
+        flat_node.patch_content(&ContentPatch::PatchString(StringPatch::Replace("a".into())));
+        assert_eq!(flat_node.content, Content::String("a".into()));
+    }
+
+    #[test]
+    fn patch_string_concurrently() {
+        let base = "The quick brown fox";
+        let mut flat_node = FlatNode::new(Content::String(base.into()));
+        flat_node.patch_content(&ContentPatch::PatchString(StringPatch::diff(
+            0,
+            base,
+            "The quick red fox",
+        )));
+        flat_node.patch_content(&ContentPatch::PatchString(StringPatch::diff(
+            0,
+            base,
+            "The quick brown fox jumps",
+        )));
+        assert_eq!(
+            flat_node.content,
+            Content::String("The quick red fox jumps".into())
+        );
+    }
+
+    #[test]
+    fn update_numbers() {
+        let mut flat_node = FlatNode::new(Content::Integer(1));
+        flat_node.patch_content(&ContentPatch::UpdateInteger(-2));
+        assert_eq!(flat_node.content, Content::Integer(-2));
+
+        let mut flat_node = FlatNode::new(Content::Real(1.0));
+        flat_node.patch_content(&ContentPatch::UpdateReal(2.5));
+        assert_eq!(flat_node.content, Content::Real(2.5));
+
+        let mut flat_node = FlatNode::new(Content::Rational(1, 2));
+        flat_node.patch_content(&ContentPatch::UpdateRational(-3, 4));
+        assert_eq!(flat_node.content, Content::Rational(-3, 4));
+    }
+
+    #[test]
+    fn update_apply() {
+        let mut flat_node = FlatNode::new(Content::Apply {
+            link_name: LinkName::None,
+        });
+        let link_name = LinkName::Card(Default::default());
+        flat_node.patch_content(&ContentPatch::UpdateApply { link_name });
+        assert_eq!(flat_node.content, Content::Apply { link_name });
+    }
+
+    #[test]
+    fn ignore_patch_for_another_kind() {
+        let mut flat_node = FlatNode::new(Content::String("a".into()));
+        flat_node.patch_content(&ContentPatch::UpdateInteger(1));
+        assert_eq!(flat_node.content, Content::String("a".into()));
+    }
 }
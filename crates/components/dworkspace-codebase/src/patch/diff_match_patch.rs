@@ -1,3 +1,7 @@
+//! A subset of diff-match-patch working on chars.
+//!
+//! Positions and lengths are counted in chars, not bytes.
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Operation {
     Delete,
@@ -19,3 +23,395 @@ pub struct Patch {
     pub length1: i32,
     pub length2: i32,
 }
+
+/// Number of chars of context around each patch.
+const PATCH_MARGIN: usize = 4;
+
+/// Beyond this many edits between two ranges, the ranges are replaced wholesale to bound the time.
+const MAX_EDIT_DISTANCE: usize = 2048;
+
+/// Computes the difference of two texts with Myers' algorithm in linear space.
+pub fn diff(text1: &str, text2: &str) -> Vec<StringDiff> {
+    let a: Vec<char> = text1.chars().collect();
+    let b: Vec<char> = text2.chars().collect();
+    let mut diffs = Vec::new();
+    diff_chars(&mut diffs, &a, &b);
+    diffs
+}
+
+fn diff_chars(diffs: &mut Vec<StringDiff>, a: &[char], b: &[char]) {
+    let prefix = a.iter().zip(b.iter()).take_while(|(a, b)| a == b).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    push_chars(diffs, Operation::Equal, &a[..prefix]);
+    let (middle_a, middle_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    if middle_a.is_empty() || middle_b.is_empty() {
+        push_chars(diffs, Operation::Delete, middle_a);
+        push_chars(diffs, Operation::Insert, middle_b);
+    } else if let Some((x, y)) = middle_snake(middle_a, middle_b) {
+        diff_chars(diffs, &middle_a[..x], &middle_b[..y]);
+        diff_chars(diffs, &middle_a[x..], &middle_b[y..]);
+    } else {
+        push_chars(diffs, Operation::Delete, middle_a);
+        push_chars(diffs, Operation::Insert, middle_b);
+    }
+    push_chars(diffs, Operation::Equal, &a[a.len() - suffix..]);
+}
+
+/// Finds where the forward and reverse paths of the shortest edit script overlap, searching
+/// from both ends at once. Returns `None` if the distance exceeds `MAX_EDIT_DISTANCE`.
+fn middle_snake(a: &[char], b: &[char]) -> Option<(usize, usize)> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d;
+    let v_len = 2 * max_d + 2;
+    let mut v1 = vec![-1isize; v_len as usize];
+    let mut v2 = vec![-1isize; v_len as usize];
+    v1[offset as usize + 1] = 0;
+    v2[offset as usize + 1] = 0;
+    let delta = n - m;
+    // The paths overlap in the forward search if the delta is odd, otherwise in the reverse.
+    let front = delta % 2 != 0;
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+    for d in 0..max_d.min(MAX_EDIT_DISTANCE as isize / 2 + 1) {
+        let mut k1 = -d + k1_start;
+        while k1 <= d - k1_end {
+            let k1_offset = (offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && v1[k1_offset - 1] < v1[k1_offset + 1]) {
+                v1[k1_offset + 1]
+            } else {
+                v1[k1_offset - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && a[x1 as usize] == b[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            v1[k1_offset] = x1;
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let k2_offset = offset + delta - k1;
+                if (0..v_len).contains(&k2_offset) && v2[k2_offset as usize] != -1 {
+                    let x2 = n - v2[k2_offset as usize];
+                    if x1 >= x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k1 += 2;
+        }
+        let mut k2 = -d + k2_start;
+        while k2 <= d - k2_end {
+            let k2_offset = (offset + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && v2[k2_offset - 1] < v2[k2_offset + 1]) {
+                v2[k2_offset + 1]
+            } else {
+                v2[k2_offset - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && a[(n - x2 - 1) as usize] == b[(m - y2 - 1) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            v2[k2_offset] = x2;
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let k1_offset = offset + delta - k2;
+                if (0..v_len).contains(&k1_offset) && v1[k1_offset as usize] != -1 {
+                    let x1 = v1[k1_offset as usize];
+                    let y1 = offset + x1 - k1_offset;
+                    if x1 >= n - x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k2 += 2;
+        }
+    }
+    None
+}
+
+fn push_chars(diffs: &mut Vec<StringDiff>, operation: Operation, chars: &[char]) {
+    if chars.is_empty() {
+        return;
+    }
+    match diffs.last_mut() {
+        Some(last) if last.operation == operation => last.text.extend(chars),
+        _ => diffs.push(StringDiff {
+            operation,
+            text: chars.iter().collect(),
+        }),
+    }
+}
+
+impl Patch {
+    fn new(start1: usize, start2: usize) -> Self {
+        Self {
+            diffs: vec![],
+            start1: start1 as i32,
+            start2: start2 as i32,
+            length1: 0,
+            length2: 0,
+        }
+    }
+
+    fn push(&mut self, operation: Operation, chars: &[char]) {
+        let len = chars.len() as i32;
+        match operation {
+            Operation::Equal => {
+                self.length1 += len;
+                self.length2 += len;
+            }
+            Operation::Delete => self.length1 += len,
+            Operation::Insert => self.length2 += len,
+        }
+        push_chars(&mut self.diffs, operation, chars);
+    }
+
+    fn text(&self, skip: Operation) -> Vec<char> {
+        self.diffs
+            .iter()
+            .filter(|diff| diff.operation != skip)
+            .flat_map(|diff| diff.text.chars())
+            .collect()
+    }
+}
+
+/// Makes patches that turn `text1` into `text2`.
+pub fn make_patches(text1: &str, text2: &str) -> Vec<Patch> {
+    let diffs = diff(text1, text2);
+    let mut patches = Vec::new();
+    let mut current: Option<Patch> = None;
+    let (mut pos1, mut pos2) = (0, 0);
+    for (i, diff) in diffs.iter().enumerate() {
+        let chars: Vec<char> = diff.text.chars().collect();
+        match diff.operation {
+            Operation::Equal => {
+                if let Some(patch) = current.as_mut() {
+                    if chars.len() <= 2 * PATCH_MARGIN && i != diffs.len() - 1 {
+                        patch.push(Operation::Equal, &chars);
+                    } else {
+                        patch.push(Operation::Equal, &chars[..chars.len().min(PATCH_MARGIN)]);
+                        patches.extend(current.take());
+                    }
+                }
+                pos1 += chars.len();
+                pos2 += chars.len();
+            }
+            Operation::Delete | Operation::Insert => {
+                let patch = current.get_or_insert_with(|| {
+                    let context = match i.checked_sub(1).map(|i| &diffs[i]) {
+                        Some(prev) if prev.operation == Operation::Equal => {
+                            let prev: Vec<char> = prev.text.chars().collect();
+                            prev[prev.len() - prev.len().min(PATCH_MARGIN)..].to_vec()
+                        }
+                        _ => vec![],
+                    };
+                    let mut patch = Patch::new(pos1 - context.len(), pos2 - context.len());
+                    patch.push(Operation::Equal, &context);
+                    patch
+                });
+                patch.push(diff.operation.clone(), &chars);
+                if diff.operation == Operation::Delete {
+                    pos1 += chars.len();
+                } else {
+                    pos2 += chars.len();
+                }
+            }
+        }
+    }
+    patches.extend(current);
+    patches
+}
+
+/// Applies patches to `text`.
+///
+/// A patch is applied where its context is found nearest to the expected location.
+/// Returns the patched text and whether each patch was applied.
+pub fn apply_patches(patches: &[Patch], text: &str) -> (String, Vec<bool>) {
+    let mut chars: Vec<char> = text.chars().collect();
+    let mut delta: isize = 0;
+    let mut results = Vec::with_capacity(patches.len());
+    for patch in patches {
+        let expected = patch.text(Operation::Insert);
+        let replacement = patch.text(Operation::Delete);
+        let expected_loc = patch.start2 as isize + delta;
+        match find_nearest(&chars, &expected, expected_loc) {
+            Some(loc) => {
+                chars.splice(loc..loc + expected.len(), replacement);
+                delta += loc as isize - expected_loc;
+                results.push(true);
+            }
+            None => {
+                // Following patches expect this patch to be applied.
+                delta -= (patch.length2 - patch.length1) as isize;
+                results.push(false);
+            }
+        }
+    }
+    (chars.into_iter().collect(), results)
+}
+
+fn find_nearest(text: &[char], pattern: &[char], expected_loc: isize) -> Option<usize> {
+    if pattern.len() > text.len() {
+        return None;
+    }
+    (0..=text.len() - pattern.len())
+        .filter(|&loc| &text[loc..loc + pattern.len()] == pattern)
+        .min_by_key(|&loc| (loc as isize - expected_loc).abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(operation: Operation, text: &str) -> StringDiff {
+        StringDiff {
+            operation,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn diff_texts() {
+        assert_eq!(diff("", ""), vec![]);
+        assert_eq!(diff("abc", "abc"), vec![d(Operation::Equal, "abc")]);
+        assert_eq!(
+            diff("abc", "ab123c"),
+            vec![
+                d(Operation::Equal, "ab"),
+                d(Operation::Insert, "123"),
+                d(Operation::Equal, "c"),
+            ]
+        );
+        assert_eq!(
+            diff("a123bc", "abc"),
+            vec![
+                d(Operation::Equal, "a"),
+                d(Operation::Delete, "123"),
+                d(Operation::Equal, "bc"),
+            ]
+        );
+        assert_eq!(
+            diff("abc", "xyz"),
+            vec![d(Operation::Delete, "abc"), d(Operation::Insert, "xyz")]
+        );
+    }
+
+    #[test]
+    fn diff_multibyte() {
+        assert_eq!(
+            diff("あいう", "あえう"),
+            vec![
+                d(Operation::Equal, "あ"),
+                d(Operation::Delete, "い"),
+                d(Operation::Insert, "え"),
+                d(Operation::Equal, "う"),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_scattered_edits() {
+        let text1: String = (0..500).map(|i| format!("line {i}\n")).collect();
+        let text2 = text1
+            .replace("line 10\n", "")
+            .replace("line 400", "line four hundred");
+        let diffs = diff(&text1, &text2);
+        let edited: usize = diffs
+            .iter()
+            .filter(|diff| diff.operation != Operation::Equal)
+            .map(|diff| diff.text.chars().count())
+            .sum();
+        assert!(edited < 30);
+        let (patched, _) = apply_patches(&make_patches(&text1, &text2), &text1);
+        assert_eq!(patched, text2);
+    }
+
+    #[test]
+    fn diff_unrelated_large_texts_wholesale() {
+        let text1 = "a".repeat(50_000);
+        let text2 = "b".repeat(50_000);
+        assert_eq!(
+            diff(&text1, &text2),
+            vec![d(Operation::Delete, &text1), d(Operation::Insert, &text2)]
+        );
+        let text2: String = (0..50_000u32)
+            .map(|i| char::from_u32('a' as u32 + i * 7919 % 26).unwrap())
+            .collect();
+        let diffs = diff(&text1, &text2);
+        let (patched, _) = apply_patches(&make_patches(&text1, &text2), &text1);
+        assert_eq!(patched, text2);
+        assert!(!diffs.is_empty());
+    }
+
+    #[test]
+    fn make_patch_with_context() {
+        assert_eq!(
+            make_patches("The quick fox", "The quick brown fox"),
+            vec![Patch {
+                diffs: vec![
+                    d(Operation::Equal, "ick "),
+                    d(Operation::Insert, "brown "),
+                    d(Operation::Equal, "fox"),
+                ],
+                start1: 6,
+                start2: 6,
+                length1: 7,
+                length2: 13,
+            }]
+        );
+    }
+
+    #[test]
+    fn make_separate_patches() {
+        let patches = make_patches("1 aaaaaaaaaaaa 2", "3 aaaaaaaaaaaa 4");
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[1].start1, 11);
+        assert_eq!(patches[1].start2, 11);
+    }
+
+    #[test]
+    fn round_trip() {
+        let cases = [
+            ("", "abc"),
+            ("abc", ""),
+            ("hello world", "hello, brave new world!"),
+            ("1 aaaaaaaaaaaa 2", "3 aaaaaaaaaaaa 4"),
+            ("$ 1; ^add *<@l 1, @r 2>", "$ 2; ^sub *<@l 2, @r 1>"),
+            ("日本語のテキスト", "英語のテキスト"),
+        ];
+        for (text1, text2) in cases {
+            let (patched, results) = apply_patches(&make_patches(text1, text2), text1);
+            assert_eq!(patched, text2);
+            assert!(results.into_iter().all(|applied| applied));
+        }
+    }
+
+    #[test]
+    fn apply_to_shifted_text() {
+        let patches = make_patches("The quick brown fox", "The quick red fox");
+        let (patched, results) = apply_patches(&patches, "Look! The quick brown fox");
+        assert_eq!(patched, "Look! The quick red fox");
+        assert_eq!(results, vec![true]);
+    }
+
+    #[test]
+    fn apply_fails_without_context() {
+        let patches = make_patches("The quick brown fox", "The quick red fox");
+        let (patched, results) = apply_patches(&patches, "Something else");
+        assert_eq!(patched, "Something else");
+        assert_eq!(results, vec![false]);
+    }
+}
@@ -1,11 +1,11 @@
-mod diff_match_patch;
+pub mod diff_match_patch;
 use deskc_ids::{LinkName, NodeId};
 use dson::Dson;
 use ty::Type;
 
 use crate::{code::SyntaxKind, content::Content};
 
-use self::diff_match_patch::Patch;
+use self::diff_match_patch::{apply_patches, make_patches, Patch};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ContentPatch {
@@ -13,16 +13,40 @@ pub enum ContentPatch {
     ChangeSourceCodeSyntax { syntax: SyntaxKind, source: String },
     PatchSourceCode(StringPatch),
     PatchString(StringPatch),
-    UpdateInteger(u64),
+    UpdateInteger(i64),
     UpdateReal(f64),
-    UpdateRational(u64, u64),
-    UpdateApply { ty: Type, link_name: LinkName },
+    UpdateRational(i64, u64),
+    // The type of function is the first operand of the node.
+    UpdateApply { link_name: LinkName },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StringPatch {
     Replace(String),
-    DiffMatchPatch(Vec<Patch>),
+    DiffMatchPatch {
+        /// The content revision of the node the patches are made against.
+        /// The workspace rebases them onto patches applied since the revision.
+        base_revision: usize,
+        patches: Vec<Patch>,
+    },
+}
+
+impl StringPatch {
+    /// Makes a patch that turns `text1` at `base_revision` into `text2`.
+    pub fn diff(base_revision: usize, text1: &str, text2: &str) -> Self {
+        StringPatch::DiffMatchPatch {
+            base_revision,
+            patches: make_patches(text1, text2),
+        }
+    }
+
+    /// Applies the patch. Hunks whose context is not found in `text` are skipped.
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            StringPatch::Replace(string) => string.clone(),
+            StringPatch::DiffMatchPatch { patches, .. } => apply_patches(patches, text).0,
+        }
+    }
 }
 
 // ContentPatch::AddReal should not be NaN
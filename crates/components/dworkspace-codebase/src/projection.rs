@@ -20,7 +20,9 @@ impl Projection {
             EventPayload::AddOwner { user_id } => {
                 self.owners.insert(user_id.clone());
             }
-            EventPayload::RemoveOwner { user_id: _ } => todo!(),
+            EventPayload::RemoveOwner { user_id } => {
+                self.owners.remove(user_id);
+            }
             EventPayload::CreateNode { node_id, content } => {
                 self.flat_nodes
                     .insert(node_id.clone(), FlatNode::new(content.clone()));
@@ -83,6 +85,19 @@ mod tests {
         assert_eq!(snapshot.owners, vec![user_id].into_iter().collect())
     }
 
+    #[test]
+    fn remove_owner() {
+        let mut snapshot = Projection::default();
+        let user_id = UserId::new();
+        snapshot.owners.insert(user_id);
+        snapshot.handle_event(&Event {
+            id: EventId::new(),
+            user_id,
+            payload: EventPayload::RemoveOwner { user_id },
+        });
+        assert!(snapshot.owners.is_empty())
+    }
+
     #[test]
     fn add_node() {
         let mut snapshot = Projection::default();
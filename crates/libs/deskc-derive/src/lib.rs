@@ -0,0 +1,463 @@
+//! `#[derive(Visitor)]` generates visitors and folders over trees of `WithMeta` nodes.
+//!
+//! Every node type names its family of traits with
+//! `#[visitor(visitor = .., visitor_mut = .., fold = ..)]`, and may belong to several families.
+//! The type that also lists `nodes(..)` and `leaves(..)` declares the traits. For each node
+//! `X` they have `visit_x` and `super_visit_x` (`fold_x` and `super_fold_x` for the folder),
+//! and the `super_` methods visit the meta and the fields of the node. Leaves are not walked
+//! into but their meta is visited.
+//!
+//! Fields of `WithMeta<X>` are visited by `visit_x` through `Box`, `Vec` and `Option`.
+//! A field without `WithMeta` is walked into only if it is marked with `#[visitor(walk)]`,
+//! and then its type must derive `Visitor` for the family too.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parenthesized, parse_macro_input, punctuated::Punctuated, Data, DeriveInput, Fields, Ident,
+    Token, Type,
+};
+
+#[proc_macro_derive(Visitor, attributes(visitor))]
+pub fn derive_visitor(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match visitor(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct Family {
+    visitor: Ident,
+    visitor_mut: Ident,
+    fold: Ident,
+    /// `Some` on the type declaring the traits.
+    nodes: Option<Vec<Ident>>,
+    leaves: Vec<Ident>,
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Visit,
+    VisitMut,
+    Fold,
+}
+
+impl Family {
+    fn parse(attr: &syn::Attribute) -> syn::Result<Self> {
+        let mut visitor = None;
+        let mut visitor_mut = None;
+        let mut fold = None;
+        let mut nodes = None;
+        let mut leaves = vec![];
+        attr.parse_nested_meta(|meta| {
+            let idents = || -> syn::Result<Vec<Ident>> {
+                let content;
+                parenthesized!(content in meta.input);
+                let idents = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+                Ok(idents.into_iter().collect())
+            };
+            if meta.path.is_ident("visitor") {
+                visitor = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("visitor_mut") {
+                visitor_mut = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("fold") {
+                fold = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("nodes") {
+                nodes = Some(idents()?);
+            } else if meta.path.is_ident("leaves") {
+                leaves = idents()?;
+            } else {
+                return Err(
+                    meta.error("expected `visitor`, `visitor_mut`, `fold`, `nodes` or `leaves`")
+                );
+            }
+            Ok(())
+        })?;
+        let missing = |name| syn::Error::new_spanned(attr, format!("`{name}` is required"));
+        Ok(Self {
+            visitor: visitor.ok_or_else(|| missing("visitor"))?,
+            visitor_mut: visitor_mut.ok_or_else(|| missing("visitor_mut"))?,
+            fold: fold.ok_or_else(|| missing("fold"))?,
+            nodes,
+            leaves,
+        })
+    }
+
+    fn walk_method(&self, mode: Mode) -> Ident {
+        let name = match mode {
+            Mode::Visit => &self.visitor,
+            Mode::VisitMut => &self.visitor_mut,
+            Mode::Fold => &self.fold,
+        };
+        format_ident!("walk_{}", snake_case(&name.to_string()))
+    }
+}
+
+fn visitor(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let families = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("visitor"))
+        .map(Family::parse)
+        .collect::<syn::Result<Vec<_>>>()?;
+    if families.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`#[visitor(..)]` is required",
+        ));
+    }
+    let mut tokens = TokenStream2::new();
+    for family in &families {
+        tokens.extend(walks(input, family)?);
+        if family.nodes.is_some() {
+            tokens.extend(traits(input, family));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Generates the methods walking into the fields of the type, which the traits call.
+fn walks(input: &DeriveInput, family: &Family) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let visitor = &family.visitor;
+    let visitor_mut = &family.visitor_mut;
+    let fold = &family.fold;
+    let walk = family.walk_method(Mode::Visit);
+    let walk_mut = family.walk_method(Mode::VisitMut);
+    let walk_fold = family.walk_method(Mode::Fold);
+    let visit = body(input, family, Mode::Visit)?;
+    let visit_mut = body(input, family, Mode::VisitMut)?;
+    let fold_body = body(input, family, Mode::Fold)?;
+    Ok(quote! {
+        impl #ident {
+            #[doc(hidden)]
+            #[allow(unused_variables)]
+            pub fn #walk<V: #visitor + ?Sized>(&self, visitor: &mut V) {
+                #visit
+            }
+            #[doc(hidden)]
+            #[allow(unused_variables)]
+            pub fn #walk_mut<V: #visitor_mut + ?Sized>(&mut self, visitor: &mut V) {
+                #visit_mut
+            }
+            #[doc(hidden)]
+            #[allow(unused_variables)]
+            pub fn #walk_fold<F: #fold + ?Sized>(self, folder: &mut F) -> Self {
+                #fold_body
+            }
+        }
+    })
+}
+
+fn body(input: &DeriveInput, family: &Family, mode: Mode) -> syn::Result<TokenStream2> {
+    match &input.data {
+        Data::Struct(data) => {
+            let (pattern, walks) = fields(&input.ident, &data.fields, family, mode)?;
+            Ok(match mode {
+                Mode::Visit | Mode::VisitMut => quote! {
+                    let Self #pattern = self;
+                    #walks
+                },
+                Mode::Fold => quote! {
+                    let Self #pattern = self;
+                    Self #walks
+                },
+            })
+        }
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let name = &variant.ident;
+                    let (pattern, walks) = fields(&input.ident, &variant.fields, family, mode)?;
+                    Ok(match mode {
+                        Mode::Visit | Mode::VisitMut => quote! {
+                            Self::#name #pattern => { #walks }
+                        },
+                        Mode::Fold => quote! {
+                            Self::#name #pattern => Self::#name #walks,
+                        },
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            Ok(quote! {
+                match self {
+                    #(#arms)*
+                }
+            })
+        }
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input.ident,
+            "unions are not supported",
+        )),
+    }
+}
+
+/// Returns the pattern binding the fields, and the statements visiting them or the fields
+/// of the folded value.
+fn fields(
+    self_ty: &Ident,
+    fields: &Fields,
+    family: &Family,
+    mode: Mode,
+) -> syn::Result<(TokenStream2, TokenStream2)> {
+    let mut patterns = vec![];
+    let mut walks = vec![];
+    for (index, field) in fields.iter().enumerate() {
+        let binding = field
+            .ident
+            .clone()
+            .unwrap_or_else(|| format_ident!("field{index}"));
+        let walk = walk(
+            &field.ty,
+            self_ty,
+            is_walked(field)?,
+            family,
+            mode,
+            quote!(#binding),
+        );
+        let pattern = match (&field.ident, mode, &walk) {
+            (Some(ident), Mode::Visit | Mode::VisitMut, None) => quote!(#ident: _),
+            (None, Mode::Visit | Mode::VisitMut, None) => quote!(_),
+            (Some(ident), _, _) => quote!(#ident),
+            (None, _, _) => quote!(#binding),
+        };
+        patterns.push(pattern);
+        match (mode, &field.ident) {
+            (Mode::Visit | Mode::VisitMut, _) => walks.extend(walk),
+            (Mode::Fold, Some(ident)) => {
+                let value = walk.unwrap_or_else(|| quote!(#binding));
+                walks.push(quote!(#ident: #value));
+            }
+            (Mode::Fold, None) => walks.push(walk.unwrap_or_else(|| quote!(#binding))),
+        }
+    }
+    Ok(match (fields, mode) {
+        (Fields::Named(_), Mode::Fold) => (quote!({ #(#patterns),* }), quote!({ #(#walks),* })),
+        (Fields::Unnamed(_), Mode::Fold) => (quote!((#(#patterns),*)), quote!((#(#walks),*))),
+        (Fields::Named(_), _) => (quote!({ #(#patterns),* }), quote!(#(#walks)*)),
+        (Fields::Unnamed(_), _) => (quote!((#(#patterns),*)), quote!(#(#walks)*)),
+        (Fields::Unit, _) => (quote!(), quote!()),
+    })
+}
+
+fn is_walked(field: &syn::Field) -> syn::Result<bool> {
+    let mut walked = false;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("visitor"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("walk") {
+                walked = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `walk`"))
+            }
+        })?;
+    }
+    Ok(walked)
+}
+
+/// Returns `None` if there is nothing to visit in the value. A statement is returned for
+/// visitors and an expression for the folder.
+fn walk(
+    ty: &Type,
+    self_ty: &Ident,
+    walked: bool,
+    family: &Family,
+    mode: Mode,
+    value: TokenStream2,
+) -> Option<TokenStream2> {
+    if let Some(node) = generic_argument(ty, "WithMeta") {
+        let node = type_name(node)?;
+        let node = if node == "Self" {
+            self_ty.clone()
+        } else {
+            node.clone()
+        };
+        let node = snake_case(&node.to_string());
+        return Some(match mode {
+            Mode::Visit | Mode::VisitMut => {
+                let method = format_ident!("visit_{node}");
+                quote!(visitor.#method(#value);)
+            }
+            Mode::Fold => {
+                let method = format_ident!("fold_{node}");
+                quote!(folder.#method(#value))
+            }
+        });
+    }
+    let item = quote!(item);
+    if let Some(inner) = generic_argument(ty, "Box") {
+        return Some(match mode {
+            Mode::Visit | Mode::VisitMut => walk(inner, self_ty, walked, family, mode, value)?,
+            Mode::Fold => {
+                let inner = walk(inner, self_ty, walked, family, mode, quote!((*#value)))?;
+                quote!(Box::new(#inner))
+            }
+        });
+    }
+    if let Some(inner) = generic_argument(ty, "Vec") {
+        let inner = walk(inner, self_ty, walked, family, mode, item.clone())?;
+        return Some(match mode {
+            Mode::Visit | Mode::VisitMut => quote!(for #item in #value { #inner }),
+            Mode::Fold => quote!(#value.into_iter().map(|#item| #inner).collect()),
+        });
+    }
+    if let Some(inner) = generic_argument(ty, "Option") {
+        let inner = walk(inner, self_ty, walked, family, mode, item.clone())?;
+        return Some(match mode {
+            Mode::Visit | Mode::VisitMut => quote!(if let Some(#item) = #value { #inner }),
+            Mode::Fold => quote!(#value.map(|#item| #inner)),
+        });
+    }
+    if !walked {
+        return None;
+    }
+    let method = family.walk_method(mode);
+    Some(match mode {
+        Mode::Visit | Mode::VisitMut => quote!(#value.#method(visitor);),
+        Mode::Fold => quote!(#value.#method(folder)),
+    })
+}
+
+/// Generates the traits of the family.
+fn traits(input: &DeriveInput, family: &Family) -> TokenStream2 {
+    let vis = &input.vis;
+    let visitor = &family.visitor;
+    let visitor_mut = &family.visitor_mut;
+    let fold = &family.fold;
+    let walk = family.walk_method(Mode::Visit);
+    let walk_mut = family.walk_method(Mode::VisitMut);
+    let walk_fold = family.walk_method(Mode::Fold);
+    let nodes: Vec<_> = std::iter::once(&input.ident)
+        .chain(family.nodes.iter().flatten())
+        .collect();
+    let mut visit = vec![];
+    let mut visit_mut = vec![];
+    let mut fold_methods = vec![];
+    for node in nodes {
+        let name = snake_case(&node.to_string());
+        let method = format_ident!("visit_{name}");
+        let super_method = format_ident!("super_visit_{name}");
+        let fold_method = format_ident!("fold_{name}");
+        let super_fold_method = format_ident!("super_fold_{name}");
+        visit.push(quote! {
+            fn #method(&mut self, node: &WithMeta<#node>) {
+                self.#super_method(node);
+            }
+            fn #super_method(&mut self, node: &WithMeta<#node>) {
+                self.visit_meta(&node.meta);
+                node.value.#walk(self);
+            }
+        });
+        visit_mut.push(quote! {
+            fn #method(&mut self, node: &mut WithMeta<#node>) {
+                self.#super_method(node);
+            }
+            fn #super_method(&mut self, node: &mut WithMeta<#node>) {
+                self.visit_meta(&mut node.meta);
+                node.value.#walk_mut(self);
+            }
+        });
+        fold_methods.push(quote! {
+            fn #fold_method(&mut self, node: WithMeta<#node>) -> WithMeta<#node> {
+                self.#super_fold_method(node)
+            }
+            fn #super_fold_method(&mut self, node: WithMeta<#node>) -> WithMeta<#node> {
+                WithMeta {
+                    meta: self.fold_meta(node.meta),
+                    value: node.value.#walk_fold(self),
+                }
+            }
+        });
+    }
+    for leaf in &family.leaves {
+        let name = snake_case(&leaf.to_string());
+        let method = format_ident!("visit_{name}");
+        let fold_method = format_ident!("fold_{name}");
+        visit.push(quote! {
+            fn #method(&mut self, node: &WithMeta<#leaf>) {
+                self.visit_meta(&node.meta);
+            }
+        });
+        visit_mut.push(quote! {
+            fn #method(&mut self, node: &mut WithMeta<#leaf>) {
+                self.visit_meta(&mut node.meta);
+            }
+        });
+        fold_methods.push(quote! {
+            fn #fold_method(&mut self, node: WithMeta<#leaf>) -> WithMeta<#leaf> {
+                WithMeta {
+                    meta: self.fold_meta(node.meta),
+                    value: node.value,
+                }
+            }
+        });
+    }
+    quote! {
+        /// Generated by `#[derive(Visitor)]`.
+        #vis trait #visitor {
+            #(#visit)*
+            fn visit_meta(&mut self, _meta: &Meta) {}
+        }
+
+        /// Generated by `#[derive(Visitor)]`.
+        #vis trait #visitor_mut {
+            #(#visit_mut)*
+            fn visit_meta(&mut self, _meta: &mut Meta) {}
+        }
+
+        /// Generated by `#[derive(Visitor)]`.
+        #vis trait #fold {
+            #(#fold_methods)*
+            fn fold_meta(&mut self, meta: Meta) -> Meta {
+                meta
+            }
+        }
+    }
+}
+
+/// Returns `T` of `name<T>`.
+fn generic_argument<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+fn type_name(ty: &Type) -> Option<&Ident> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    Some(&path.path.segments.last()?.ident)
+}
+
+fn snake_case(ident: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use ast::{
     expr::{Expr, LinkName},
     meta::{Comment, Meta, WithMeta},
@@ -5,25 +7,24 @@ use ast::{
     ty::{Effect, EffectExpr, Function, Type},
 };
 use dson::{Dson, MapElem};
+use parol_runtime::{ParolError, ParserError};
+use parser::MinimalistSyntaxError;
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::{Span, TokenTree};
+use quote::{quote, quote_spanned};
 use uuid::Uuid;
 
 #[proc_macro]
 pub fn ty(item: TokenStream) -> TokenStream {
-    fn input(string: String) -> String {
-        "&".to_string() + &string
-    }
-    fn map(expr: &WithMeta<Expr>) -> proc_macro2::TokenStream {
-        if let Expr::Apply { function, .. } = &expr.value {
-            from_type(&function.value)
-        } else {
-            panic!("Failed to parse reference")
-        }
+    fn map(expr: &WithMeta<Expr>) -> Result<proc_macro2::TokenStream, String> {
+        let Expr::Apply { function, .. } = &expr.value else {
+            return Err("expected a type".into());
+        };
+        Ok(from_type(&function.value))
     }
     parse(
         item,
-        input,
+        ("&", ""),
         map,
         quote! {
             use deskc_type::{Effect, Function, Type, EffectExpr};
@@ -34,25 +35,23 @@ pub fn ty(item: TokenStream) -> TokenStream {
 
 #[proc_macro]
 pub fn effect(item: TokenStream) -> TokenStream {
-    fn input(string: String) -> String {
-        format!("& ! {{ {string} }} 'integer")
-    }
-    fn map(expr: &WithMeta<Expr>) -> proc_macro2::TokenStream {
-        let Expr::Apply { function, .. } = &expr.value
-         else {
-            panic!("Failed to parse reference")
+    fn map(expr: &WithMeta<Expr>) -> Result<proc_macro2::TokenStream, String> {
+        let Expr::Apply { function, .. } = &expr.value else {
+            return Err("expected an effect".into());
         };
         let Type::Effectful { ty: _, effects } = &function.value else {
-            panic!("Failed to parse effectful")
-        };
-        let EffectExpr::Effects(effects) = &effects.value else {
-            panic!("Failed to parse effects")
+            return Err("expected an effect".into());
         };
-        from_effect(&effects[0].value)
+        match &effects.value {
+            EffectExpr::Effects(effects) if effects.len() == 1 => {
+                Ok(from_effect(&effects[0].value))
+            }
+            _ => Err("expected exactly one effect".into()),
+        }
     }
     parse(
         item,
-        input,
+        ("& ! { ", " } 'integer"),
         map,
         quote! {
             use deskc_type::{Effect, Function, Type, EffectExpr};
@@ -63,18 +62,15 @@ pub fn effect(item: TokenStream) -> TokenStream {
 
 #[proc_macro]
 pub fn dson(item: TokenStream) -> TokenStream {
-    fn input(string: String) -> String {
-        format!("# {string} 1")
-    }
-    fn map(expr: &WithMeta<Expr>) -> proc_macro2::TokenStream {
+    fn map(expr: &WithMeta<Expr>) -> Result<proc_macro2::TokenStream, String> {
         let Expr::Attributed { attr, item: _ } = &expr.value else {
-            panic!("Failed to parse label")
+            return Err("expected a dson".into());
         };
-        from_dson(attr)
+        Ok(from_dson(attr))
     }
     parse(
         item,
-        input,
+        ("# ", " 1"),
         map,
         quote! {
             use dson::{Dson, Literal};
@@ -84,15 +80,12 @@ pub fn dson(item: TokenStream) -> TokenStream {
 
 #[proc_macro]
 pub fn ast(item: TokenStream) -> TokenStream {
-    fn input(string: String) -> String {
-        string
-    }
-    fn map(expr: &WithMeta<Expr>) -> proc_macro2::TokenStream {
-        from_expr(expr)
+    fn map(expr: &WithMeta<Expr>) -> Result<proc_macro2::TokenStream, String> {
+        Ok(from_expr(expr))
     }
     parse(
         item,
-        input,
+        ("", ""),
         map,
         quote! {
             use deskc_ast::{
@@ -106,50 +99,131 @@ pub fn ast(item: TokenStream) -> TokenStream {
     )
 }
 
+/// Yields the `Cards` HIR of a file. HIR generation is checked at compile time and
+/// done again at runtime by `deskc_hirgen`, which is the only crate users depend on.
+#[proc_macro]
+pub fn card(item: TokenStream) -> TokenStream {
+    fn map(expr: &WithMeta<Expr>) -> Result<proc_macro2::TokenStream, String> {
+        hirgen::gen_cards(expr).map_err(|err| err.to_string())?;
+        let expr = from_expr(expr);
+        Ok(quote! {
+            ::deskc_hirgen::gen_cards(&#expr)
+                .expect("HIR generation is checked at compile time")
+                .1
+        })
+    }
+    parse(
+        item,
+        ("", ""),
+        map,
+        quote! {
+            use ::deskc_hirgen::macro_support::{
+                ast::{
+                    expr::{Expr, Literal, MatchCase},
+                    meta::{WithMeta, Meta, Comment, Comments},
+                    ty::{Effect, EffectExpr, Function, Type},
+                },
+                dson::{self, Dson},
+                ids::{CardId, LinkName, NodeId},
+                uuid,
+            };
+        },
+    )
+}
+
+/// `wrapper` is put around the string to make it an expression, and `map` picks out the
+/// part of the expression to be generated.
 fn parse(
     item: TokenStream,
-    input: fn(String) -> String,
-    map: fn(&WithMeta<Expr>) -> proc_macro2::TokenStream,
+    wrapper: (&str, &str),
+    map: fn(&WithMeta<Expr>) -> Result<proc_macro2::TokenStream, String>,
     uses: proc_macro2::TokenStream,
 ) -> TokenStream {
-    if let Some(literal) = item.into_iter().next() {
-        let string = literal.to_string();
-        let string = if string.starts_with('"') {
-            // Remove the quotes
-            string[1..string.len() - 1].to_string()
-        } else if string.starts_with("r#") {
-            // Remove the delimiters
-            string[3..string.len() - 2].to_string()
-        } else {
-            return quote! {
-                compile_error!("The first argument must be a string literal")
-            }
-            .into();
-        };
-        match parser::MinimalistSyntaxParser::parse(&input(string)) {
-            Ok(expr) => {
-                let tokens = map(&expr.expr);
-                quote! {
-                    {
-                        #uses
-                        #tokens
-                    }
-                }
-                .into()
-            }
-            Err(err) => {
-                let err = format!("{err:?}");
-                quote! {
-                    compile_error!(#err)
-                }
-                .into()
-            }
-        }
-    } else {
-        quote! {
+    let Some((literal, string, start)) = string_literal(item.into()) else {
+        return quote! {
             compile_error!("The first argument must be a string literal")
         }
-        .into()
+        .into();
+    };
+    let (prefix, suffix) = wrapper;
+    let tokens = match parser::MinimalistSyntaxParser::parse(&format!("{prefix}{string}{suffix}")) {
+        Ok(parsed) => map(&parsed.expr).map_err(|message| (message, None)),
+        Err(err) => {
+            // Positions are shifted from the wrapped input to the string.
+            let range = error_range(&err)
+                .map(|range| {
+                    let shift =
+                        |offset: usize| offset.saturating_sub(prefix.len()).min(string.len());
+                    shift(range.start)..shift(range.end)
+                })
+                .filter(|range| !range.is_empty());
+            Err((error_message(&err), range))
+        }
+    };
+    match tokens {
+        Ok(tokens) => quote! {
+            {
+                #uses
+                #tokens
+            }
+        }
+        .into(),
+        Err((message, range)) => {
+            // `Literal::subspan` always returns `None` on stable, where the whole literal is
+            // pointed, so the message quotes the erroneous part of the string.
+            let message = match range.clone().and_then(|range| string.get(range)) {
+                Some(found) => format!("{message}\nfound `{found}` in the string"),
+                None => message,
+            };
+            let span = range
+                .and_then(|range| literal.subspan(start + range.start..start + range.end))
+                .unwrap_or_else(|| literal.span());
+            compile_error(span, &message).into()
+        }
+    }
+}
+
+/// Returns the literal, its contents and the byte offset of the contents in the literal.
+fn string_literal(item: proc_macro2::TokenStream) -> Option<(proc_macro2::Literal, String, usize)> {
+    let Some(TokenTree::Literal(literal)) = item.into_iter().next() else {
+        return None;
+    };
+    let source = literal.to_string();
+    let (start, end) = if source.starts_with('"') {
+        (1, source.len() - 1)
+    } else if let Some(raw) = source.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        (hashes + 2, source.len() - hashes - 1)
+    } else {
+        return None;
+    };
+    let string = source.get(start..end)?.to_string();
+    Some((literal, string, start))
+}
+
+fn error_range(err: &MinimalistSyntaxError) -> Option<Range<usize>> {
+    let MinimalistSyntaxError::ParseError(ParolError::ParserError(
+        ParserError::PredictionErrorWithExpectations { error_location, .. },
+    )) = err
+    else {
+        return None;
+    };
+    // `offset` is the end of the token.
+    let end = error_location.scanner_switch_pos + error_location.offset;
+    Some(end - error_location.length..end)
+}
+
+fn error_message(err: &MinimalistSyntaxError) -> String {
+    match err {
+        // Not the `Debug` output of `MinimalistSyntaxError`, which dumps the whole input.
+        MinimalistSyntaxError::ParseError(err) => err.to_string(),
+        err => err.to_string(),
+    }
+}
+
+fn compile_error(span: Span, message: &str) -> proc_macro2::TokenStream {
+    quote_spanned! {span=>
+        compile_error!(#message)
     }
 }
 
@@ -0,0 +1,5 @@
+fn main() {
+    deskc_macros::card!(
+        "'card 00000000-0000-0000-0000-000000000001 'card 00000000-0000-0000-0000-000000000002 1; 2; 3"
+    );
+}
@@ -0,0 +1,3 @@
+fn main() {
+    deskc_macros::card!("*<1, ) 2>");
+}
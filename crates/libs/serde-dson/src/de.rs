@@ -1,5 +1,11 @@
-use crate::{Error, Result};
-use dson::{Dson, Literal, MapElem};
+use std::{
+    num::{IntErrorKind, ParseIntError},
+    ptr,
+    str::FromStr,
+};
+
+use crate::{Error, Result, BIG_INTEGER};
+use dson::{Dson, Literal, MapElem, Type};
 use serde::{
     de::{self, Deserialize, EnumAccess, MapAccess, SeqAccess, VariantAccess},
     forward_to_deserialize_any,
@@ -21,13 +27,184 @@ impl Deserializer {
     }
 }
 
-fn unwrap(dson: &mut Dson) {
-    while let Dson::Attributed { expr, .. }
-    | Dson::Labeled { expr, .. }
-    | Dson::Comment { expr, .. }
-    | Dson::Typed { expr, .. } = dson
-    {
+/// Unlike `deserialize_any`, which exposes a label as a map with a single entry, these look through labels.
+macro_rules! deserialize_through_labels {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                unwrap_labels(&mut self.0)?;
+                self.deserialize_any(visitor)
+            }
+        )*
+    };
+}
+
+macro_rules! deserialize_integers {
+    ($($method:ident $ty:ident $visit:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                visitor.$visit(integer::<$ty>(&self.0, stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+/// Accepts both integer literals and big integers, looking through other labels.
+fn integer<T>(dson: &Dson, ty: &'static str) -> Result<T>
+where
+    T: TryFrom<i64> + FromStr<Err = ParseIntError>,
+{
+    let overflow = || Error::IntegerOverflow {
+        ty,
+        got: dson.clone(),
+    };
+    let mut current = dson.clone();
+    loop {
+        unwrap(&mut current)?;
+        match current {
+            Dson::Literal(Literal::Integer(int)) => {
+                return T::try_from(int).map_err(|_| overflow())
+            }
+            Dson::Labeled { label, expr } if label == BIG_INTEGER => {
+                let mut expr = *expr;
+                unwrap(&mut expr)?;
+                return match &expr {
+                    Dson::Literal(Literal::String(digits)) => {
+                        digits
+                            .parse()
+                            .map_err(|err: ParseIntError| match err.kind() {
+                                IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => overflow(),
+                                _ => Error::ExpectedInteger { got: dson.clone() },
+                            })
+                    }
+                    _ => Err(Error::ExpectedInteger { got: dson.clone() }),
+                };
+            }
+            Dson::Labeled { expr, .. } => current = *expr,
+            _ => return Err(Error::ExpectedInteger { got: dson.clone() }),
+        }
+    }
+}
+
+/// Skips comments and attributes, and checks typed values against their types.
+fn unwrap(dson: &mut Dson) -> Result<()> {
+    loop {
+        match dson {
+            Dson::Attributed { expr, .. } | Dson::Comment { expr, .. } => {
+                *dson = (**expr).clone();
+            }
+            Dson::Typed { ty, expr } => {
+                if !conforms_to(expr, ty, &mut vec![], &mut vec![]) {
+                    return Err(Error::TypeMismatch {
+                        ty: ty.clone(),
+                        got: (**expr).clone(),
+                    });
+                }
+                *dson = (**expr).clone();
+            }
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Also looks through labels, for targets that are not serialized as labels.
+fn unwrap_labels(dson: &mut Dson) -> Result<()> {
+    unwrap(dson)?;
+    while let Dson::Labeled { expr, .. } = dson {
         *dson = (**expr).clone();
+        unwrap(dson)?;
+    }
+    Ok(())
+}
+
+/// `variables` holds the definitions of enclosing `Type::Let`s, innermost last, and `expanding`
+/// holds the definitions being expanded with the values they are checked against.
+fn conforms_to<'a, 'd>(
+    dson: &'d Dson,
+    ty: &'a Type,
+    variables: &mut Vec<(&'a str, &'a Type)>,
+    expanding: &mut Vec<(&'a Type, &'d Dson)>,
+) -> bool {
+    match (dson, ty) {
+        (Dson::Attributed { expr, .. } | Dson::Comment { expr, .. }, ty) => {
+            conforms_to(expr, ty, variables, expanding)
+        }
+        (Dson::Typed { ty: inner, expr }, ty) => {
+            conforms_to(expr, inner, &mut vec![], &mut vec![])
+                && conforms_to(expr, ty, variables, expanding)
+        }
+        (_, Type::Attributed { ty, .. } | Type::Comment { item: ty, .. }) => {
+            conforms_to(dson, ty, variables, expanding)
+        }
+        (
+            _,
+            Type::Let {
+                variable,
+                definition,
+                body,
+            },
+        ) => {
+            variables.push((variable, definition));
+            let conforms = conforms_to(dson, body, variables, expanding);
+            variables.pop();
+            conforms
+        }
+        (_, Type::Variable(name)) => {
+            match variables.iter().rposition(|(variable, _)| variable == name) {
+                // A definition only sees the variables defined outside of it and itself.
+                Some(index) => {
+                    let definition = variables[index].1;
+                    // Expanding a definition again for the same value never reaches the value,
+                    // as in `$a a; a`, so the expansion does not conform.
+                    if expanding.iter().any(|(expanded, value)| {
+                        ptr::eq(*expanded, definition) && ptr::eq(*value, dson)
+                    }) {
+                        return false;
+                    }
+                    let mut outer = variables[..=index].to_vec();
+                    expanding.push((definition, dson));
+                    let conforms = conforms_to(dson, definition, &mut outer, expanding);
+                    expanding.pop();
+                    conforms
+                }
+                // Free variables are not checked.
+                None => true,
+            }
+        }
+        (Dson::Labeled { label, expr }, Type::Brand { brand, item }) if label == brand => {
+            conforms_to(expr, item, variables, expanding)
+        }
+        (_, Type::Brand { item, .. }) => conforms_to(dson, item, variables, expanding),
+        (Dson::Literal(literal), Type::Integer) => matches!(literal, Literal::Integer(_)),
+        (Dson::Literal(literal), Type::Rational) => {
+            matches!(literal, Literal::Integer(_) | Literal::Rational(..))
+        }
+        (Dson::Literal(literal), Type::Real) => !matches!(literal, Literal::String(_)),
+        (Dson::Literal(literal), Type::String) => matches!(literal, Literal::String(_)),
+        (_, Type::Sum(types)) => types
+            .iter()
+            .any(|ty| conforms_to(dson, ty, variables, expanding)),
+        (Dson::Product(values), Type::Product(types)) => {
+            values.len() == types.len()
+                && values
+                    .iter()
+                    .zip(types)
+                    .all(|(value, ty)| conforms_to(value, ty, variables, expanding))
+        }
+        (Dson::Vector(values), Type::Vector(ty)) => values
+            .iter()
+            .all(|value| conforms_to(value, ty, variables, expanding)),
+        (Dson::Map(elems), Type::Map { key, value }) => elems.iter().all(|elem| {
+            conforms_to(&elem.key, key, variables, expanding)
+                && conforms_to(&elem.value, value, variables, expanding)
+        }),
+        _ => false,
     }
 }
 
@@ -38,38 +215,71 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: de::Visitor<'de>,
     {
-        unwrap(&mut self.0);
+        unwrap(&mut self.0)?;
         match &self.0 {
             Dson::Literal(Literal::Integer(int)) => visitor.visit_i64(*int),
             Dson::Literal(Literal::Real(float)) => visitor.visit_f64(float.0),
             Dson::Literal(Literal::Rational(a, b)) => visitor.visit_f64(*a as f64 / *b as f64),
             Dson::Literal(Literal::String(string)) => visitor.visit_string(string.clone()),
-            Dson::Product(values) => {
-                if values.is_empty() {
-                    visitor.visit_unit()
-                } else {
-                    visitor.visit_seq(ValuesDeserializer::new(values.clone()))
-                }
-            }
+            Dson::Product(values) if values.is_empty() => visitor.visit_unit(),
+            // A product of labeled values is a struct.
+            Dson::Product(values) => match labeled_fields(values) {
+                Ok(fields) => visitor.visit_map(MapDeserializer::new(fields)),
+                Err(_) => visitor.visit_seq(ValuesDeserializer::new(values.clone())),
+            },
             Dson::Vector(values) => visitor.visit_seq(ValuesDeserializer::new(values.clone())),
             Dson::Map(values) => visitor.visit_map(MapDeserializer::new(values.clone())),
-            // These must be handled in unwrap(&mut self.0).
-            Dson::Labeled { .. } => panic!(),
-            Dson::Attributed { .. } => panic!(),
-            Dson::Typed { .. } => panic!(),
-            Dson::Comment { .. } => todo!(),
+            Dson::Labeled { label, expr } => match (label.as_str(), expr.as_ref()) {
+                (BIG_INTEGER, Dson::Literal(Literal::String(digits))) => {
+                    if let Ok(int) = digits.parse::<u64>() {
+                        visitor.visit_u64(int)
+                    } else if let Ok(int) = digits.parse::<u128>() {
+                        visitor.visit_u128(int)
+                    } else {
+                        visitor.visit_i128(integer(&self.0, "i128")?)
+                    }
+                }
+                ("true", Dson::Product(values)) if values.is_empty() => visitor.visit_bool(true),
+                ("false", Dson::Product(values)) if values.is_empty() => visitor.visit_bool(false),
+                // Same as the externally tagged representation of enums.
+                _ => visitor.visit_map(MapDeserializer::new(vec![MapElem {
+                    key: Dson::Literal(Literal::String(label.clone())),
+                    value: *expr.clone(),
+                }])),
+            },
+            // These are handled in unwrap(&mut self.0).
+            Dson::Attributed { .. } | Dson::Typed { .. } | Dson::Comment { .. } => unreachable!(),
         }
     }
 
     forward_to_deserialize_any! {
-        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf unit seq identifier ignored_any
+        ignored_any
+    }
+
+    deserialize_integers! {
+        deserialize_i8 i8 visit_i8
+        deserialize_i16 i16 visit_i16
+        deserialize_i32 i32 visit_i32
+        deserialize_i64 i64 visit_i64
+        deserialize_i128 i128 visit_i128
+        deserialize_u8 u8 visit_u8
+        deserialize_u16 u16 visit_u16
+        deserialize_u32 u32 visit_u32
+        deserialize_u64 u64 visit_u64
+        deserialize_u128 u128 visit_u128
+    }
+
+    deserialize_through_labels! {
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_unit deserialize_seq
+        deserialize_identifier
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        unwrap(&mut self.0)?;
         match &self.0 {
             Dson::Labeled { label: v, expr: _ } if v == "true" => visitor.visit_bool(true),
             Dson::Labeled { label: v, expr: _ } if v == "false" => visitor.visit_bool(false),
@@ -83,16 +293,28 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: de::Visitor<'de>,
     {
+        unwrap(&mut self.0)?;
         match &self.0 {
             Dson::Product(values) if values.is_empty() => visitor.visit_none(),
             _ => visitor.visit_some(self),
         }
     }
 
-    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        unwrap(&mut self.0)?;
+        if let Dson::Labeled { label, expr } = &self.0 {
+            if label != name {
+                return Err(Error::LabelMismatch {
+                    expected: name.into(),
+                    got: label.clone(),
+                });
+            }
+            self.0 = *expr.clone();
+            unwrap(&mut self.0)?;
+        }
         match &self.0 {
             Dson::Product(values) if values.is_empty() => visitor.visit_unit(),
             _ => Err(Error::ExpectedProduct {
@@ -105,6 +327,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: de::Visitor<'de>,
     {
+        unwrap(&mut self.0)?;
         match &self.0 {
             Dson::Map(elems) => {
                 let values = elems
@@ -133,6 +356,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: de::Visitor<'de>,
     {
+        unwrap(&mut self.0)?;
         match &self.0 {
             Dson::Labeled {
                 label: variant,
@@ -148,6 +372,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: de::Visitor<'de>,
     {
+        unwrap(&mut self.0)?;
         match &self.0 {
             Dson::Labeled { label, expr } => {
                 if name == label {
@@ -170,6 +395,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: de::Visitor<'de>,
     {
+        unwrap(&mut self.0)?;
         match &self.0 {
             Dson::Product(values) => visitor.visit_seq(ValuesDeserializer::new(values.clone())),
             _ => Err(Error::ExpectedProduct {
@@ -187,7 +413,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: de::Visitor<'de>,
     {
-        unwrap(&mut self.0);
+        unwrap_labels(&mut self.0)?;
         match &self.0 {
             Dson::Product(values) => visitor.visit_seq(ValuesDeserializer::new(values.clone())),
             _ => Err(Error::ExpectedProduct {
@@ -205,22 +431,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     where
         V: de::Visitor<'de>,
     {
-        unwrap(&mut self.0);
+        unwrap_labels(&mut self.0)?;
         match &self.0 {
             Dson::Product(values) => {
-                let values = values
-                    .iter()
-                    .map(|dson| match dson {
-                        Dson::Labeled { label, expr } => Ok(MapElem {
-                            key: Dson::Literal(Literal::String(label.clone())),
-                            value: *expr.clone(),
-                        }),
-                        _ => Err(Error::ExpectedLabel {
-                            got: (*dson).clone(),
-                        }),
-                    })
-                    .collect::<Result<Vec<_>>>()?;
-                visitor.visit_map(MapDeserializer::new(values))
+                visitor.visit_map(MapDeserializer::new(labeled_fields(values)?))
             }
             _ => Err(Error::ExpectedProduct {
                 got: self.0.clone(),
@@ -229,6 +443,24 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer {
     }
 }
 
+/// The fields of a struct, which may be commented or attributed.
+fn labeled_fields(values: &[Dson]) -> Result<Vec<MapElem>> {
+    values
+        .iter()
+        .map(|dson| {
+            let mut dson = dson.clone();
+            unwrap(&mut dson)?;
+            match dson {
+                Dson::Labeled { label, expr } => Ok(MapElem {
+                    key: Dson::Literal(Literal::String(label)),
+                    value: *expr,
+                }),
+                _ => Err(Error::ExpectedLabel { got: dson }),
+            }
+        })
+        .collect()
+}
+
 pub struct ValuesDeserializer(Vec<Dson>);
 
 impl ValuesDeserializer {
@@ -464,4 +696,174 @@ mod tests {
         }]);
         assert_eq!(expected, from_dson(dson).unwrap());
     }
+
+    #[test]
+    fn test_skips_comments_and_attributes() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            a: bool,
+            b: Option<i64>,
+        }
+
+        let dson = Dson::Comment {
+            text: "test".into(),
+            expr: Box::new(Dson::Product(vec![
+                Dson::Attributed {
+                    attr: Box::new("attr".into()),
+                    expr: Box::new(Dson::Labeled {
+                        label: "a".into(),
+                        expr: Box::new(Dson::Comment {
+                            text: "a".into(),
+                            expr: Box::new(Dson::Labeled {
+                                label: "true".into(),
+                                expr: Box::new(Dson::Product(vec![])),
+                            }),
+                        }),
+                    }),
+                },
+                Dson::Labeled {
+                    label: "b".into(),
+                    expr: Box::new(Dson::Attributed {
+                        attr: Box::new("attr".into()),
+                        expr: Box::new(1.into()),
+                    }),
+                },
+            ])),
+        };
+        let expected = Test {
+            a: true,
+            b: Some(1),
+        };
+        assert_eq!(expected, from_dson(dson).unwrap());
+    }
+
+    #[test]
+    fn test_typed() {
+        let ty = Type::Let {
+            variable: "list".into(),
+            definition: Box::new(Type::Sum(vec![
+                Type::Product(vec![]),
+                Type::Product(vec![Type::Rational, Type::Variable("list".into())]),
+            ])),
+            body: Box::new(Type::Variable("list".into())),
+        };
+        let list = Dson::Product(vec![
+            1.into(),
+            Dson::Product(vec![
+                Dson::Literal(Literal::Rational(1, 2)),
+                Dson::Product(vec![]),
+            ]),
+        ]);
+        let dson = Dson::Typed {
+            ty: ty.clone(),
+            expr: Box::new(list),
+        };
+        assert!(from_dson::<'_, (i64, (f64, ()))>(dson).is_ok());
+        let dson = Dson::Typed {
+            ty,
+            expr: Box::new(Dson::Product(vec![1.into(), "a".into()])),
+        };
+        assert!(matches!(
+            from_dson::<'_, (i64, String)>(dson),
+            Err(Error::TypeMismatch { .. })
+        ));
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(rename = "meter")]
+        struct Meter(i64);
+        let meter = Type::Brand {
+            brand: "meter".into(),
+            item: Box::new(Type::Integer),
+        };
+        let dson = Dson::Typed {
+            ty: meter.clone(),
+            expr: Box::new(Dson::Labeled {
+                label: "meter".into(),
+                expr: Box::new(1.into()),
+            }),
+        };
+        assert_eq!(from_dson::<'_, Meter>(dson), Ok(Meter(1)));
+        let dson = Dson::Typed {
+            ty: meter,
+            expr: Box::new(1.into()),
+        };
+        assert_eq!(from_dson::<'_, i64>(dson), Ok(1));
+
+        let dson = Dson::Typed {
+            ty: Type::Integer,
+            expr: Box::new("a".into()),
+        };
+        assert_eq!(
+            from_dson::<'_, String>(dson),
+            Err(Error::TypeMismatch {
+                ty: Type::Integer,
+                got: "a".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_non_contractive_type() {
+        let let_a = |definition| Type::Let {
+            variable: "a".into(),
+            definition: Box::new(definition),
+            body: Box::new(Type::Variable("a".into())),
+        };
+        let dson = Dson::Typed {
+            ty: let_a(Type::Variable("a".into())),
+            expr: Box::new(1.into()),
+        };
+        assert!(matches!(
+            from_dson::<'_, i64>(dson),
+            Err(Error::TypeMismatch { .. })
+        ));
+        let ty = let_a(Type::Sum(vec![Type::Variable("a".into()), Type::Integer]));
+        let dson = Dson::Typed {
+            ty: ty.clone(),
+            expr: Box::new("a".into()),
+        };
+        assert!(matches!(
+            from_dson::<'_, String>(dson),
+            Err(Error::TypeMismatch { .. })
+        ));
+        let dson = Dson::Typed {
+            ty,
+            expr: Box::new(1.into()),
+        };
+        assert_eq!(from_dson::<'_, i64>(dson), Ok(1));
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        assert_eq!(
+            from_dson::<'_, u8>(256.into()),
+            Err(Error::IntegerOverflow {
+                ty: "u8",
+                got: 256.into(),
+            })
+        );
+        assert_eq!(
+            from_dson::<'_, u64>((-1).into()),
+            Err(Error::IntegerOverflow {
+                ty: "u64",
+                got: (-1).into(),
+            })
+        );
+        let big = Dson::Labeled {
+            label: "big integer".into(),
+            expr: Box::new("18446744073709551616".into()),
+        };
+        assert_eq!(
+            from_dson::<'_, u64>(big.clone()),
+            Err(Error::IntegerOverflow {
+                ty: "u64",
+                got: big.clone(),
+            })
+        );
+        assert_eq!(from_dson::<'_, u128>(big), Ok(u64::MAX as u128 + 1));
+        assert_eq!(
+            from_dson::<'_, i64>("1".into()),
+            Err(Error::ExpectedInteger { got: "1".into() })
+        );
+    }
 }
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use dson::Dson;
+use dson::{Dson, Type};
 use serde::{de, ser};
 use thiserror::Error;
 
@@ -20,8 +20,14 @@ pub enum Error {
     ExpectedMap { got: Dson },
     #[error("expected a label but got {got:?}")]
     ExpectedLabel { got: Dson },
+    #[error("expected an integer but got {got:?}")]
+    ExpectedInteger { got: Dson },
+    #[error("{got:?} is out of the range of {ty}")]
+    IntegerOverflow { ty: &'static str, got: Dson },
     #[error("expected a string literal but got {got:?}")]
     ExpectedString { got: Dson },
+    #[error("{got:?} does not conform to the type {ty:?}")]
+    TypeMismatch { ty: Type, got: Dson },
     #[error("label mismatch: expected {expected:?} but got {got:?}")]
     LabelMismatch { expected: String, got: String },
 }
@@ -5,3 +5,139 @@ mod ser;
 pub use de::{from_dson, Deserializer};
 pub use error::{Error, Result};
 pub use ser::{to_dson, Serializer};
+
+/// The label of integers out of the range of `Literal::Integer`, which wraps their decimal digits.
+///
+/// It has a space so that no variant named by a Rust identifier is taken as an integer.
+pub(crate) const BIG_INTEGER: &str = "big integer";
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+    use super::*;
+
+    fn round_trip<T>(value: T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let dson = to_dson(&value).unwrap();
+        assert_eq!(from_dson::<T>(dson).unwrap(), value);
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum E {
+        Unit,
+        Newtype(bool),
+        Tuple(i32, Option<String>),
+        Struct { a: Vec<E> },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Unit;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Newtype(u8);
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Struct {
+        flag: bool,
+        option: Option<Newtype>,
+        unit: Unit,
+        tuple: (char, f64, E),
+        map: BTreeMap<String, HashMap<i64, Vec<E>>>,
+    }
+
+    #[test]
+    fn round_trips_primitives() {
+        round_trip(true);
+        round_trip(false);
+        round_trip(-1_i8);
+        round_trip(i64::MIN);
+        round_trip(u64::MAX);
+        round_trip(i128::MIN);
+        round_trip(u128::MAX);
+        round_trip(1.5_f32);
+        round_trip('a');
+        round_trip("a".to_string());
+        round_trip(());
+        round_trip(Unit);
+        round_trip(Newtype(1));
+    }
+
+    #[test]
+    fn round_trips_enums() {
+        round_trip(E::Unit);
+        round_trip(E::Newtype(true));
+        round_trip(E::Tuple(1, None));
+        round_trip(E::Tuple(1, Some("a".into())));
+        round_trip(E::Struct {
+            a: vec![E::Unit, E::Struct { a: vec![] }],
+        });
+    }
+
+    #[test]
+    fn round_trips_options_and_tuples() {
+        round_trip(Some(1_u16));
+        round_trip(None::<u16>);
+        round_trip(vec![Some(true), None]);
+        round_trip((1_u32, "a".to_string(), (false, E::Unit)));
+    }
+
+    #[test]
+    fn round_trips_nested_maps() {
+        round_trip(Struct {
+            flag: true,
+            option: Some(Newtype(2)),
+            unit: Unit,
+            tuple: ('x', 0.5, E::Newtype(false)),
+            map: [
+                ("a".to_string(), HashMap::new()),
+                (
+                    "b".to_string(),
+                    [(1, vec![E::Unit]), (-2, vec![])].into_iter().collect(),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        });
+    }
+
+    #[test]
+    fn round_trips_self_describing() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(untagged)]
+        enum Untagged {
+            Bool(bool),
+            Struct { a: i64 },
+            Enum(E),
+            Seq(Vec<i64>),
+            Big(u64),
+            Named(Named),
+        }
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        enum Named {
+            #[serde(rename = "integer")]
+            Integer(String),
+        }
+        round_trip(Untagged::Bool(true));
+        round_trip(Untagged::Struct { a: 1 });
+        round_trip(Untagged::Enum(E::Newtype(true)));
+        round_trip(Untagged::Seq(vec![1, 2]));
+        round_trip(Untagged::Big(u64::MAX));
+        round_trip(Untagged::Named(Named::Integer("1".into())));
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Flattened {
+            a: bool,
+            #[serde(flatten)]
+            rest: BTreeMap<String, i64>,
+        }
+        round_trip(Flattened {
+            a: false,
+            rest: [("b".to_string(), 1)].into_iter().collect(),
+        });
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Error, Result};
+use crate::{Error, Result, BIG_INTEGER};
 use dson::{Dson, Literal, MapElem, Real};
 use serde::{
     ser::{
@@ -53,6 +53,13 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         Ok(Dson::Literal(Literal::Integer(v)))
     }
 
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) => Ok(big_integer(v)),
+        }
+    }
+
     fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
         self.serialize_i64(v as i64)
     }
@@ -66,7 +73,14 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
-        self.serialize_i64(v as i64)
+        self.serialize_u128(v as u128)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) => Ok(big_integer(v)),
+        }
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
@@ -204,6 +218,13 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 }
 
+fn big_integer(v: impl ToString) -> Dson {
+    Dson::Labeled {
+        label: BIG_INTEGER.into(),
+        expr: Box::new(Dson::Literal(Literal::String(v.to_string()))),
+    }
+}
+
 #[derive(Default)]
 pub struct SeqSerializer(Vec<Dson>);
 
@@ -441,6 +462,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_big_integer() {
+        assert_eq!(to_dson(&(i64::MAX as u64)).unwrap(), i64::MAX.into());
+        assert_eq!(
+            to_dson(&u64::MAX).unwrap(),
+            Dson::Labeled {
+                label: "big integer".into(),
+                expr: Box::new("18446744073709551615".into())
+            }
+        );
+        assert_eq!(to_dson(&(i64::MIN as i128)).unwrap(), i64::MIN.into());
+        assert_eq!(
+            to_dson(&(i64::MIN as i128 - 1)).unwrap(),
+            Dson::Labeled {
+                label: "big integer".into(),
+                expr: Box::new("-9223372036854775809".into())
+            }
+        );
+    }
+
     #[test]
     fn test_map() {
         use std::collections::HashMap;
@@ -7,13 +7,15 @@ use desk_window::{
     window::{DefaultWindow, Window},
 };
 use deskc_ast::{
-    expr::Expr,
+    expr::{Expr, Handler, MapElem, MatchCase},
     meta::WithMeta,
     ty::{Effect, EffectExpr, Type},
+    visitor::{ExprVisitor, TypeVisitor},
 };
 use deskc_ids::NodeId;
 use deskc_macros::ast;
 use deskc_type::DsonTypeDeduction;
+use dson::Dson;
 use dworkspace::{
     prelude::{
         AttributePatch, Content, Event, EventId, EventPayload, OperandPatch, OperandPosition,
@@ -78,778 +80,202 @@ const FIBONACCI: Lazy<WithMeta<Expr>> = Lazy::new(|| {
     )
 });
 
-fn create_nodes_for_ast<'a>(ctx: &mut Ctx<egui::Context>, expr: &'a WithMeta<Expr>) -> &'a NodeId {
-    struct Visitor<'a, 'b> {
-        ctx: &'a mut Ctx<'b, egui::Context>,
+fn create_nodes_for_ast(ctx: &mut Ctx<egui::Context>, expr: &WithMeta<Expr>) {
+    let mut creator = NodeCreator::default();
+    ExprVisitor::visit_expr(&mut creator, expr);
+    for payload in creator.events {
+        ctx.add_event(Event {
+            id: EventId::new(),
+            user_id: ctx.workspace.user_id(),
+            payload,
+        });
     }
-    // FIXME: implement using ExprVisitor generated by `#[derive(Visitor)]`
-    impl Visitor<'_, '_> {
-        fn add_event(&mut self, event: EventPayload) {
-            self.ctx.add_event(Event {
-                id: EventId::new(),
-                user_id: self.ctx.workspace.user_id(),
-                payload: event,
-            });
-        }
-        #[must_use]
-        fn visit_expr<'a>(&mut self, expr: &'a WithMeta<Expr>) -> &'a NodeId {
-            let node_id = &expr.meta.id;
-            match &expr.value {
-                Expr::Literal(literal) => {
-                    use deskc_ast::expr::Literal::*;
-                    let content = match literal {
-                        String(string) => Content::String(string.clone()),
-                        Integer(int) => Content::Integer(*int),
-                        Rational(a, b) => Content::Rational(*a, *b),
-                        Real(real) => Content::Real(*real),
-                    };
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content,
-                    });
-                }
-                Expr::Do { stmt, expr } => {
-                    let stmt = self.visit_expr(stmt);
-                    let expr = self.visit_expr(expr);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Do,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: stmt.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: expr.clone(),
-                        },
-                    });
-                }
-                Expr::Let { definition, body } => {
-                    let definition = self.visit_expr(definition);
-                    let body = self.visit_expr(body);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Let,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: definition.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: body.clone(),
-                        },
-                    });
-                }
-                Expr::Perform { input, output } => {
-                    let input = self.visit_expr(input);
-                    let output = self.visit_type(output);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Perform,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: input.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: output.clone(),
-                        },
-                    });
-                }
-                Expr::Continue { input, output } => {
-                    let input = self.visit_expr(input);
-                    let output = self.visit_type(output);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Continue,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: input.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: output.clone(),
-                        },
-                    });
-                }
-                Expr::Handle { expr, handlers } => {
-                    let expr = self.visit_expr(expr);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Handle,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: expr.clone(),
-                        },
-                    });
-                    for handler in handlers.iter() {
-                        let effect = self.visit_effect(&handler.value.effect);
-                        let expr = self.visit_expr(&handler.value.handler);
-                        self.add_event(EventPayload::CreateNode {
-                            node_id: handler.meta.id.clone(),
-                            content: Content::Handler,
-                        });
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: handler.meta.id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: effect.clone(),
-                            },
-                        });
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: handler.meta.id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: expr.clone(),
-                            },
-                        });
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: handler.meta.id.clone(),
-                            },
-                        });
-                    }
-                }
-                Expr::Apply {
-                    function,
-                    link_name,
-                    arguments,
-                } => {
-                    let function = self.visit_type(function);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Apply {
-                            link_name: link_name.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: function.clone(),
-                        },
-                    });
-                    for argument in arguments.iter() {
-                        let argument = self.visit_expr(argument);
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: argument.clone(),
-                            },
-                        });
-                    }
-                }
-                Expr::Product(exprs) => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Product,
-                    });
-                    for expr in exprs.iter() {
-                        let expr = self.visit_expr(expr);
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: expr.clone(),
-                            },
-                        });
-                    }
-                }
-                Expr::Match { of, cases } => {
-                    let of = self.visit_expr(of);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Match,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: of.clone(),
-                        },
-                    });
-                    for case in cases.iter() {
-                        let expr = self.visit_expr(&case.value.expr);
-                        let ty = self.visit_type(&case.value.ty);
-                        self.add_event(EventPayload::CreateNode {
-                            node_id: case.meta.id.clone(),
-                            content: Content::Case,
-                        });
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: case.meta.id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: ty.clone(),
-                            },
-                        });
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: case.meta.id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: expr.clone(),
-                            },
-                        });
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: *node_id,
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: case.meta.id.clone(),
-                            },
-                        });
-                    }
-                }
-                Expr::Typed { ty, item } => {
-                    let ty = self.visit_type(ty);
-                    let item = self.visit_expr(item);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Typed,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: ty.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: item.clone(),
-                        },
-                    });
-                }
-                Expr::Hole => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Hole,
-                    });
-                }
-                Expr::Function { parameter, body } => {
-                    let body = self.visit_expr(body);
-                    let parameter = self.visit_type(parameter);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Function,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: parameter.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: body.clone(),
-                        },
-                    });
-                }
-                Expr::Vector(exprs) => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Vector,
-                    });
-                    for expr in exprs.iter() {
-                        let expr = self.visit_expr(expr);
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: expr.clone(),
-                            },
-                        });
-                    }
-                }
-                Expr::Map(elems) => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Map,
-                    });
-                    for elem in elems.iter() {
-                        let key = self.visit_expr(&elem.value.key);
-                        let value = self.visit_expr(&elem.value.value);
-                        self.add_event(EventPayload::CreateNode {
-                            node_id: elem.meta.id.clone(),
-                            content: Content::MapElem,
-                        });
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: elem.meta.id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: key.clone(),
-                            },
-                        });
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: elem.meta.id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: value.clone(),
-                            },
-                        });
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: elem.meta.id.clone(),
-                            },
-                        });
-                    }
-                }
-                Expr::Attributed { attr, item } => {
-                    let node_id = self.visit_expr(item);
-                    self.add_event(EventPayload::PatchAttribute {
-                        node_id: node_id.clone(),
-                        patch: AttributePatch::Update {
-                            key: attr.deduct_type(),
-                            value: attr.clone(),
-                        },
-                    });
-                    // Use the node_id of the item, not the attributed node.
-                    return node_id;
-                }
-                Expr::DeclareBrand { brand, item } => {
-                    let item = self.visit_expr(item);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::DeclareBrand {
-                            brand: brand.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: item.clone(),
-                        },
-                    });
-                }
-                Expr::Label { label, item } => {
-                    let item = self.visit_expr(item);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Label {
-                            label: label.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: item.clone(),
-                        },
-                    });
-                }
-                Expr::NewType { ident, ty, expr } => {
-                    let ty = self.visit_type(ty);
-                    let expr = self.visit_expr(expr);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::NewType {
-                            ident: ident.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: ty.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: expr.clone(),
-                        },
-                    });
-                }
-                Expr::Card { id, item, next } => {
-                    let item = self.visit_expr(item);
-                    self.add_event(EventPayload::PatchAttribute {
-                        node_id: item.clone(),
-                        patch: AttributePatch::Update {
-                            key: todo!(),
-                            value: todo!(),
-                        },
-                    });
-                    let _ = self.visit_expr(next);
-                    // Use the node_id of the next, not the card node.
-                    return item;
-                }
-            };
-            node_id
-        }
-        #[must_use]
-        fn visit_type<'a>(&mut self, ty: &'a WithMeta<Type>) -> &'a NodeId {
-            let node_id = &ty.meta.id;
-            match &ty.value {
-                Type::Labeled { brand, item } => {
-                    let item = self.visit_type(item);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyLabeled {
-                            brand: brand.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: item.clone(),
-                        },
-                    });
-                }
-                Type::Real => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyReal,
-                    });
-                }
-                Type::Rational => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyRational,
-                    });
-                }
-                Type::Integer => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyInteger,
-                    });
-                }
-                Type::String => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyString,
-                    });
-                }
-                Type::Effectful { ty, effects } => {
-                    let ty = self.visit_type(ty);
-                    let effects = self.visit_effect_expr(effects);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyEffectful,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: ty.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: effects.clone(),
-                        },
-                    });
-                }
-                Type::Infer => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Infer,
-                    });
-                }
-                Type::Product(types) => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyProduct,
-                    });
-                    for ty in types.iter() {
-                        let ty = self.visit_type(ty);
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: ty.clone(),
-                            },
-                        });
-                    }
-                }
-                Type::Sum(types) => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Sum,
-                    });
-                    for ty in types.iter() {
-                        let ty = self.visit_type(ty);
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: ty.clone(),
-                            },
-                        });
-                    }
-                }
-                Type::Function(function) => {
-                    let parameter = self.visit_type(&function.parameter);
-                    let body = self.visit_type(&function.body);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyFunction,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: parameter.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: body.clone(),
-                        },
-                    });
-                }
-                Type::Vector(ty) => {
-                    let ty = self.visit_type(ty);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyVector,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: ty.clone(),
-                        },
-                    });
-                }
-                Type::Map { key, value } => {
-                    let key = self.visit_type(key);
-                    let value = self.visit_type(value);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyMap,
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: key.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: value.clone(),
-                        },
-                    });
-                }
-                Type::Let {
-                    variable,
-                    definition,
-                    body,
-                } => {
-                    let definition = self.visit_type(definition);
-                    let body = self.visit_type(body);
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::TyLet {
-                            ident: variable.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: definition.clone(),
-                        },
-                    });
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: body.clone(),
-                        },
-                    });
-                }
-                Type::Variable(ident) => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Variable {
-                            ident: ident.clone(),
-                        },
-                    });
-                }
-                Type::Attributed { attr, ty } => {
-                    let node_id = self.visit_type(ty);
-                    self.add_event(EventPayload::PatchAttribute {
-                        node_id: node_id.clone(),
-                        patch: AttributePatch::Update {
-                            key: attr.deduct_type(),
-                            value: attr.clone(),
-                        },
-                    });
-                    // Use the node_id of the item, not the attributed node.
-                    return node_id;
-                }
-                Type::Forall {
-                    variable,
-                    bound,
-                    body,
-                } => todo!(),
-                Type::Exists {
-                    variable,
-                    bound,
-                    body,
-                } => todo!(),
-            };
-            node_id
-        }
-        fn visit_effect_expr<'a>(&mut self, effects: &'a WithMeta<EffectExpr>) -> &'a NodeId {
-            let node_id = &effects.meta.id;
-            match &effects.value {
-                EffectExpr::Effects(effects) => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::Effects,
-                    });
-                    for effect in effects.iter() {
-                        let effect = self.visit_effect(effect);
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: effect.clone(),
-                            },
-                        });
-                    }
-                }
-                EffectExpr::Add(exprs) => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::EAdd,
-                    });
-                    for expr in exprs.iter() {
-                        let expr = self.visit_effect_expr(expr);
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: expr.clone(),
-                            },
-                        });
-                    }
-                }
-                EffectExpr::Sub {
-                    minuend,
-                    subtrahend,
-                } => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::ESub,
-                    });
-                    let minuend = self.visit_effect_expr(minuend);
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: minuend.clone(),
-                        },
-                    });
-                    let subtrahend = self.visit_effect_expr(subtrahend);
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: subtrahend.clone(),
-                        },
-                    });
-                }
-                EffectExpr::Apply {
-                    function,
-                    arguments,
-                } => {
-                    self.add_event(EventPayload::CreateNode {
-                        node_id: node_id.clone(),
-                        content: Content::EApply,
-                    });
-                    let function = self.visit_type(function);
-                    self.add_event(EventPayload::PatchOperand {
-                        node_id: node_id.clone(),
-                        patch: OperandPatch::Insert {
-                            position: OperandPosition::Last,
-                            node_id: function.clone(),
-                        },
-                    });
-                    for argument in arguments.iter() {
-                        let argument = self.visit_type(argument);
-                        self.add_event(EventPayload::PatchOperand {
-                            node_id: node_id.clone(),
-                            patch: OperandPatch::Insert {
-                                position: OperandPosition::Last,
-                                node_id: argument.clone(),
-                            },
-                        });
-                    }
-                }
-            };
-            node_id
-        }
-        fn visit_effect<'a>(&mut self, effect: &'a WithMeta<Effect>) -> &'a NodeId {
-            let node_id = &effect.meta.id;
-            let input = self.visit_type(&effect.value.input);
-            let output = self.visit_type(&effect.value.output);
-            self.add_event(EventPayload::CreateNode {
-                node_id: node_id.clone(),
-                content: Content::Effect,
-            });
-            self.add_event(EventPayload::PatchOperand {
-                node_id: node_id.clone(),
-                patch: OperandPatch::Insert {
-                    position: OperandPosition::Last,
-                    node_id: input.clone(),
-                },
-            });
-            self.add_event(EventPayload::PatchOperand {
-                node_id: node_id.clone(),
+}
+
+/// Creates a node for each node of the AST, as the last operand of its parent.
+#[derive(Default)]
+struct NodeCreator {
+    events: Vec<EventPayload>,
+    /// The nodes whose operands are being created, innermost last.
+    parents: Vec<NodeId>,
+}
+
+impl NodeCreator {
+    /// `create_operands` is called while the node is the parent.
+    fn create_node(
+        &mut self,
+        node_id: NodeId,
+        content: Content,
+        create_operands: impl FnOnce(&mut Self),
+    ) {
+        self.events
+            .push(EventPayload::CreateNode { node_id, content });
+        if let Some(parent) = self.parents.last() {
+            self.events.push(EventPayload::PatchOperand {
+                node_id: *parent,
                 patch: OperandPatch::Insert {
                     position: OperandPosition::Last,
-                    node_id: output.clone(),
+                    node_id,
                 },
             });
-            node_id
         }
+        self.parents.push(node_id);
+        create_operands(self);
+        self.parents.pop();
+    }
+
+    fn patch_attribute(&mut self, node_id: NodeId, attr: &Dson) {
+        self.events.push(EventPayload::PatchAttribute {
+            node_id,
+            patch: AttributePatch::Update {
+                key: attr.deduct_type(),
+                value: attr.clone(),
+            },
+        });
+    }
+
+    fn create_effect(&mut self, effect: &WithMeta<Effect>) {
+        self.create_node(effect.meta.id, Content::Effect, |this| {
+            TypeVisitor::super_visit_effect(this, effect)
+        });
+    }
+}
+
+/// Attributed expressions are not nodes but attributes of their items.
+fn expr_node_id(mut expr: &WithMeta<Expr>) -> NodeId {
+    while let Expr::Attributed { item, .. } = &expr.value {
+        expr = item;
+    }
+    expr.meta.id
+}
+
+fn type_node_id(mut ty: &WithMeta<Type>) -> NodeId {
+    while let Type::Attributed { ty: item, .. } = &ty.value {
+        ty = item;
+    }
+    ty.meta.id
+}
+
+impl ExprVisitor for NodeCreator {
+    fn visit_expr(&mut self, expr: &WithMeta<Expr>) {
+        let content = match &expr.value {
+            Expr::Literal(literal) => {
+                use deskc_ast::expr::Literal::*;
+                match literal {
+                    String(string) => Content::String(string.clone()),
+                    Integer(int) => Content::Integer(*int),
+                    Rational(a, b) => Content::Rational(*a, *b),
+                    Real(real) => Content::Real(*real),
+                }
+            }
+            Expr::Do { .. } => Content::Do,
+            Expr::Let { .. } => Content::Let,
+            Expr::Perform { .. } => Content::Perform,
+            Expr::Continue { .. } => Content::Continue,
+            Expr::Handle { .. } => Content::Handle,
+            Expr::Apply { link_name, .. } => Content::Apply {
+                link_name: *link_name,
+            },
+            Expr::Product(_) => Content::Product,
+            Expr::Match { .. } => Content::Match,
+            Expr::Typed { .. } => Content::Typed,
+            Expr::Hole => Content::Hole,
+            Expr::Function { .. } => Content::Function,
+            Expr::Vector(_) => Content::Vector,
+            Expr::Map(_) => Content::Map,
+            Expr::Attributed { attr, item } => {
+                self.visit_expr(item);
+                self.patch_attribute(expr_node_id(item), attr);
+                return;
+            }
+            Expr::DeclareBrand { brand, .. } => Content::DeclareBrand {
+                brand: brand.clone(),
+            },
+            Expr::Label { label, .. } => Content::Label {
+                label: label.clone(),
+            },
+            Expr::NewType { ident, .. } => Content::NewType {
+                ident: ident.clone(),
+            },
+            Expr::Card { .. } => todo!(),
+        };
+        self.create_node(expr.meta.id, content, |this| this.super_visit_expr(expr));
+    }
+
+    fn visit_handler(&mut self, handler: &WithMeta<Handler>) {
+        self.create_node(handler.meta.id, Content::Handler, |this| {
+            this.super_visit_handler(handler)
+        });
+    }
+
+    fn visit_match_case(&mut self, case: &WithMeta<MatchCase>) {
+        self.create_node(case.meta.id, Content::Case, |this| {
+            this.super_visit_match_case(case)
+        });
+    }
+
+    fn visit_map_elem(&mut self, elem: &WithMeta<MapElem>) {
+        self.create_node(elem.meta.id, Content::MapElem, |this| {
+            this.super_visit_map_elem(elem)
+        });
+    }
+
+    fn visit_effect(&mut self, effect: &WithMeta<Effect>) {
+        self.create_effect(effect);
+    }
+
+    fn visit_type(&mut self, ty: &WithMeta<Type>) {
+        TypeVisitor::visit_type(self, ty);
+    }
+}
+
+impl TypeVisitor for NodeCreator {
+    fn visit_type(&mut self, ty: &WithMeta<Type>) {
+        let content = match &ty.value {
+            Type::Labeled { brand, .. } => Content::TyLabeled {
+                brand: brand.clone(),
+            },
+            Type::Real => Content::TyReal,
+            Type::Rational => Content::TyRational,
+            Type::Integer => Content::TyInteger,
+            Type::String => Content::TyString,
+            Type::Effectful { .. } => Content::TyEffectful,
+            Type::Infer => Content::Infer,
+            Type::Product(_) => Content::TyProduct,
+            Type::Sum(_) => Content::Sum,
+            Type::Function(_) => Content::TyFunction,
+            Type::Vector(_) => Content::TyVector,
+            Type::Map { .. } => Content::TyMap,
+            Type::Let { variable, .. } => Content::TyLet {
+                ident: variable.clone(),
+            },
+            Type::Variable(ident) => Content::Variable {
+                ident: ident.clone(),
+            },
+            Type::Attributed { attr, ty: item } => {
+                TypeVisitor::visit_type(self, item);
+                self.patch_attribute(type_node_id(item), attr);
+                return;
+            }
+            Type::Forall { .. } => todo!(),
+            Type::Exists { .. } => todo!(),
+        };
+        self.create_node(ty.meta.id, content, |this| this.super_visit_type(ty));
+    }
+
+    fn visit_effect_expr(&mut self, effects: &WithMeta<EffectExpr>) {
+        let content = match &effects.value {
+            EffectExpr::Effects(_) => Content::Effects,
+            EffectExpr::Add(_) => Content::EAdd,
+            EffectExpr::Sub { .. } => Content::ESub,
+            EffectExpr::Apply { .. } => Content::EApply,
+        };
+        self.create_node(effects.meta.id, content, |this| {
+            this.super_visit_effect_expr(effects)
+        });
+    }
+
+    fn visit_effect(&mut self, effect: &WithMeta<Effect>) {
+        self.create_effect(effect);
     }
-    Visitor { ctx }.visit_expr(expr)
 }
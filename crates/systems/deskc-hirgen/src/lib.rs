@@ -1,6 +1,20 @@
 mod error;
 mod gen_effect_expr;
 
+/// The crates the code generated by `deskc_macros::card!` refers to, so that users of the
+/// macro need no dependencies on them.
+#[doc(hidden)]
+pub mod macro_support {
+    pub use ::ast;
+    pub use ::dson;
+    pub use ::ids;
+    pub use ::uuid;
+}
+
+// `card!` refers to this crate by its name.
+#[cfg(test)]
+extern crate self as deskc_hirgen;
+
 use dson::Dson;
 use ids::NodeId;
 
@@ -19,7 +33,15 @@ use hir::{
 };
 
 pub fn gen_cards(src: &AstWithMeta<ast::expr::Expr>) -> Result<(HirGen, Cards), HirGenError> {
+    gen_cards_with_imports(src, &TypeAliases::default())
+}
+
+pub fn gen_cards_with_imports(
+    src: &AstWithMeta<ast::expr::Expr>,
+    imports: &TypeAliases,
+) -> Result<(HirGen, Cards), HirGenError> {
     let hirgen = HirGen::default();
+    hirgen.import(imports);
     let file = hirgen.gen_cards(src)?;
     let hir = Cards {
         cards: hirgen.cards.borrow_mut().drain(..).collect(),
@@ -36,6 +58,21 @@ pub fn gen_hir(
     Ok((hirgen, card))
 }
 
+/// Type aliases and brands that a file shares with files importing it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TypeAliases {
+    pub aliases: HashMap<String, Type>,
+    pub brands: HashSet<String>,
+}
+
+impl TypeAliases {
+    /// Later ones take precedence over earlier ones.
+    pub fn extend(&mut self, other: &TypeAliases) {
+        self.aliases.extend(other.aliases.clone());
+        self.brands.extend(other.brands.clone());
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct HirGen {
     next_id: RefCell<usize>,
@@ -185,10 +222,7 @@ impl HirGen {
                 ast::expr::Literal::Rational(a, b) => Literal::Rational(*a, *b),
                 ast::expr::Literal::Real(value) => Literal::Real(*value),
             })),
-            ast::expr::Expr::Hole => self.with_meta(Expr::Perform {
-                input: Box::new(self.with_no_span(Expr::Product(vec![]))),
-                output: self.with_no_span(Type::Infer),
-            }),
+            ast::expr::Expr::Hole => self.with_meta(Expr::Hole),
             ast::expr::Expr::Do { stmt, expr } => self.with_meta(Expr::Do {
                 stmt: Box::new(self.gen_card(stmt)?),
                 expr: Box::new(self.gen_card(expr)?),
@@ -322,6 +356,22 @@ impl HirGen {
         Ok(with_meta)
     }
 
+    /// Makes type aliases and brands of other files visible.
+    pub fn import(&self, imports: &TypeAliases) {
+        self.type_aliases
+            .borrow_mut()
+            .extend(imports.aliases.clone());
+        self.brands.borrow_mut().extend(imports.brands.clone());
+    }
+
+    /// Type aliases and brands visible at the end of the file, including imported ones.
+    pub fn exports(&self) -> TypeAliases {
+        TypeAliases {
+            aliases: self.type_aliases.borrow().clone(),
+            brands: self.brands.borrow().clone(),
+        }
+    }
+
     pub(crate) fn add_new_type(
         &self,
         ty: &AstWithMeta<ast::ty::Type>,
@@ -504,4 +554,43 @@ mod tests {
             dummy_meta(Expr::Literal(Literal::Integer(1)))
         );
     }
+
+    #[test]
+    fn imported_type_aliases_and_brands() {
+        let (exporter, _) = gen_cards(&parse(
+            r#"
+        'type add \ *<@l 'integer, @r 'integer> -> @sum 'integer;
+        'brand brand;
+        1
+        "#,
+        ))
+        .unwrap();
+        let exports = exporter.exports();
+        assert!(exports.aliases.contains_key("add"));
+        assert!(exports.brands.contains("brand"));
+
+        let (_, hir) = gen_cards_with_imports(&parse("& @brand add"), &exports).unwrap();
+        let Expr::Apply { function, .. } = hir.file.value else {
+            panic!("expected apply");
+        };
+        let Type::Brand { brand, item } = function.value else {
+            panic!("expected brand");
+        };
+        assert_eq!(brand, "brand");
+        assert_eq!(item.value, exports.aliases["add"]);
+    }
+
+    #[test]
+    fn card_macro() {
+        let source = "'card 00000000-0000-0000-0000-000000000001 1; 2";
+        let cards = deskc_macros::card!("'card 00000000-0000-0000-0000-000000000001 1; 2");
+        let (_, expected) = gen_cards(&parse(source)).unwrap();
+        assert_eq!(cards.cards.len(), 1);
+        assert_eq!(cards.cards[0].id, expected.cards[0].id);
+        assert_eq!(
+            remove_meta(cards.cards[0].hir.clone()),
+            remove_meta(expected.cards[0].hir.clone())
+        );
+        assert_eq!(remove_meta(cards.file), remove_meta(expected.file));
+    }
 }
@@ -0,0 +1,179 @@
+use std::collections::{HashMap, VecDeque};
+
+use mir::{
+    block::{BasicBlock, BlockId},
+    mir::ControlFlowGraph,
+    stmt::Terminator,
+};
+
+/// Jumps over empty blocks.
+pub fn thread_gotos(cfg: &mut ControlFlowGraph) {
+    let threaded: Vec<_> = (0..cfg.blocks.len())
+        .map(|id| thread(cfg, BlockId(id)))
+        .collect();
+    for block in &mut cfg.blocks {
+        for next in successors_mut(&mut block.terminator) {
+            *next = threaded[next.0];
+        }
+    }
+}
+
+/// Merges blocks into their only predecessor.
+///
+/// Unreachable blocks are counted as predecessors, so they should be removed before.
+pub fn merge_blocks(cfg: &mut ControlFlowGraph) {
+    for id in 0..cfg.blocks.len() {
+        while let Terminator::Goto(next) = cfg.blocks[id].terminator {
+            if next.0 == 0 || next.0 == id || predecessors(cfg, next) != 1 {
+                break;
+            }
+            // The merged block is left as an unreachable self loop not to count its successors.
+            let merged = std::mem::replace(
+                &mut cfg.blocks[next.0],
+                BasicBlock {
+                    stmts: vec![],
+                    terminator: Terminator::Goto(next),
+                },
+            );
+            let block = &mut cfg.blocks[id];
+            block.stmts.extend(merged.stmts);
+            block.terminator = merged.terminator;
+        }
+    }
+}
+
+/// Removes blocks not reachable from the entry block and renumbers the rest.
+pub fn remove_unreachable_blocks(cfg: &mut ControlFlowGraph) {
+    let mut ids = HashMap::from([(BlockId(0), BlockId(0))]);
+    let mut order = vec![BlockId(0)];
+    let mut queue = VecDeque::from([BlockId(0)]);
+    while let Some(id) = queue.pop_front() {
+        let mut terminator = cfg.blocks[id.0].terminator.clone();
+        for next in successors_mut(&mut terminator) {
+            if !ids.contains_key(next) {
+                ids.insert(*next, BlockId(order.len()));
+                order.push(*next);
+                queue.push_back(*next);
+            }
+        }
+    }
+    let mut blocks: Vec<_> = order
+        .into_iter()
+        .map(|id| cfg.blocks[id.0].clone())
+        .collect();
+    for block in &mut blocks {
+        for next in successors_mut(&mut block.terminator) {
+            *next = ids[next];
+        }
+    }
+    cfg.blocks = blocks;
+}
+
+/// Follows gotos of empty blocks, stopping at a loop of them.
+fn thread(cfg: &ControlFlowGraph, mut id: BlockId) -> BlockId {
+    let mut visited = vec![id];
+    while let (true, Terminator::Goto(next)) = (
+        cfg.blocks[id.0].stmts.is_empty(),
+        &cfg.blocks[id.0].terminator,
+    ) {
+        if visited.contains(next) {
+            break;
+        }
+        visited.push(*next);
+        id = *next;
+    }
+    id
+}
+
+fn predecessors(cfg: &ControlFlowGraph, id: BlockId) -> usize {
+    cfg.blocks
+        .iter()
+        .map(|block| {
+            let mut terminator = block.terminator.clone();
+            successors_mut(&mut terminator)
+                .filter(|next| **next == id)
+                .count()
+        })
+        .sum()
+}
+
+fn successors_mut(terminator: &mut Terminator) -> Box<dyn Iterator<Item = &mut BlockId> + '_> {
+    match terminator {
+        Terminator::Return(_) => Box::new(std::iter::empty()),
+        Terminator::Match { var: _, cases } => {
+            Box::new(cases.iter_mut().map(|case| &mut case.next))
+        }
+        Terminator::Goto(next) => Box::new(std::iter::once(next)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use deskc_type::Type;
+    use mir::{stmt::MatchCase, var::VarId};
+
+    use crate::tests::{bind, block, cfg, int};
+
+    use super::*;
+
+    #[test]
+    fn threads_and_merges_gotos() {
+        let mut cfg = cfg(
+            vec![Type::Integer, Type::Integer],
+            vec![
+                block(vec![bind(0, int(1))], Terminator::Goto(BlockId(1))),
+                block(vec![], Terminator::Goto(BlockId(2))),
+                block(vec![bind(1, int(2))], Terminator::Return(VarId(1))),
+            ],
+        );
+        thread_gotos(&mut cfg);
+        remove_unreachable_blocks(&mut cfg);
+        merge_blocks(&mut cfg);
+        remove_unreachable_blocks(&mut cfg);
+        assert_eq!(
+            cfg.blocks,
+            vec![block(
+                vec![bind(0, int(1)), bind(1, int(2))],
+                Terminator::Return(VarId(1)),
+            )]
+        );
+    }
+
+    #[test]
+    fn keeps_match_joins() {
+        let match_ = Terminator::Match {
+            var: VarId(0),
+            cases: vec![
+                MatchCase {
+                    ty: Type::Integer,
+                    next: BlockId(2),
+                },
+                MatchCase {
+                    ty: Type::String,
+                    next: BlockId(3),
+                },
+            ],
+        };
+        let mut cfg = cfg(
+            vec![Type::Integer, Type::Integer],
+            vec![
+                block(vec![bind(0, int(1))], match_.clone()),
+                block(vec![], Terminator::Return(VarId(1))),
+                block(vec![bind(1, int(2))], Terminator::Goto(BlockId(4))),
+                block(vec![bind(1, int(3))], Terminator::Goto(BlockId(4))),
+                block(vec![], Terminator::Goto(BlockId(1))),
+            ],
+        );
+        thread_gotos(&mut cfg);
+        remove_unreachable_blocks(&mut cfg);
+        let Terminator::Match { cases, .. } = &cfg.blocks[0].terminator else {
+            panic!("expected match");
+        };
+        assert_eq!(cases[0].next, BlockId(1));
+        assert_eq!(cases[1].next, BlockId(2));
+        assert_eq!(cfg.blocks.len(), 4);
+        assert_eq!(cfg.blocks[1].terminator, Terminator::Goto(BlockId(3)));
+        assert_eq!(cfg.blocks[2].terminator, Terminator::Goto(BlockId(3)));
+        assert_eq!(cfg.blocks[3], block(vec![], Terminator::Return(VarId(1))));
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use mir::{mir::ControlFlowGraph, stmt::Stmt};
+
+use crate::vars::{assignments, rename_uses};
+
+/// Removes casts between the same types by using the source var instead.
+pub fn remove_identity_casts(cfg: &mut ControlFlowGraph) {
+    let assignments = assignments(cfg);
+    let mut renames = HashMap::new();
+    for block in &cfg.blocks {
+        for bind in &block.stmts {
+            let Stmt::Cast(from) = bind.stmt else {
+                continue;
+            };
+            // A var assigned in several match arms may hold another value when it is used.
+            if assignments[&bind.var] == 1
+                && assignments.get(&from).copied().unwrap_or_default() <= 1
+                && cfg.vars.get(&bind.var).ty == cfg.vars.get(&from).ty
+            {
+                renames.insert(bind.var, from);
+            }
+        }
+    }
+    for block in &mut cfg.blocks {
+        block.stmts.retain(|bind| !renames.contains_key(&bind.var));
+    }
+    rename_uses(cfg, &renames);
+}
+
+#[cfg(test)]
+mod tests {
+    use deskc_type::Type;
+    use mir::{stmt::Terminator, var::VarId};
+
+    use crate::tests::{bind, block, cfg, int};
+
+    use super::*;
+
+    #[test]
+    fn removes_chained_identity_casts() {
+        let mut cfg = cfg(
+            vec![Type::Integer, Type::Integer, Type::Integer, Type::Real],
+            vec![block(
+                vec![
+                    bind(0, int(1)),
+                    bind(1, Stmt::Cast(VarId(0))),
+                    bind(2, Stmt::Cast(VarId(1))),
+                    bind(3, Stmt::Cast(VarId(2))),
+                ],
+                Terminator::Return(VarId(3)),
+            )],
+        );
+        remove_identity_casts(&mut cfg);
+        assert_eq!(
+            cfg.blocks,
+            vec![block(
+                vec![bind(0, int(1)), bind(3, Stmt::Cast(VarId(0)))],
+                Terminator::Return(VarId(3)),
+            )]
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use mir::{mir::ControlFlowGraph, stmt::Stmt};
+
+use crate::vars::uses;
+
+/// Removes statements without effects whose results are never used.
+pub fn remove_dead_stmts(cfg: &mut ControlFlowGraph) {
+    loop {
+        let uses = uses(cfg);
+        let mut removed = false;
+        for block in &mut cfg.blocks {
+            let before = block.stmts.len();
+            block
+                .stmts
+                .retain(|bind| !is_pure(&bind.stmt) || uses.contains_key(&bind.var));
+            removed |= block.stmts.len() != before;
+        }
+        if !removed {
+            break;
+        }
+    }
+}
+
+fn is_pure(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Const(_)
+        | Stmt::Product(_)
+        | Stmt::Vector(_)
+        | Stmt::Map(_)
+        | Stmt::Fn(_)
+        | Stmt::Cast(_)
+        | Stmt::Parameter
+        | Stmt::Recursion
+        | Stmt::MatchResult(_) => true,
+        Stmt::Apply { .. } | Stmt::Perform(_) | Stmt::Link(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use deskc_type::Type;
+    use mir::{stmt::Terminator, var::VarId};
+
+    use crate::tests::{bind, block, cfg, int};
+
+    use super::*;
+
+    #[test]
+    fn removes_unused_stmts_transitively() {
+        let mut cfg = cfg(
+            vec![Type::Integer, Type::Integer, Type::Integer, Type::String],
+            vec![block(
+                vec![
+                    bind(0, int(1)),
+                    bind(1, Stmt::Cast(VarId(0))),
+                    bind(2, int(2)),
+                    bind(3, Stmt::Perform(VarId(2))),
+                ],
+                Terminator::Return(VarId(2)),
+            )],
+        );
+        remove_dead_stmts(&mut cfg);
+        assert_eq!(
+            cfg.blocks,
+            vec![block(
+                vec![bind(2, int(2)), bind(3, Stmt::Perform(VarId(2)))],
+                Terminator::Return(VarId(2)),
+            )]
+        );
+    }
+}
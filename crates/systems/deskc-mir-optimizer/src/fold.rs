@@ -0,0 +1,284 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use deskc_type::{conclusion::TypeConclusions, Type};
+use mir::{
+    mir::{ControlFlowGraph, Mir},
+    stmt::{Closure, Const, Stmt},
+    var::VarId,
+};
+use miri::{
+    const_stmt,
+    eval_cfg::EvalCfg,
+    operators::{Operator, OPERATORS},
+    value::{FnRef, OperatorOutput, Value},
+};
+
+use crate::vars::assignments;
+
+/// Captured types of each graph whose values are always the operators.
+///
+/// The entrypoint captures the operators unless the caller supplies values of their types,
+/// which is assumed not to happen. Other graphs capture whatever their creators pass, so a
+/// captured type is an operator only if every creator passes its own captured operator.
+pub fn operator_captures(mir: &Mir) -> Vec<HashSet<Type>> {
+    let candidates = |cfg: &ControlFlowGraph| {
+        cfg.captured
+            .iter()
+            .filter(|ty| OPERATORS.contains_key(*ty) && cfg.parameter.as_ref() != Some(*ty))
+            .cloned()
+            .collect::<HashSet<_>>()
+    };
+    let mut operators = vec![HashSet::new(); mir.cfgs.len()];
+    operators[mir.entrypoint.0] = candidates(&mir.cfgs[mir.entrypoint.0]);
+    // Nested graphs depend on their creators, so iterates until nothing is known newly.
+    loop {
+        let mut changed = false;
+        for (id, cfg) in mir.cfgs.iter().enumerate() {
+            if id == mir.entrypoint.0 {
+                continue;
+            }
+            let known = candidates(cfg)
+                .into_iter()
+                .filter(|ty| {
+                    let mut creators = creators(mir, id).peekable();
+                    creators.peek().is_some()
+                        && creators.all(|(creator, closure)| {
+                            passes_operator(&mir.cfgs[creator], &operators[creator], closure, ty)
+                        })
+                })
+                .collect::<HashSet<_>>();
+            if known != operators[id] {
+                operators[id] = known;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    operators
+}
+
+/// Closures that create the graph, with the graphs they are in.
+fn creators(mir: &Mir, id: usize) -> impl Iterator<Item = (usize, &Closure)> {
+    mir.cfgs.iter().enumerate().flat_map(move |(creator, cfg)| {
+        cfg.blocks
+            .iter()
+            .flat_map(|block| &block.stmts)
+            .filter_map(move |bind| match &bind.stmt {
+                Stmt::Fn(closure) if closure.mir.0 == id => Some((creator, closure)),
+                _ => None,
+            })
+    })
+}
+
+fn passes_operator(
+    creator: &ControlFlowGraph,
+    operators: &HashSet<Type>,
+    closure: &Closure,
+    ty: &Type,
+) -> bool {
+    operators.contains(ty)
+        && closure
+            .captured
+            .iter()
+            .filter(|var| &creator.vars.get(var).ty == ty)
+            .all(|var| {
+                creator
+                    .blocks
+                    .iter()
+                    .flat_map(|block| &block.stmts)
+                    .filter(|bind| bind.var == *var)
+                    .all(|bind| matches!(bind.stmt, Stmt::Parameter))
+            })
+}
+
+/// Replaces applies of operators with constant arguments by their results.
+///
+/// `operators` are the captured types whose values are the operators.
+pub fn fold_constants(
+    cfg: &mut ControlFlowGraph,
+    operators: &HashSet<Type>,
+    conclusion: Arc<TypeConclusions>,
+) {
+    let assignments = assignments(cfg);
+    let mut values: HashMap<VarId, Value> = HashMap::new();
+    // Blocks are not ordered by dependency, so evaluates until nothing is known newly.
+    loop {
+        let known = values.len();
+        for block in &cfg.blocks {
+            for bind in &block.stmts {
+                if assignments[&bind.var] != 1 || values.contains_key(&bind.var) {
+                    continue;
+                }
+                if let Some(value) =
+                    eval(cfg, operators, &values, &bind.var, &bind.stmt, &conclusion)
+                {
+                    values.insert(bind.var, value);
+                }
+            }
+        }
+        if values.len() == known {
+            break;
+        }
+    }
+    for block in &mut cfg.blocks {
+        for bind in &mut block.stmts {
+            if let Stmt::Apply { .. } = bind.stmt {
+                if let Some(value) = values.get(&bind.var).and_then(to_const) {
+                    bind.stmt = Stmt::Const(value);
+                }
+            }
+        }
+    }
+}
+
+fn eval(
+    cfg: &ControlFlowGraph,
+    operators: &HashSet<Type>,
+    values: &HashMap<VarId, Value>,
+    var: &VarId,
+    stmt: &Stmt,
+    conclusion: &Arc<TypeConclusions>,
+) -> Option<Value> {
+    let ty = |var: &VarId| &cfg.vars.get(var).ty;
+    match stmt {
+        Stmt::Const(value) => Some(const_stmt::eval(value)),
+        Stmt::Product(vars) => Some(Value::Product(
+            vars.iter()
+                .map(|var| Some((ty(var).clone(), values.get(var)?.clone())))
+                .collect::<Option<_>>()?,
+        )),
+        Stmt::Cast(from) => Some(EvalCfg::cast(
+            conclusion.clone(),
+            values.get(from)?,
+            ty(from),
+            ty(var),
+        )),
+        // The parameter of the function itself is not an operator.
+        Stmt::Parameter
+            if operators.contains(ty(var)) && cfg.parameter.as_ref() != Some(ty(var)) =>
+        {
+            OPERATORS
+                .get(ty(var))
+                .map(|operator| Value::FnRef(FnRef::Operator(*operator)))
+        }
+        Stmt::Apply {
+            function,
+            arguments,
+        } => {
+            let Some(Value::FnRef(FnRef::Operator(operator))) = values.get(function) else {
+                return None;
+            };
+            let [argument] = arguments.as_slice() else {
+                return None;
+            };
+            call(*operator, values.get(argument)?)
+        }
+        _ => None,
+    }
+}
+
+fn call(operator: Operator, argument: &Value) -> Option<Value> {
+    // Overflowing applies are left to the runtime.
+    match operator.checked_call(argument)? {
+        OperatorOutput::Return(value) => Some(value),
+        OperatorOutput::Perform { .. } => None,
+    }
+}
+
+fn to_const(value: &Value) -> Option<Const> {
+    match value {
+        Value::Int(value) => Some(Const::Int(*value)),
+        Value::Rational(a, b) => Some(Const::Rational(*a, *b)),
+        Value::Real(value) => Some(Const::Real(*value)),
+        Value::String(value) => Some(Const::String(value.clone())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use deskc_macros::ty;
+    use deskc_type::Type;
+    use mir::stmt::Terminator;
+
+    use crate::tests::{bind, block, cfg, int};
+
+    use super::*;
+
+    fn division(l: i64, r: i64) -> ControlFlowGraph {
+        let div = ty!(r#"
+            \ *<@l 'integer, @r 'integer> -> ! {
+                @`division by zero` 'integer ~> @quot 'integer
+            } @quot 'integer
+        "#);
+        let l_ty = ty!("@l 'integer");
+        let r_ty = ty!("@r 'integer");
+        cfg(
+            vec![
+                div,
+                l_ty.clone(),
+                r_ty.clone(),
+                Type::Product(vec![l_ty, r_ty]),
+                ty!("@quot 'integer"),
+            ],
+            vec![block(
+                vec![
+                    bind(0, Stmt::Parameter),
+                    bind(1, int(l)),
+                    bind(2, int(r)),
+                    bind(3, Stmt::Product(vec![VarId(1), VarId(2)])),
+                    bind(
+                        4,
+                        Stmt::Apply {
+                            function: VarId(0),
+                            arguments: vec![VarId(3)],
+                        },
+                    ),
+                ],
+                Terminator::Return(VarId(4)),
+            )],
+        )
+    }
+
+    fn operators(cfg: &ControlFlowGraph) -> HashSet<Type> {
+        [cfg.vars.get(&VarId(0)).ty.clone()].into()
+    }
+
+    #[test]
+    fn folds_operator() {
+        let mut cfg = division(7, 2);
+        let operators = operators(&cfg);
+        fold_constants(&mut cfg, &operators, Default::default());
+        assert_eq!(cfg.blocks[0].stmts[4], bind(4, int(3)));
+    }
+
+    #[test]
+    fn effects_are_not_folded() {
+        let before = division(7, 0);
+        let mut cfg = before.clone();
+        fold_constants(&mut cfg, &operators(&before), Default::default());
+        assert_eq!(cfg, before);
+    }
+
+    #[test]
+    fn overflow_is_not_folded() {
+        let before = division(i64::MIN, -1);
+        let mut cfg = before.clone();
+        fold_constants(&mut cfg, &operators(&before), Default::default());
+        assert_eq!(cfg, before);
+    }
+
+    #[test]
+    fn parameter_of_function_is_not_operator() {
+        let mut before = division(7, 2);
+        before.parameter = Some(before.vars.get(&VarId(0)).ty.clone());
+        let mut cfg = before.clone();
+        fold_constants(&mut cfg, &operators(&before), Default::default());
+        assert_eq!(cfg, before);
+    }
+}
@@ -0,0 +1,242 @@
+mod blocks;
+mod cast;
+mod dead_stmt;
+mod fold;
+mod vars;
+
+use std::{collections::HashSet, sync::Arc};
+
+use deskc_type::{conclusion::TypeConclusions, Type};
+use mir::mir::{ControlFlowGraph, Mir};
+
+/// Optimizes every control flow graph without changing the result of evaluation.
+pub fn optimize(mir: &Mir, conclusion: Arc<TypeConclusions>) -> Mir {
+    let operators = fold::operator_captures(mir);
+    Mir {
+        entrypoint: mir.entrypoint,
+        cfgs: mir
+            .cfgs
+            .iter()
+            .zip(&operators)
+            .map(|(cfg, operators)| optimize_cfg(cfg, operators, conclusion.clone()))
+            .collect(),
+    }
+}
+
+/// `operators` are the captured types whose values are the operators.
+pub fn optimize_cfg(
+    cfg: &ControlFlowGraph,
+    operators: &HashSet<Type>,
+    conclusion: Arc<TypeConclusions>,
+) -> ControlFlowGraph {
+    let mut cfg = cfg.clone();
+    fold::fold_constants(&mut cfg, operators, conclusion);
+    cast::remove_identity_casts(&mut cfg);
+    dead_stmt::remove_dead_stmts(&mut cfg);
+    blocks::thread_gotos(&mut cfg);
+    blocks::remove_unreachable_blocks(&mut cfg);
+    blocks::merge_blocks(&mut cfg);
+    blocks::remove_unreachable_blocks(&mut cfg);
+    cfg
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use deskc_type::Type;
+    use mir::{
+        block::BasicBlock,
+        mir::ControlFlowGraph,
+        scope::{Scope, ScopeId},
+        stmt::{Const, Stmt, StmtBind, Terminator},
+        var::{Var, VarId, Vars},
+    };
+
+    pub fn cfg(vars: Vec<Type>, blocks: Vec<BasicBlock>) -> ControlFlowGraph {
+        ControlFlowGraph {
+            parameter: None,
+            captured: vec![],
+            output: Type::Integer,
+            vars: Vars(
+                vars.into_iter()
+                    .map(|ty| Var {
+                        ty,
+                        scope: ScopeId(0),
+                    })
+                    .collect(),
+            ),
+            scopes: vec![Scope { super_scope: None }],
+            blocks,
+            links: vec![],
+        }
+    }
+
+    pub fn bind(var: usize, stmt: Stmt) -> StmtBind {
+        StmtBind {
+            var: VarId(var),
+            stmt,
+        }
+    }
+
+    pub fn block(stmts: Vec<StmtBind>, terminator: Terminator) -> BasicBlock {
+        BasicBlock { stmts, terminator }
+    }
+
+    pub fn int(value: i64) -> Stmt {
+        Stmt::Const(Const::Int(value))
+    }
+
+    /// Optimizes MIR in the textual format and prints it.
+    fn optimize_text(before: &str) -> String {
+        super::optimize(&before.parse().unwrap(), Default::default()).to_string()
+    }
+
+    #[test]
+    fn optimizes_addition() {
+        let before = r#"entrypoint cfg0
+
+cfg0 {
+  captured \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  output @sum 'integer
+  scope0
+  bb0 {
+    %0: \ *<@l 'integer, @r 'integer> -> @sum 'integer = parameter
+    %1: 'integer = const 1
+    %2: @l 'integer = cast %1
+    %3: 'integer = const 2
+    %4: @r 'integer = cast %3
+    %5: *<@l 'integer, @r 'integer> = product(%2, %4)
+    %6: @sum 'integer = apply %0(%5)
+    goto bb1
+  }
+  bb1 {
+    %7: @sum 'integer = cast %6
+    return %7
+  }
+}
+"#;
+        let after = r#"entrypoint cfg0
+
+cfg0 {
+  captured \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  output @sum 'integer
+  scope0
+  %0: \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  %1: 'integer
+  %2: @l 'integer
+  %3: 'integer
+  %4: @r 'integer
+  %5: *<@l 'integer, @r 'integer>
+  %7: @sum 'integer
+  bb0 {
+    %6: @sum 'integer = const 3
+    return %6
+  }
+}
+"#;
+        assert_eq!(optimize_text(before), after);
+    }
+
+    #[test]
+    fn folds_operators_captured_by_closures() {
+        let before = r#"entrypoint cfg0
+
+cfg0 {
+  captured \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  output \ 'integer -> @sum 'integer
+  scope0
+  bb0 {
+    %0: \ *<@l 'integer, @r 'integer> -> @sum 'integer = parameter
+    %1: \ 'integer -> @sum 'integer = fn cfg1(%0)
+    return %1
+  }
+}
+
+cfg1 {
+  parameter 'integer
+  captured \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  output @sum 'integer
+  scope0
+  bb0 {
+    %0: \ *<@l 'integer, @r 'integer> -> @sum 'integer = parameter
+    %1: @l 'integer = const 1
+    %2: @r 'integer = const 2
+    %3: *<@l 'integer, @r 'integer> = product(%1, %2)
+    %4: @sum 'integer = apply %0(%3)
+    return %4
+  }
+}
+"#;
+        let after = r#"entrypoint cfg0
+
+cfg0 {
+  captured \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  output \ 'integer -> @sum 'integer
+  scope0
+  bb0 {
+    %0: \ *<@l 'integer, @r 'integer> -> @sum 'integer = parameter
+    %1: \ 'integer -> @sum 'integer = fn cfg1(%0)
+    return %1
+  }
+}
+
+cfg1 {
+  parameter 'integer
+  captured \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  output @sum 'integer
+  scope0
+  %0: \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  %1: @l 'integer
+  %2: @r 'integer
+  %3: *<@l 'integer, @r 'integer>
+  bb0 {
+    %4: @sum 'integer = const 3
+    return %4
+  }
+}
+"#;
+        assert_eq!(optimize_text(before), after);
+    }
+
+    #[test]
+    fn captured_function_shadows_operator() {
+        let before = r#"entrypoint cfg0
+
+cfg0 {
+  captured \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  output \ 'integer -> @sum 'integer
+  scope0
+  bb0 {
+    %0: \ *<@l 'integer, @r 'integer> -> @sum 'integer = fn cfg1()
+    %1: \ 'integer -> @sum 'integer = fn cfg2(%0)
+    return %1
+  }
+}
+
+cfg1 {
+  parameter *<@l 'integer, @r 'integer>
+  output @sum 'integer
+  scope0
+  bb0 {
+    %0: @sum 'integer = const 0
+    return %0
+  }
+}
+
+cfg2 {
+  parameter 'integer
+  captured \ *<@l 'integer, @r 'integer> -> @sum 'integer
+  output @sum 'integer
+  scope0
+  bb0 {
+    %0: \ *<@l 'integer, @r 'integer> -> @sum 'integer = parameter
+    %1: @l 'integer = const 1
+    %2: @r 'integer = const 2
+    %3: *<@l 'integer, @r 'integer> = product(%1, %2)
+    %4: @sum 'integer = apply %0(%3)
+    return %4
+  }
+}
+"#;
+        assert_eq!(optimize_text(before), before);
+    }
+}
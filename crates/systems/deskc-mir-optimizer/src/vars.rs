@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use mir::{
+    mir::ControlFlowGraph,
+    stmt::{Closure, MapElem, Stmt, Terminator},
+    var::VarId,
+};
+
+/// How many times each var is assigned in the graph.
+///
+/// Only vars assigned once have a single value; results of match arms are assigned in every arm.
+pub fn assignments(cfg: &ControlFlowGraph) -> HashMap<VarId, usize> {
+    let mut assignments = HashMap::new();
+    for block in &cfg.blocks {
+        for bind in &block.stmts {
+            *assignments.entry(bind.var).or_default() += 1;
+        }
+    }
+    assignments
+}
+
+/// How many times each var is used in the graph.
+pub fn uses(cfg: &ControlFlowGraph) -> HashMap<VarId, usize> {
+    let mut uses = HashMap::new();
+    let mut count = |var: &mut VarId| *uses.entry(*var).or_default() += 1;
+    // Counting needs no mutation, but sharing the visitor keeps them in sync.
+    let mut cfg = cfg.clone();
+    for block in &mut cfg.blocks {
+        for bind in &mut block.stmts {
+            visit_stmt_vars(&mut bind.stmt, &mut count);
+        }
+        visit_terminator_vars(&mut block.terminator, &mut count);
+    }
+    uses
+}
+
+/// Replaces uses of vars, not assignments.
+pub fn rename_uses(cfg: &mut ControlFlowGraph, renames: &HashMap<VarId, VarId>) {
+    let mut rename = |var: &mut VarId| {
+        while let Some(renamed) = renames.get(var) {
+            *var = *renamed;
+        }
+    };
+    for block in &mut cfg.blocks {
+        for bind in &mut block.stmts {
+            visit_stmt_vars(&mut bind.stmt, &mut rename);
+        }
+        visit_terminator_vars(&mut block.terminator, &mut rename);
+    }
+}
+
+fn visit_stmt_vars(stmt: &mut Stmt, f: &mut impl FnMut(&mut VarId)) {
+    match stmt {
+        Stmt::Product(vars) | Stmt::Vector(vars) => vars.iter_mut().for_each(f),
+        Stmt::Map(elems) => elems.iter_mut().for_each(|MapElem { key, value }| {
+            f(key);
+            f(value);
+        }),
+        Stmt::Fn(Closure {
+            mir: _,
+            captured,
+            handlers,
+        }) => {
+            captured.iter_mut().for_each(&mut *f);
+            handlers.values_mut().for_each(f);
+        }
+        Stmt::Perform(var) | Stmt::MatchResult(var) | Stmt::Cast(var) => f(var),
+        Stmt::Apply {
+            function,
+            arguments,
+        } => {
+            f(function);
+            arguments.iter_mut().for_each(f);
+        }
+        Stmt::Const(_) | Stmt::Parameter | Stmt::Recursion | Stmt::Link(_) => {}
+    }
+}
+
+fn visit_terminator_vars(terminator: &mut Terminator, f: &mut impl FnMut(&mut VarId)) {
+    match terminator {
+        Terminator::Return(var) | Terminator::Match { var, cases: _ } => f(var),
+        Terminator::Goto(_) => {}
+    }
+}
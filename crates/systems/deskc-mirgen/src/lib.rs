@@ -1,5 +1,6 @@
 mod block_proto;
 mod mir_proto;
+mod monomorphization;
 mod scope_proto;
 
 use std::collections::HashMap;
@@ -16,6 +17,9 @@ use mir::{
     var::VarId,
 };
 use mir_proto::MirProto;
+use monomorphization::{
+    function_body, instantiate_application, substitute, PolymorphicDefinition, Substitution,
+};
 use ty::{conclusion::TypeConclusions, Effect, Type};
 
 pub fn gen_mir(expr: &WithMeta<Expr>, conclusion: &TypeConclusions) -> Result<Mir, GenMirError> {
@@ -30,6 +34,8 @@ pub struct MirGen<'a> {
     mirs: Vec<ControlFlowGraph>,
     protos: Vec<MirProto>,
     conclusion: &'a TypeConclusions,
+    // a stack; substitution of the current specialization
+    substitutions: Vec<Substitution>,
 }
 
 impl<'a> MirGen<'a> {
@@ -38,6 +44,7 @@ impl<'a> MirGen<'a> {
             mirs: vec![],
             protos: vec![MirProto::default()],
             conclusion,
+            substitutions: vec![],
         }
     }
 }
@@ -62,10 +69,10 @@ impl MirGen<'_> {
         self.begin_mir();
         let var = self.gen_stmt(hir)?;
         let ty = self.get_type(hir)?;
-        Ok(self.end_mir(var, ty.clone()))
+        Ok(self.end_mir(var, ty))
     }
     pub fn gen_stmt(&mut self, hir: &WithMeta<Expr>) -> Result<VarId, GenMirError> {
-        let ty = self.get_type(hir)?.clone();
+        let ty = self.get_type(hir)?;
         self.gen_stmt_ty(hir, &ty)
     }
     pub fn gen_stmt_ty(
@@ -83,6 +90,11 @@ impl MirGen<'_> {
                 };
                 self.mir_proto().bind_stmt(stmt_ty.clone(), const_value)
             }
+            Expr::Hole => {
+                return Err(GenMirError::Hole {
+                    for_expr: hir.meta.clone(),
+                })
+            }
             Expr::Vector(values) => {
                 let values = values
                     .iter()
@@ -112,37 +124,35 @@ impl MirGen<'_> {
                 self.mir_proto().begin_scope();
 
                 // gen definition
-                let def_var = if let Expr::Function { parameter: _, body } = &definition.value {
-                    let definition_ty = self.get_type(definition)?.clone();
-                    let Type::Function(function) = definition_ty.clone() else {
-                        return Err(GenMirError::FunctionInferredAsNonFunction {
-                            for_expr: definition.meta.clone(),
-                        });
+                let definition_ty = self.get_type(definition)?;
+                if let (Type::ForAll { .. }, Some(_)) = (&definition_ty, function_body(definition))
+                {
+                    // Generated for each type on reference.
+                    let definition = PolymorphicDefinition {
+                        ty: definition_ty,
+                        definition: definition.as_ref().clone(),
+                        substitution: self.substitutions.last().cloned().unwrap_or_default(),
                     };
-                    // prepare recursion
-                    let recursion_var = self
-                        .mir_proto()
-                        .bind_stmt(definition_ty.clone(), Stmt::Recursion);
-                    self.mir_proto().create_named_var(recursion_var);
-                    // gen definition
-                    let function = self.gen_function(&function.parameter, body)?;
-                    let fn_ref = self.create_closure(function, Default::default());
-                    // finish recursion
-                    self.mir_proto().bind_stmt(definition_ty, Stmt::Fn(fn_ref))
+                    self.mir_proto()
+                        .current_scope()
+                        .polymorphic_definitions
+                        .push(definition);
+                } else if let Expr::Function { parameter: _, body } = &definition.value {
+                    let def_var = self.gen_let_function(definition_ty, definition, body)?;
+                    self.mir_proto().create_named_var(def_var);
                 } else {
-                    // gen definition
-                    self.gen_stmt(definition)?
+                    let def_var = self.gen_stmt(definition)?;
+                    // make it named
+                    self.mir_proto().create_named_var(def_var);
                 };
 
-                // make it named
-                self.mir_proto().create_named_var(def_var);
                 // gen body
                 let var = self.gen_stmt(expr)?;
 
                 self.mir_proto().end_scope_then_return(var)
             }
             Expr::Perform { input, output } | Expr::Continue { input, output } => {
-                let output = self.get_type(output)?.clone();
+                let output = self.get_type(output)?;
                 let var = self.gen_stmt(input)?;
                 let output_var = self.mir_proto().bind_stmt(output, Stmt::Perform(var));
                 self.mir_proto()
@@ -155,8 +165,8 @@ impl MirGen<'_> {
                     .map(|handler| {
                         let Handler { effect, handler } = &handler.value;
                         let effect = Effect {
-                            input: self.get_type(&effect.input)?.clone(),
-                            output: self.get_type(&effect.output)?.clone(),
+                            input: self.get_type(&effect.input)?,
+                            output: self.get_type(&effect.output)?,
                         };
                         self.begin_mir();
                         self.set_parameter(effect.input.clone());
@@ -164,7 +174,7 @@ impl MirGen<'_> {
                         let handler_cfg_id = self.end_mir(handler_end, stmt_ty.clone());
                         let handler_type = self.get_mir(&handler_cfg_id).get_type().clone();
                         // call effectful mir
-                        let fn_ref = self.create_closure(handler_cfg_id, Default::default());
+                        let fn_ref = self.create_closure(handler_cfg_id, Default::default())?;
                         let handler_var =
                             self.mir_proto().bind_stmt(handler_type, Stmt::Fn(fn_ref));
                         Ok((effect, handler_var))
@@ -177,7 +187,7 @@ impl MirGen<'_> {
                 let effectful_cfg_id = self.end_mir(effectful_end, stmt_ty.clone());
                 let effectful_type = self.get_mir(&effectful_cfg_id).get_type().clone();
 
-                let fn_ref = self.create_closure(effectful_cfg_id, handlers);
+                let fn_ref = self.create_closure(effectful_cfg_id, handlers)?;
                 let effectful_fun = self.mir_proto().bind_stmt(effectful_type, Stmt::Fn(fn_ref));
                 self.mir_proto().bind_stmt(
                     stmt_ty.clone(),
@@ -192,12 +202,23 @@ impl MirGen<'_> {
                 link_name,
                 arguments,
             } => {
-                let function_ty = self.get_type(function_ty)?.clone();
+                let function_ty = match self.get_type(function_ty)? {
+                    // The arguments tell which specialization is referenced.
+                    polymorphic @ Type::ForAll { .. } if link_name == &LinkName::None => {
+                        let argument_types = arguments
+                            .iter()
+                            .map(|arg| self.get_type(arg))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        instantiate_application(&polymorphic, &argument_types, stmt_ty)
+                            .unwrap_or(polymorphic)
+                    }
+                    ty => ty,
+                };
                 let function = if link_name != &LinkName::None {
                     self.mir_proto()
                         .bind_link(function_ty.clone(), link_name.clone())
                 } else {
-                    self.mir_proto().find_var(&function_ty)
+                    self.find_var(&function_ty)?
                 };
                 if arguments.is_empty() {
                     function
@@ -207,10 +228,19 @@ impl MirGen<'_> {
                         .iter()
                         .map(|arg| {
                             let Some(parameter) = parameters.next() else {
-                                return Err(GenMirError::InvalidFunctionCall { expr: hir.meta.clone(), ty: function_ty.clone(), arguments: arguments.iter().map(|arg| arg.meta.clone()).collect() });
+                                return Err(GenMirError::InvalidFunctionCall {
+                                    expr: hir.meta.clone(),
+                                    ty: function_ty.clone(),
+                                    arguments: arguments
+                                        .iter()
+                                        .map(|arg| arg.meta.clone())
+                                        .collect(),
+                                });
                             };
                             let var = self.gen_stmt(arg)?;
-                            Ok(self.mir_proto().bind_stmt(parameter.clone(), Stmt::Cast(var)))
+                            Ok(self
+                                .mir_proto()
+                                .bind_stmt(parameter.clone(), Stmt::Cast(var)))
                         })
                         .collect::<Result<Vec<_>, _>>()?;
                     self.mir_proto().bind_stmt(
@@ -231,9 +261,9 @@ impl MirGen<'_> {
                     .bind_stmt(stmt_ty.clone(), Stmt::Product(values))
             }
             Expr::Function { parameter, body } => {
-                let parameter = self.get_type(parameter)?.clone();
+                let parameter = self.get_type(parameter)?;
                 let function = self.gen_function(&parameter, body)?;
-                let fn_ref = self.create_closure(function, Default::default());
+                let fn_ref = self.create_closure(function, Default::default())?;
                 self.mir_proto()
                     .bind_stmt(stmt_ty.clone(), Stmt::Fn(fn_ref))
             }
@@ -242,7 +272,7 @@ impl MirGen<'_> {
                 let sum_type = Type::sum(
                     cases
                         .iter()
-                        .map(|c| Ok(self.get_type(&c.value.ty)?.clone()))
+                        .map(|c| self.get_type(&c.value.ty))
                         .collect::<Result<_, _>>()?,
                 );
                 let input = self.gen_stmt(of)?;
@@ -252,7 +282,8 @@ impl MirGen<'_> {
                 let goal_block_id = self.mir_proto().begin_block();
                 self.mir_proto().defer_block();
 
-                let match_result_var = self.mir_proto().create_var(stmt_ty.clone());
+                // assigned by every arm
+                let arm_result_var = self.mir_proto().create_var(stmt_ty.clone());
                 let cases: Vec<_> = cases
                     .iter()
                     .map(|case| {
@@ -261,11 +292,11 @@ impl MirGen<'_> {
                         let case_block_id = self.mir_proto().begin_block();
                         let match_case_result = self.gen_stmt(expr)?;
                         self.mir_proto()
-                            .bind_to(match_result_var, Stmt::Cast(match_case_result));
+                            .bind_to(arm_result_var, Stmt::Cast(match_case_result));
                         // close the last block with goto goal
                         self.mir_proto().end_block(Terminator::Goto(goal_block_id));
                         Ok(MatchCase {
-                            ty: self.get_type(ty)?.clone(),
+                            ty: self.get_type(ty)?,
                             next: case_block_id,
                         })
                     })
@@ -274,27 +305,70 @@ impl MirGen<'_> {
                     .end_block(Terminator::Match { var: input, cases });
                 // undefer the goal block
                 self.mir_proto().pop_deferred_block();
-                match_result_var
+                // join the arms
+                self.mir_proto()
+                    .bind_stmt(stmt_ty.clone(), Stmt::MatchResult(arm_result_var))
             }
-            Expr::Label { label: _, item } | Expr::Brand { brand: _, item } => {
+            Expr::Label { label, item } | Expr::Brand { brand: label, item } => {
                 // TODO: simplify this.
                 if let Expr::Apply { .. } = item.value {
                     // Reference needs a correct type.
                     let var = self.gen_stmt(item)?;
                     self.mir_proto().bind_stmt(stmt_ty.clone(), Stmt::Cast(var))
                 } else {
-                    self.gen_stmt_ty(item, stmt_ty)?
+                    let item_ty = Box::new(self.get_type(item)?);
+                    let ty = if let Expr::Label { .. } = hir.value {
+                        Type::Label {
+                            label: label.clone(),
+                            item: item_ty,
+                        }
+                    } else {
+                        Type::Brand {
+                            brand: label.clone(),
+                            item: item_ty,
+                        }
+                    };
+                    if &ty == stmt_ty {
+                        self.gen_stmt_ty(item, stmt_ty)?
+                    } else {
+                        // Checked against a supertype such as a sum.
+                        let var = self.gen_stmt_ty(item, &ty)?;
+                        self.mir_proto().bind_stmt(stmt_ty.clone(), Stmt::Cast(var))
+                    }
                 }
             }
             Expr::Typed { ty, item } => {
                 let var = self.gen_stmt(item)?;
-                let ty = self.get_type(ty)?.clone();
+                let ty = self.get_type(ty)?;
                 self.mir_proto().bind_stmt(ty, Stmt::Cast(var))
             }
         };
         Ok(var_id)
     }
 
+    fn gen_let_function(
+        &mut self,
+        definition_ty: Type,
+        definition: &WithMeta<Expr>,
+        body: &WithMeta<Expr>,
+    ) -> Result<VarId, GenMirError> {
+        let Type::Function(function) = definition_ty.clone() else {
+            return Err(GenMirError::FunctionInferredAsNonFunction {
+                for_expr: definition.meta.clone(),
+            });
+        };
+        // prepare recursion
+        let recursion_var = self
+            .mir_proto()
+            .bind_stmt(definition_ty.clone(), Stmt::Recursion);
+        self.mir_proto().create_named_var(recursion_var);
+        // gen definition
+        let function = self.gen_function(&function.parameter, body)?;
+        let fn_ref = self.create_closure(function, Default::default())?;
+        // finish recursion
+        Ok(self.mir_proto().bind_stmt(definition_ty, Stmt::Fn(fn_ref)))
+    }
+
     fn gen_function(
         &mut self,
         parameter: &Type,
@@ -309,7 +383,7 @@ impl MirGen<'_> {
         let var = self.gen_stmt(body)?;
 
         // Out of function
-        let ty = self.get_type(body)?.clone();
+        let ty = self.get_type(body)?;
         Ok(self.end_mir(var, ty))
     }
 
@@ -317,19 +391,40 @@ impl MirGen<'_> {
         &mut self,
         cfg_id: ControlFlowGraphId,
         handlers: HashMap<Effect, VarId>,
-    ) -> Closure {
-        let mir = get_mir!(self, cfg_id);
-        let captured = mir
-            .captured
+    ) -> Result<Closure, GenMirError> {
+        let captured = get_mir!(self, cfg_id).captured.clone();
+        let captured = captured
             .iter()
-            .map(|ty| mir_proto!(self).find_var(ty))
-            .collect();
+            .map(|ty| self.find_var(ty))
+            .collect::<Result<_, _>>()?;
 
-        Closure {
+        Ok(Closure {
             mir: cfg_id,
             captured,
             handlers,
+        })
+    }
+
+    /// Finds a variable of the type, specializing a polymorphic definition if required.
+    fn find_var(&mut self, ty: &Type) -> Result<VarId, GenMirError> {
+        if let Some(var) = self.mir_proto().find_named_var(ty) {
+            return Ok(var);
         }
+        let Some((polymorphic, substitution)) = self.mir_proto().find_polymorphic(ty) else {
+            return Ok(self.mir_proto().find_var(ty));
+        };
+        let Some(body) = function_body(&polymorphic.definition) else {
+            return Err(GenMirError::FunctionInferredAsNonFunction {
+                for_expr: polymorphic.definition.meta.clone(),
+            });
+        };
+        self.substitutions.push(substitution);
+        let var = self.gen_let_function(ty.clone(), &polymorphic.definition, body);
+        self.substitutions.pop();
+        let var = var?;
+        // Later references use the same specialization.
+        self.mir_proto().create_named_var(var);
+        Ok(var)
     }
 
     fn set_parameter(&mut self, parameter: Type) {
@@ -354,12 +449,17 @@ impl MirGen<'_> {
         get_mir!(self, id)
     }
 
-    fn get_type<T: std::fmt::Debug>(&self, hir: &WithMeta<T>) -> Result<&Type, GenMirError> {
-        self.conclusion
-            .get_type(&hir.meta.id)
-            .ok_or_else(|| GenMirError::TypeNotFound {
-                for_expr: hir.meta.clone(),
-            })
+    fn get_type<T: std::fmt::Debug>(&self, hir: &WithMeta<T>) -> Result<Type, GenMirError> {
+        let ty =
+            self.conclusion
+                .get_type(&hir.meta.id)
+                .ok_or_else(|| GenMirError::TypeNotFound {
+                    for_expr: hir.meta.clone(),
+                })?;
+        Ok(match self.substitutions.last() {
+            Some(substitution) => substitute(ty, substitution),
+            None => ty.clone(),
+        })
     }
 }
 
@@ -383,6 +483,8 @@ mod tests {
         let conclusion = TypeConclusions {
             types: [(hir.meta.id.clone(), Type::Integer)].into_iter().collect(),
             cast_strategies: Default::default(),
+            holes: Default::default(),
+            warnings: Default::default(),
         };
         let mut gen = MirGen::new(&conclusion);
         gen.gen_mir(&hir).unwrap();
@@ -10,7 +10,11 @@ use mir::{
 };
 use ty::Type;
 
-use crate::{block_proto::BlockProto, scope_proto::ScopeProto};
+use crate::{
+    block_proto::BlockProto,
+    monomorphization::{instantiate, PolymorphicDefinition, Substitution},
+    scope_proto::ScopeProto,
+};
 
 pub struct MirProto {
     parameter: Option<Type>,
@@ -24,8 +28,8 @@ pub struct MirProto {
     current_block: Vec<BlockId>,
     // Deferred block stack
     deferred_block: Vec<BlockId>,
-    // Values that referenced but not included in parameter
-    captured_values: HashMap<Type, VarId>,
+    // Values that referenced but not included in parameter, with the block that binds them
+    captured_values: HashMap<Type, (BlockId, VarId)>,
     // Values that referenced with link name
     links: HashSet<LinkId>,
 }
@@ -118,21 +122,50 @@ impl MirProto {
         self.blocks_proto.get_mut(&self.current_block_id()).unwrap()
     }
 
-    pub fn find_var(&mut self, ty: &Type) -> VarId {
+    pub fn find_named_var(&mut self, ty: &Type) -> Option<VarId> {
         let mut next_scope_id = Some(self.current_scope_id());
         while let Some(scope_id) = next_scope_id {
             let scope = self.get_scope(&scope_id);
             next_scope_id = scope.super_id;
             if let Some(var_id) = scope.named_vars.get(ty) {
-                return *var_id;
+                return Some(*var_id);
+            }
+        }
+        None
+    }
+
+    /// Finds a polymorphic definition that can be the type, and the substitution to specialize it.
+    pub fn find_polymorphic(&mut self, ty: &Type) -> Option<(PolymorphicDefinition, Substitution)> {
+        let mut next_scope_id = Some(self.current_scope_id());
+        while let Some(scope_id) = next_scope_id {
+            let scope = self.get_scope(&scope_id);
+            next_scope_id = scope.super_id;
+            for definition in scope.polymorphic_definitions.iter().rev() {
+                if let Some(instance) = instantiate(&definition.ty, ty) {
+                    let mut substitution = definition.substitution.clone();
+                    substitution.extend(instance);
+                    return Some((definition.clone(), substitution));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn find_var(&mut self, ty: &Type) -> VarId {
+        if let Some(var_id) = self.find_named_var(ty) {
+            return var_id;
+        }
+        let block_id = self.current_block_id();
+        match self.captured_values.get(ty) {
+            // A var bound in another block may not be bound on this path, e.g. in another case.
+            Some((bound_in, var)) if *bound_in == block_id => *var,
+            _ => {
+                let var = self.create_var(ty.clone());
+                self.captured_values.insert(ty.clone(), (block_id, var));
+                self.bind_to(var, Stmt::Parameter);
+                var
             }
         }
-        self.captured_values.get(ty).cloned().unwrap_or_else(|| {
-            let var = self.create_var(ty.clone());
-            self.captured_values.insert(ty.clone(), var);
-            self.bind_to(var, Stmt::Parameter);
-            var
-        })
     }
 
     pub fn bind_link(&mut self, ty: Type, name: LinkName) -> VarId {
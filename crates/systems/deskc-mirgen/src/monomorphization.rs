@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+
+use hir::{expr::Expr, meta::WithMeta};
+use ty::{Effect, EffectExpr, Function, Type};
+
+/// Types of type variables.
+pub type Substitution = HashMap<String, Type>;
+
+/// A let-bound function generated for each type it is referenced with.
+#[derive(Debug, Clone)]
+pub struct PolymorphicDefinition {
+    /// The `ForAll` type of the definition.
+    pub ty: Type,
+    pub definition: WithMeta<Expr>,
+    /// Substitution of the specialization that encloses the definition.
+    pub substitution: Substitution,
+}
+
+/// The function of a let definition under its type annotations.
+pub fn function_body(definition: &WithMeta<Expr>) -> Option<&WithMeta<Expr>> {
+    match &definition.value {
+        Expr::Function { parameter: _, body } => Some(body),
+        Expr::Typed { ty: _, item } => function_body(item),
+        _ => None,
+    }
+}
+
+/// Finds types of the universal variables of `polymorphic` that make it `ty`.
+pub fn instantiate(polymorphic: &Type, ty: &Type) -> Option<Substitution> {
+    let (mut instantiation, body) = Instantiation::new(polymorphic)?;
+    instantiation
+        .unify(body, ty)
+        .then_some(instantiation.substitution)
+}
+
+/// The instance of `polymorphic` that is applied to `arguments` and results in `output`.
+///
+/// e.g. a recursive reference to a polymorphic function, which is typed by its `ForAll` type.
+pub fn instantiate_application(
+    polymorphic: &Type,
+    arguments: &[Type],
+    output: &Type,
+) -> Option<Type> {
+    let (mut instantiation, body) = Instantiation::new(polymorphic)?;
+    let mut parameters = vec![];
+    let mut rest = body;
+    for _ in arguments {
+        let Type::Function(function) = rest else {
+            return None;
+        };
+        parameters.push(&function.parameter);
+        rest = &function.body;
+    }
+    if !instantiation.unify(rest, output) {
+        return None;
+    }
+    // Arguments may be subtypes of the parameters, which are cast on application.
+    for (parameter, argument) in parameters.into_iter().zip(arguments) {
+        let mut unified = Instantiation {
+            variables: instantiation.variables.clone(),
+            substitution: instantiation.substitution.clone(),
+        };
+        if unified.unify(parameter, argument) {
+            instantiation = unified;
+        }
+    }
+    let Instantiation {
+        variables,
+        substitution,
+    } = instantiation;
+    variables
+        .iter()
+        .all(|variable| substitution.contains_key(variable))
+        .then(|| substitute(body, &substitution))
+}
+
+struct Instantiation {
+    variables: Vec<String>,
+    substitution: Substitution,
+}
+
+impl Instantiation {
+    fn new(polymorphic: &Type) -> Option<(Self, &Type)> {
+        let mut variables = vec![];
+        let mut body = polymorphic;
+        while let Type::ForAll {
+            variable,
+            bound: _,
+            body: inner,
+        } = body
+        {
+            variables.push(variable.clone());
+            body = inner;
+        }
+        if variables.is_empty() {
+            return None;
+        }
+        let instantiation = Instantiation {
+            variables,
+            substitution: Substitution::new(),
+        };
+        Some((instantiation, body))
+    }
+
+    fn unify(&mut self, polymorphic: &Type, ty: &Type) -> bool {
+        match (polymorphic, ty) {
+            (Type::Variable(variable), ty) if self.variables.contains(variable) => {
+                match self.substitution.get(variable) {
+                    Some(instance) => instance == ty,
+                    None => {
+                        self.substitution.insert(variable.clone(), ty.clone());
+                        true
+                    }
+                }
+            }
+            (Type::Product(polymorphic), Type::Product(types))
+            | (Type::Sum(polymorphic), Type::Sum(types)) => self.unify_all(polymorphic, types),
+            (Type::Function(polymorphic), Type::Function(function)) => {
+                self.unify(&polymorphic.parameter, &function.parameter)
+                    && self.unify(&polymorphic.body, &function.body)
+            }
+            (Type::Vector(polymorphic), Type::Vector(ty)) => self.unify(polymorphic, ty),
+            (
+                Type::Map {
+                    key: polymorphic_key,
+                    value: polymorphic_value,
+                },
+                Type::Map { key, value },
+            ) => self.unify(polymorphic_key, key) && self.unify(polymorphic_value, value),
+            (
+                Type::ForAll {
+                    variable: polymorphic_variable,
+                    bound: _,
+                    body: polymorphic,
+                },
+                Type::ForAll {
+                    variable,
+                    bound: _,
+                    body,
+                },
+            ) => polymorphic_variable == variable && self.unify(polymorphic, body),
+            (
+                Type::Effectful {
+                    ty: polymorphic,
+                    effects: polymorphic_effects,
+                },
+                Type::Effectful { ty, effects },
+            ) => self.unify(polymorphic, ty) && self.unify_effects(polymorphic_effects, effects),
+            // Effects of applications may vanish with pure instances.
+            (
+                Type::Effectful {
+                    ty: polymorphic,
+                    effects: _,
+                },
+                ty,
+            ) => self.unify(polymorphic, ty),
+            (
+                Type::Brand {
+                    brand: polymorphic_brand,
+                    item: polymorphic,
+                },
+                Type::Brand { brand, item },
+            ) => polymorphic_brand == brand && self.unify(polymorphic, item),
+            (
+                Type::Label {
+                    label: polymorphic_label,
+                    item: polymorphic,
+                },
+                Type::Label { label, item },
+            ) => polymorphic_label == label && self.unify(polymorphic, item),
+            (polymorphic, ty) => polymorphic == ty,
+        }
+    }
+
+    fn unify_all(&mut self, polymorphic: &[Type], types: &[Type]) -> bool {
+        polymorphic.len() == types.len()
+            && polymorphic
+                .iter()
+                .zip(types)
+                .all(|(polymorphic, ty)| self.unify(polymorphic, ty))
+    }
+
+    fn unify_effects(&mut self, polymorphic: &EffectExpr, effects: &EffectExpr) -> bool {
+        match (polymorphic, effects) {
+            (EffectExpr::Effects(polymorphic), EffectExpr::Effects(effects)) => {
+                polymorphic.len() == effects.len()
+                    && polymorphic
+                        .iter()
+                        .zip(effects)
+                        .all(|(polymorphic, effect)| {
+                            self.unify(&polymorphic.input, &effect.input)
+                                && self.unify(&polymorphic.output, &effect.output)
+                        })
+            }
+            (EffectExpr::Add(polymorphic), EffectExpr::Add(effects)) => {
+                polymorphic.len() == effects.len()
+                    && polymorphic
+                        .iter()
+                        .zip(effects)
+                        .all(|(polymorphic, effects)| self.unify_effects(polymorphic, effects))
+            }
+            (
+                EffectExpr::Sub {
+                    minuend: polymorphic_minuend,
+                    subtrahend: polymorphic_subtrahend,
+                },
+                EffectExpr::Sub {
+                    minuend,
+                    subtrahend,
+                },
+            ) => {
+                self.unify_effects(polymorphic_minuend, minuend)
+                    && self.unify_effects(polymorphic_subtrahend, subtrahend)
+            }
+            (
+                EffectExpr::Apply {
+                    function: polymorphic_function,
+                    arguments: polymorphic_arguments,
+                },
+                EffectExpr::Apply {
+                    function,
+                    arguments,
+                },
+            ) => {
+                self.unify(polymorphic_function, function)
+                    && self.unify_all(polymorphic_arguments, arguments)
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn substitute(ty: &Type, substitution: &Substitution) -> Type {
+    match ty {
+        Type::Variable(variable) => substitution
+            .get(variable)
+            .cloned()
+            .unwrap_or_else(|| ty.clone()),
+        Type::Product(types) => Type::Product(substitute_all(types, substitution)),
+        Type::Sum(types) => Type::Sum(substitute_all(types, substitution)),
+        Type::Function(function) => Type::Function(Box::new(Function {
+            parameter: substitute(&function.parameter, substitution),
+            body: substitute(&function.body, substitution),
+        })),
+        Type::Vector(ty) => Type::Vector(Box::new(substitute(ty, substitution))),
+        Type::Map { key, value } => Type::Map {
+            key: Box::new(substitute(key, substitution)),
+            value: Box::new(substitute(value, substitution)),
+        },
+        Type::ForAll {
+            variable,
+            bound,
+            body,
+        } => {
+            // The variable is shadowed in the body.
+            let mut substitution = substitution.clone();
+            substitution.remove(variable);
+            Type::ForAll {
+                variable: variable.clone(),
+                bound: bound
+                    .as_ref()
+                    .map(|bound| Box::new(substitute(bound, &substitution))),
+                body: Box::new(substitute(body, &substitution)),
+            }
+        }
+        Type::Effectful { ty, effects } => Type::Effectful {
+            ty: Box::new(substitute(ty, substitution)),
+            effects: substitute_effects(effects, substitution),
+        },
+        Type::Brand { brand, item } => Type::Brand {
+            brand: brand.clone(),
+            item: Box::new(substitute(item, substitution)),
+        },
+        Type::Label { label, item } => Type::Label {
+            label: label.clone(),
+            item: Box::new(substitute(item, substitution)),
+        },
+        Type::Real | Type::Rational | Type::Integer | Type::String => ty.clone(),
+    }
+}
+
+fn substitute_all(types: &[Type], substitution: &Substitution) -> Vec<Type> {
+    types
+        .iter()
+        .map(|ty| substitute(ty, substitution))
+        .collect()
+}
+
+fn substitute_effects(effects: &EffectExpr, substitution: &Substitution) -> EffectExpr {
+    match effects {
+        EffectExpr::Effects(effects) => EffectExpr::Effects(
+            effects
+                .iter()
+                .map(|effect| Effect {
+                    input: substitute(&effect.input, substitution),
+                    output: substitute(&effect.output, substitution),
+                })
+                .collect(),
+        ),
+        EffectExpr::Add(effects) => EffectExpr::Add(
+            effects
+                .iter()
+                .map(|effects| substitute_effects(effects, substitution))
+                .collect(),
+        ),
+        EffectExpr::Sub {
+            minuend,
+            subtrahend,
+        } => EffectExpr::Sub {
+            minuend: Box::new(substitute_effects(minuend, substitution)),
+            subtrahend: Box::new(substitute_effects(subtrahend, substitution)),
+        },
+        EffectExpr::Apply {
+            function,
+            arguments,
+        } => EffectExpr::Apply {
+            function: Box::new(substitute(function, substitution)),
+            arguments: substitute_all(arguments, substitution),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity() -> Type {
+        Type::ForAll {
+            variable: "a".into(),
+            bound: None,
+            body: Box::new(Type::function(
+                Type::Variable("a".into()),
+                Type::Variable("a".into()),
+            )),
+        }
+    }
+
+    #[test]
+    fn instantiate_identity() {
+        assert_eq!(
+            instantiate(&identity(), &Type::function(Type::Integer, Type::Integer)),
+            Some([("a".to_string(), Type::Integer)].into_iter().collect())
+        );
+    }
+
+    #[test]
+    fn instantiate_inconsistently() {
+        assert_eq!(
+            instantiate(&identity(), &Type::function(Type::Integer, Type::String)),
+            None
+        );
+    }
+
+    #[test]
+    fn instantiate_application_with_subtype_argument() {
+        let labeled = Type::Label {
+            label: "a".into(),
+            item: Box::new(Type::Integer),
+        };
+        assert_eq!(
+            instantiate_application(&identity(), &[labeled], &Type::Integer),
+            Some(Type::function(Type::Integer, Type::Integer))
+        );
+    }
+
+    #[test]
+    fn instantiate_application_of_too_many_arguments() {
+        assert_eq!(
+            instantiate_application(&identity(), &[Type::Integer, Type::Integer], &Type::Integer),
+            None
+        );
+    }
+
+    #[test]
+    fn substitute_shadowed_variable() {
+        let substitution = [("a".to_string(), Type::Integer)].into_iter().collect();
+        assert_eq!(
+            substitute(
+                &Type::Product(vec![Type::Variable("a".into()), identity()]),
+                &substitution
+            ),
+            Type::Product(vec![Type::Integer, identity()])
+        );
+    }
+}
@@ -6,11 +6,15 @@ use mir::{
 };
 use ty::Type;
 
+use crate::monomorphization::PolymorphicDefinition;
+
 #[derive(Debug, Default)]
 pub struct ScopeProto {
     pub super_id: Option<ScopeId>,
     // Only used in mir generation
     pub named_vars: HashMap<Type, VarId>,
+    // Only used in mir generation
+    pub polymorphic_definitions: Vec<PolymorphicDefinition>,
 }
 
 impl ScopeProto {
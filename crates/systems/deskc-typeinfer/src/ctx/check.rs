@@ -4,7 +4,12 @@ use hir::{
     meta::WithMeta,
 };
 
-use crate::{ctx::Ctx, ctx::Log, internal_type::Type, to_expr_type_error};
+use crate::{
+    ctx::Ctx,
+    ctx::Log,
+    internal_type::{effect_expr::EffectExpr, Type},
+    to_expr_type_error,
+};
 
 use super::{with_effects::WithEffects, with_type::WithType};
 
@@ -16,21 +21,60 @@ impl Ctx {
     ) -> Result<WithEffects<Ctx>, ExprTypeError> {
         let scope = self.begin_scope();
         let ctx = match (&expr.value, ty) {
+            (Expr::Hole, _) => {
+                self.record_hole(expr, ty);
+                self.clone()
+            }
             (Expr::Literal(Literal::Integer(_)), Type::Real) => self.clone(),
             (Expr::Literal(Literal::Real(_)), Type::Real) => self.clone(),
             (Expr::Literal(Literal::Rational(_, _)), Type::Real) => self.clone(),
             (Expr::Literal(Literal::String(_)), Type::String) => self.clone(),
             (
-                Expr::Function {
-                    parameter: _,
-                    body: _,
-                },
+                Expr::Function { parameter, body },
                 Type::Function {
-                    parameter: _ty_parameter,
-                    body: _ty_body,
+                    parameter: ty_parameter,
+                    body: ty_body,
                 },
             ) => {
-                todo!()
+                // Effects declared by the expected type are captured by the function.
+                let (ty_body, declared_effects) = match ty_body.as_ref() {
+                    Type::Effectful { ty, effects } => (ty.as_ref(), Some(effects)),
+                    ty => (ty, None),
+                };
+                let WithEffects(ctx, effects) = if let Type::Variable(id) = self
+                    .save_from_hir_type(parameter)
+                    .map_err(|error| to_expr_type_error(expr, error))?
+                {
+                    let typed = Log::TypedVariable(id, *ty_parameter.clone());
+                    let ctx = self.add(typed.clone());
+                    // Store the parameter as the expected type.
+                    ctx.save_from_hir_type(parameter)
+                        .map_err(|error| to_expr_type_error(expr, error))?;
+                    ctx.check(body, ty_body)?
+                        .recover_effects()
+                        .truncate_from(&typed)
+                } else {
+                    // The expected parameter must be accepted by the annotated one.
+                    let parameter = self
+                        .save_from_hir_type(parameter)
+                        .map_err(|error| to_expr_type_error(expr, error))?;
+                    let ctx = self
+                        .subtype(ty_parameter, &parameter)
+                        .map_err(|error| to_expr_type_error(expr, error))?
+                        .ctx;
+                    ctx.references.borrow_mut().push(parameter);
+                    let WithEffects(ctx, effects) = ctx.check(body, ty_body)?;
+                    ctx.references.borrow_mut().pop();
+                    WithEffects(ctx, effects)
+                };
+                // Undeclared effects escape to the enclosing expression.
+                match declared_effects {
+                    Some(declared_effects) => ctx.add_effects(&EffectExpr::Sub {
+                        minuend: Box::new(effects),
+                        subtrahend: Box::new(declared_effects.clone()),
+                    }),
+                    None => ctx.add_effects(&effects),
+                }
             }
             (
                 _,
@@ -0,0 +1,131 @@
+use errors::typeinfer::TypeError;
+use hir::{expr::MatchCase, meta::WithMeta};
+use itertools::Itertools;
+use ty::conclusion::TypeWarning;
+
+use crate::{
+    ctx::Ctx,
+    internal_type::Type,
+    partial_ord_max::PartialOrdMax,
+    similarity::{Similarities, SimilaritiesList, WithSimilaritiesList},
+};
+
+impl Ctx {
+    /// Checks that `cases` cover all variants of `of`, and warns on cases that never match.
+    ///
+    /// Like the cast to the sum of cases, each variant goes to a distinct case, in the mapping
+    /// with the most similarities.
+    pub(super) fn check_exhaustiveness(
+        &self,
+        of: &Type,
+        cases: &[(&WithMeta<MatchCase>, Type)],
+    ) -> Result<(), TypeError> {
+        let variants = match self.resolve_solved(of) {
+            Type::Sum(variants) => variants,
+            ty => vec![ty],
+        };
+        // Unknown variants may be any of the cases.
+        if variants
+            .iter()
+            .any(|variant| matches!(variant, Type::Existential(_)))
+        {
+            return Ok(());
+        }
+        let cases: Vec<_> = cases
+            .iter()
+            .map(|(case, ty)| (*case, self.resolve_solved(ty)))
+            .collect();
+        let similarities: Vec<Vec<_>> = variants
+            .iter()
+            .map(|variant| {
+                cases
+                    .iter()
+                    .map(|(_, ty)| self.similarities(variant, ty))
+                    .collect()
+            })
+            .collect();
+        let mappings: Vec<_> = (0..cases.len())
+            .permutations(variants.len())
+            .filter_map(|mapping| {
+                let list = mapping
+                    .iter()
+                    .enumerate()
+                    .map(|(variant, &case)| similarities[variant][case].clone())
+                    .collect::<Option<_>>()?;
+                Some(WithSimilaritiesList {
+                    ctx: mapping,
+                    list: SimilaritiesList(list),
+                })
+            })
+            .collect();
+        // Incomparable mappings are still exhaustive.
+        let Some(mapping) = mappings
+            .iter()
+            .cloned()
+            .partial_max()
+            .or_else(|| mappings.first().cloned())
+        else {
+            // Variants left out of a maximum matching have no case of their own.
+            let mut assigned = vec![None; cases.len()];
+            let missing = (0..variants.len())
+                .filter(|&variant| {
+                    !assign(
+                        variant,
+                        &similarities,
+                        &mut assigned,
+                        &mut vec![false; cases.len()],
+                    )
+                })
+                .map(|variant| self.gen_type_or_string(&variants[variant]))
+                .collect();
+            return Err(TypeError::NonExhaustiveMatch { missing });
+        };
+        let mut reached = vec![false; cases.len()];
+        for case in mapping.ctx {
+            reached[case] = true;
+        }
+        for (index, (case, ty)) in cases.iter().enumerate() {
+            let former = cases[..index].iter().find(|(_, former)| former == ty);
+            let warning = match former {
+                Some((former, _)) => TypeWarning::DuplicateCase {
+                    case_id: case.meta.id,
+                    former_id: former.meta.id,
+                },
+                None if !reached[index] => TypeWarning::UnreachableCase {
+                    case_id: case.meta.id,
+                },
+                None => continue,
+            };
+            self.warn(warning);
+        }
+        Ok(())
+    }
+
+    fn warn(&self, warning: TypeWarning) {
+        let mut warnings = self.warnings.borrow_mut();
+        // A match may be synthed more than once.
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+}
+
+/// Assigns a case to the variant, reassigning other variants to other cases if needed.
+fn assign(
+    variant: usize,
+    similarities: &[Vec<Option<Similarities>>],
+    assigned: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    for (case, pair) in similarities[variant].iter().enumerate() {
+        if pair.is_none() || visited[case] {
+            continue;
+        }
+        visited[case] = true;
+        if assigned[case].is_none_or(|other| assign(other, similarities, assigned, visited)) {
+            assigned[case] = Some(variant);
+            return true;
+        }
+    }
+    false
+}
@@ -1,46 +1,67 @@
+use errors::typeinfer::TypeError;
 use hir::meta::WithMeta;
 
 use crate::{
-    ctx::Ctx,
+    ctx::{Ctx, Log},
     internal_type::{effect_expr::EffectExpr, Effect, Type},
+    substitute::substitute,
 };
 
 impl Ctx {
-    pub(crate) fn gen_from_hir_type(&self, ty: &WithMeta<hir::ty::Type>) -> Type {
+    pub(crate) fn gen_from_hir_type(
+        &self,
+        ty: &WithMeta<hir::ty::Type>,
+    ) -> Result<Type, TypeError> {
         use hir::ty::Type::*;
-        match &ty.value {
+        let ty = match &ty.value {
             Real => Type::Real,
             Rational => Type::Rational,
             Integer => Type::Integer,
             String => Type::String,
             Effectful { ty, effects } => self.with_effects(
-                self.gen_from_hir_type(ty),
-                self.gen_from_hir_effect_expr(effects),
+                self.gen_from_hir_type(ty)?,
+                self.gen_from_hir_effect_expr(effects)?,
             ),
             Infer => Type::Infer(ty.meta.id.clone()),
-            Product(types) => {
-                Type::Product(types.iter().map(|t| self.gen_from_hir_type(t)).collect())
-            }
-            Sum(types) => Type::Sum(types.iter().map(|t| self.gen_from_hir_type(t)).collect()),
+            Product(types) => Type::Product(
+                types
+                    .iter()
+                    .map(|t| self.gen_from_hir_type(t))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Sum(types) => Type::Sum(
+                types
+                    .iter()
+                    .map(|t| self.gen_from_hir_type(t))
+                    .collect::<Result<_, _>>()?,
+            ),
             Function(function) => Type::Function {
-                parameter: Box::new(self.gen_from_hir_type(&function.parameter)),
-                body: Box::new(self.gen_from_hir_type(&function.body)),
+                parameter: Box::new(self.gen_from_hir_type(&function.parameter)?),
+                body: Box::new(self.gen_from_hir_type(&function.body)?),
             },
-            Vector(ty) => Type::Vector(Box::new(self.gen_from_hir_type(ty))),
+            Vector(ty) => Type::Vector(Box::new(self.gen_from_hir_type(ty)?)),
             Map { key, value } => Type::Map {
-                key: Box::new(self.gen_from_hir_type(key)),
-                value: Box::new(self.gen_from_hir_type(value)),
+                key: Box::new(self.gen_from_hir_type(key)?),
+                value: Box::new(self.gen_from_hir_type(value)?),
             },
             Variable(id) => Type::Variable(self.get_id_of(id.clone())),
             Brand { brand, item } => Type::Brand {
                 brand: brand.clone(),
-                item: Box::new(self.gen_from_hir_type(item)),
+                item: Box::new(self.gen_from_hir_type(item)?),
             },
             Label { label, item } => Type::Label {
                 label: label.clone(),
-                item: Box::new(self.gen_from_hir_type(item)),
+                item: Box::new(self.gen_from_hir_type(item)?),
             },
-            Let { .. } => todo!(),
+            Let {
+                variable,
+                definition,
+                body,
+            } => substitute(
+                &self.gen_from_hir_type(body)?,
+                &self.get_id_of(variable.clone()),
+                &self.gen_from_hir_type(definition)?,
+            ),
             Forall {
                 variable,
                 bound,
@@ -49,51 +70,75 @@ impl Ctx {
                 variable: self.get_id_of(variable.clone()),
                 bound: bound
                     .as_ref()
-                    .map(|bound| Box::new(self.gen_from_hir_type(bound))),
-                body: Box::new(self.gen_from_hir_type(body)),
+                    .map(|bound| Ok(Box::new(self.gen_from_hir_type(bound)?)))
+                    .transpose()?,
+                body: Box::new(self.gen_from_hir_type(body)?),
             },
-            Exists { .. } => todo!(),
-        }
+            // An existential type is opened with a fresh existential to be solved by usage.
+            // The bound is checked as the one of a universal type in application.
+            Exists {
+                variable,
+                bound,
+                body,
+            } => {
+                let a = self.fresh_existential();
+                self.logs.borrow_mut().push(Log::Existential(a));
+                if let Some(bound) = bound {
+                    let bound = self.gen_from_hir_type(bound)?;
+                    let ctx = self.subtype(&Type::Existential(a), &bound)?.ctx;
+                    *self.logs.borrow_mut() = ctx.logs.into_inner();
+                }
+                substitute(
+                    &self.gen_from_hir_type(body)?,
+                    &self.get_id_of(variable.clone()),
+                    &Type::Existential(a),
+                )
+            }
+        };
+        Ok(ty)
     }
 
     pub(crate) fn gen_from_hir_effect_expr(
         &self,
         effects: &WithMeta<hir::ty::EffectExpr>,
-    ) -> EffectExpr {
-        match &effects.value {
+    ) -> Result<EffectExpr, TypeError> {
+        let effects = match &effects.value {
             hir::ty::EffectExpr::Effects(effects) => EffectExpr::Effects(
                 effects
                     .iter()
-                    .map(|e| Effect {
-                        input: self.gen_from_hir_type(&e.value.input),
-                        output: self.gen_from_hir_type(&e.value.output),
+                    .map(|e| {
+                        Ok(Effect {
+                            input: self.gen_from_hir_type(&e.value.input)?,
+                            output: self.gen_from_hir_type(&e.value.output)?,
+                        })
                     })
-                    .collect(),
+                    .collect::<Result<_, _>>()?,
             ),
             hir::ty::EffectExpr::Add(effects) => EffectExpr::Add(
                 effects
                     .iter()
                     .map(|e| self.gen_from_hir_effect_expr(e))
-                    .collect(),
+                    .collect::<Result<_, _>>()?,
             ),
             hir::ty::EffectExpr::Sub {
                 minuend,
                 subtrahend,
             } => EffectExpr::Sub {
-                minuend: Box::new(self.gen_from_hir_effect_expr(minuend)),
-                subtrahend: Box::new(self.gen_from_hir_effect_expr(subtrahend)),
+                minuend: Box::new(self.gen_from_hir_effect_expr(minuend)?),
+                subtrahend: Box::new(self.gen_from_hir_effect_expr(subtrahend)?),
             },
             hir::ty::EffectExpr::Apply {
                 function,
                 arguments,
             } => EffectExpr::Apply {
-                function: Box::new(self.gen_from_hir_type(function)),
+                function: Box::new(self.gen_from_hir_type(function)?),
                 arguments: arguments
                     .iter()
                     .map(|a| self.gen_from_hir_type(a))
-                    .collect(),
+                    .collect::<Result<_, _>>()?,
             },
-        }
+        };
+        Ok(effects)
     }
 
     pub(crate) fn get_id_of(&self, ident: String) -> usize {
@@ -0,0 +1,145 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use hir::{expr::Expr, meta::WithMeta};
+use ids::NodeId;
+use ty::conclusion::HoleConclusion;
+
+use crate::{
+    ctx::{Ctx, Id, Log},
+    internal_type::{Type, TypeVisitor, TypeVisitorMut},
+    similarity::Similarities,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hole {
+    pub id: NodeId,
+    pub expected: Type,
+    /// Pairs of a reference and its type, innermost first.
+    pub references: Vec<(Type, Type)>,
+}
+
+impl Ctx {
+    pub(crate) fn record_hole(&self, expr: &WithMeta<Expr>, expected: &Type) {
+        let mut holes = self.holes.borrow_mut();
+        // A hole may be synthed again after checked.
+        if holes.iter().any(|hole| hole.id == expr.meta.id) {
+            return;
+        }
+        let mut references: Vec<_> = self
+            .references
+            .borrow()
+            .iter()
+            .map(|ty| (ty.clone(), ty.clone()))
+            .chain(self.logs.borrow().iter().filter_map(|log| match log {
+                Log::TypedVariable(id, ty) => Some((Type::Variable(*id), ty.clone())),
+                _ => None,
+            }))
+            .collect();
+        references.reverse();
+        holes.push(Hole {
+            id: expr.meta.id,
+            expected: self.substitute_from_ctx(expected),
+            references,
+        });
+    }
+
+    pub(crate) fn hole_conclusions(&self) -> HashMap<NodeId, HoleConclusion> {
+        self.holes
+            .borrow()
+            .iter()
+            .map(|hole| {
+                let expected = self.resolve_solved(&hole.expected);
+                let mut references = vec![];
+                let mut functions = vec![];
+                for (reference, ty) in &hole.references {
+                    let ty = self.resolve_solved(ty);
+                    if let Some(similarities) = self.similarities(&ty, &expected) {
+                        references.push((similarities, reference));
+                    }
+                    if let Type::Function { parameter: _, body } = &ty {
+                        if let Some(similarities) = self.similarities(body, &expected) {
+                            functions.push((similarities, reference));
+                        }
+                    }
+                }
+                let conclusion = HoleConclusion {
+                    expected: self.gen_type(&expected).ok(),
+                    references: self.rank(references),
+                    functions: self.rank(functions),
+                };
+                (hole.id, conclusion)
+            })
+            .collect()
+    }
+
+    /// Candidates whose types are not inferred are left out.
+    fn rank(&self, mut candidates: Vec<(Similarities, &Type)>) -> Vec<ty::Type> {
+        candidates.sort_by(|(a, _), (b, _)| b.cmp(a));
+        candidates
+            .into_iter()
+            .filter_map(|(_, ty)| self.gen_type(ty).ok())
+            .collect()
+    }
+
+    /// Tries subtyping in a disposable context not to solve existentials of the result.
+    pub(super) fn similarities(&self, sub: &Type, ty: &Type) -> Option<Similarities> {
+        let ctx = Ctx {
+            id_gen: self.id_gen.clone(),
+            ident_gen: self.ident_gen.clone(),
+            types: Rc::new(RefCell::new(self.types.borrow().clone())),
+            ..Default::default()
+        };
+        let mut free = FreeVariables::default();
+        free.visit(sub);
+        free.visit(ty);
+        ctx.logs.borrow_mut().extend(
+            free.variables
+                .into_iter()
+                .map(Log::Variable)
+                .chain(free.existentials.into_iter().map(Log::Existential)),
+        );
+        ctx.subtype(sub, ty)
+            .ok()
+            .map(|with_similarities| with_similarities.similarities)
+    }
+
+    pub(super) fn resolve_solved(&self, ty: &Type) -> Type {
+        let mut ty = self.substitute_from_ctx(ty);
+        ResolveSolved { ctx: self }.visit(&mut ty);
+        ty
+    }
+}
+
+struct ResolveSolved<'a> {
+    ctx: &'a Ctx,
+}
+
+impl<'a> TypeVisitorMut for ResolveSolved<'a> {
+    fn visit(&mut self, ty: &mut Type) {
+        let solved = match ty {
+            Type::Existential(id) => self.ctx.types.borrow().get(id).cloned(),
+            _ => None,
+        };
+        if let Some(solved) = solved {
+            *ty = solved;
+            self.visit(ty);
+        } else {
+            self.visit_inner(ty);
+        }
+    }
+}
+
+#[derive(Default)]
+struct FreeVariables {
+    variables: Vec<Id>,
+    existentials: Vec<Id>,
+}
+
+impl TypeVisitor for FreeVariables {
+    fn visit_variable(&mut self, id: &Id) {
+        self.variables.push(*id);
+    }
+    fn visit_existential(&mut self, id: &Id) {
+        self.existentials.push(*id);
+    }
+}
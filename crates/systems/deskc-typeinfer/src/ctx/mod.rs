@@ -1,6 +1,8 @@
 mod apply;
 mod check;
+mod exhaustiveness;
 mod from_hir_type;
+mod hole;
 mod instantiate_subtype;
 mod instantiate_supertype;
 mod into_type;
@@ -14,6 +16,7 @@ use std::{cell::RefCell, collections::HashMap, rc::Rc};
 use errors::typeinfer::TypeError;
 use hir::meta::WithMeta;
 use ids::NodeId;
+use ty::conclusion::TypeWarning;
 
 use crate::{
     cast_strategies::CastStrategy,
@@ -26,7 +29,7 @@ use crate::{
     well_formed::WellFormed,
 };
 
-use self::{with_effects::WithEffects, with_type::WithType};
+use self::{hole::Hole, with_effects::WithEffects, with_type::WithType};
 
 pub type Id = usize;
 
@@ -57,6 +60,10 @@ pub(crate) struct Ctx {
     pub inferred_types: RefCell<HashMap<NodeId, Type>>,
     pub variables_ids: RefCell<HashMap<String, usize>>,
     pub variables_idents: RefCell<HashMap<usize, String>>,
+    // a stack; types referenceable by `&` in current context
+    pub references: RefCell<Vec<Type>>,
+    pub holes: Rc<RefCell<Vec<Hole>>>,
+    pub warnings: Rc<RefCell<Vec<TypeWarning>>>,
 }
 
 impl Ctx {
@@ -76,6 +83,9 @@ impl Ctx {
             inferred_types: Default::default(),
             variables_idents: Default::default(),
             variables_ids: Default::default(),
+            references: Default::default(),
+            holes: self.holes.clone(),
+            warnings: self.warnings.clone(),
         }
     }
 
@@ -113,11 +123,11 @@ impl Ctx {
         ty
     }
 
-    fn save_from_hir_type(&self, hir_ty: &WithMeta<hir::ty::Type>) -> Type {
-        let ty = self.gen_from_hir_type(hir_ty);
+    fn save_from_hir_type(&self, hir_ty: &WithMeta<hir::ty::Type>) -> Result<Type, TypeError> {
+        let ty = self.gen_from_hir_type(hir_ty)?;
         let ty = self.substitute_from_ctx(&ty);
         self.store_type_and_effects(hir_ty.meta.id.clone(), ty.clone(), Default::default());
-        ty
+        Ok(ty)
     }
 
     fn begin_scope(&self) -> Id {
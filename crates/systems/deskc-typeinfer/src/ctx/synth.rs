@@ -25,6 +25,12 @@ impl Ctx {
             Expr::Literal(Literal::Rational(_, _)) => self.clone().with_type(Type::Rational),
             Expr::Literal(Literal::Real(_)) => self.clone().with_type(Type::Real),
             Expr::Literal(Literal::String(_)) => self.clone().with_type(Type::String),
+            Expr::Hole => {
+                let a = self.fresh_existential();
+                let ctx = self.add(Log::Existential(a));
+                ctx.record_hole(expr, &Type::Existential(a));
+                ctx.with_type(Type::Existential(a))
+            }
             Expr::Do { stmt, expr } => {
                 let WithType(ctx, _) = self.synth(stmt)?.recover_effects();
                 ctx.synth(expr)?.recover_effects()
@@ -34,13 +40,17 @@ impl Ctx {
                 expr: expression,
             } => {
                 let WithType(ctx, def_ty) = self.synth(definition)?.recover_effects();
-                let ctx = ctx.make_polymorphic(definition, def_ty);
+                let ctx = ctx.make_polymorphic(self, definition, def_ty.clone());
+                ctx.references.borrow_mut().push(def_ty);
                 let WithType(ctx, ty) = ctx.synth(expression)?.recover_effects();
+                ctx.references.borrow_mut().pop();
                 ctx.with_type(ty)
             }
             Expr::Perform { input, output } => {
                 let WithType(ctx, ty) = self.synth(input)?.recover_effects();
-                let output = ctx.save_from_hir_type(output);
+                let output = ctx
+                    .save_from_hir_type(output)
+                    .map_err(|error| to_expr_type_error(expr, error))?;
                 ctx.add(Log::Effect(EffectExpr::Effects(vec![Effect {
                     input: ty,
                     output: output.clone(),
@@ -49,7 +59,9 @@ impl Ctx {
             }
             Expr::Continue { input, output } => {
                 let WithType(ctx, input_ty) = self.synth(input)?.recover_effects();
-                let output = ctx.save_from_hir_type(output);
+                let output = ctx
+                    .save_from_hir_type(output)
+                    .map_err(|error| to_expr_type_error(expr, error))?;
                 let ctx = ctx
                     .subtype(
                         ctx.continue_output
@@ -92,7 +104,9 @@ impl Ctx {
                     .iter()
                     .map(|handler| {
                         let Handler { effect, handler } = &handler.value;
-                        let output = self.save_from_hir_type(&effect.output);
+                        let output = self
+                            .save_from_hir_type(&effect.output)
+                            .map_err(|error| to_expr_type_error(expr, error))?;
                         // push handler input type
                         ctx.continue_input.borrow_mut().push(output.clone());
 
@@ -105,7 +119,9 @@ impl Ctx {
 
                         // handled effect and continue effect
                         let handled_effect = Effect {
-                            input: ctx.save_from_hir_type(&effect.input),
+                            input: ctx
+                                .save_from_hir_type(&effect.input)
+                                .map_err(|error| to_expr_type_error(expr, error))?,
                             output,
                         };
                         let continue_effect = Effect {
@@ -155,27 +171,53 @@ impl Ctx {
             } => {
                 if arguments.is_empty() {
                     // Reference
-                    let fun = self.save_from_hir_type(function);
-                    if let Type::Variable(id) = fun {
-                        self.clone().with_type(
-                            self.get_typed_var(&id)
-                                .map_err(|error| to_expr_type_error(expr, error))?,
-                        )
-                    } else {
-                        self.clone().with_type(fun)
+                    let fun = self
+                        .save_from_hir_type(function)
+                        .map_err(|error| to_expr_type_error(expr, error))?;
+                    match fun {
+                        // A parameter typed with a universal variable is substituted to it.
+                        Type::Variable(id) if !self.has_variable(&id) => {
+                            let ty = self
+                                .get_typed_var(&id)
+                                .map_err(|error| to_expr_type_error(expr, error))?;
+                            self.store_type_and_effects(
+                                function.meta.id,
+                                ty.clone(),
+                                Default::default(),
+                            );
+                            self.clone().with_type(ty)
+                        }
+                        fun => self.clone().with_type(fun),
                     }
                 } else {
                     // Normal application
-                    let fun = match self.save_from_hir_type(function) {
-                        Type::Variable(var) => self
-                            .get_typed_var(&var)
-                            .map_err(|error| to_expr_type_error(expr, error))?,
+                    let fun = match self
+                        .save_from_hir_type(function)
+                        .map_err(|error| to_expr_type_error(expr, error))?
+                    {
+                        Type::Variable(var) => {
+                            let ty = self
+                                .get_typed_var(&var)
+                                .map_err(|error| to_expr_type_error(expr, error))?;
+                            // Later passes see the type of the parameter instead of its name.
+                            self.store_type_and_effects(
+                                function.meta.id,
+                                ty.clone(),
+                                Default::default(),
+                            );
+                            ty
+                        }
                         ty => ty,
                     };
                     let WithType(ctx, ty) = arguments.iter().try_fold(
                         WithType(self.clone(), fun.clone()),
                         |WithType(ctx, fun), arg| ctx.apply(&fun, arg),
                     )?;
+                    // Effects of the body are represented by the effects of application.
+                    let ty = match ty {
+                        Type::Effectful { ty, effects: _ } => *ty,
+                        ty => ty,
+                    };
 
                     ctx.add_effects(&EffectExpr::Apply {
                         function: Box::new(fun),
@@ -201,17 +243,26 @@ impl Ctx {
                 ctx.with_type(Type::Product(types))
             }
             Expr::Typed { ty, item: expr } => {
-                let ty = self.save_from_hir_type(ty);
+                let ty = self
+                    .save_from_hir_type(ty)
+                    .map_err(|error| to_expr_type_error(expr, error))?;
                 self.check(expr, &ty)?.recover_effects().with_type(ty)
             }
             Expr::Function { parameter, body } => {
-                if let Type::Variable(id) = self.save_from_hir_type(parameter) {
+                if let Type::Variable(id) = self
+                    .save_from_hir_type(parameter)
+                    .map_err(|error| to_expr_type_error(expr, error))?
+                {
                     let a = self.fresh_existential();
                     let b = self.fresh_existential();
-                    let WithEffects(ctx, effects) = self
+                    let ctx = self
                         .add(Log::Existential(a))
                         .add(Log::Existential(b))
-                        .add(Log::TypedVariable(id, Type::Existential(a)))
+                        .add(Log::TypedVariable(id, Type::Existential(a)));
+                    // Store the parameter as the inferred type.
+                    ctx.save_from_hir_type(parameter)
+                        .map_err(|error| to_expr_type_error(expr, error))?;
+                    let WithEffects(ctx, effects) = ctx
                         .check(body, &Type::Existential(b))?
                         .recover_effects()
                         .truncate_from(&Log::TypedVariable(id, Type::Existential(a)));
@@ -221,9 +272,15 @@ impl Ctx {
                         body: Box::new(self.with_effects(Type::Existential(b), effects)),
                     })
                 } else {
-                    let WithType(ctx, ty) = self.synth(body)?.recover_effects();
+                    let parameter = self
+                        .save_from_hir_type(parameter)
+                        .map_err(|error| to_expr_type_error(expr, error))?;
+                    let ctx = self.clone();
+                    ctx.references.borrow_mut().push(parameter.clone());
+                    let WithType(ctx, ty) = ctx.synth(body)?.recover_effects();
+                    ctx.references.borrow_mut().pop();
                     ctx.with_type(Type::Function {
-                        parameter: Box::new(self.save_from_hir_type(parameter)),
+                        parameter: Box::new(parameter),
                         body: Box::new(ty),
                     })
                 }
@@ -262,16 +319,33 @@ impl Ctx {
                     .map(|case| {
                         let MatchCase { ty, expr } = &case.value;
                         Ok((
-                            self.save_from_hir_type(ty),
+                            self.save_from_hir_type(ty)
+                                .map_err(|error| to_expr_type_error(expr, error))?,
                             self.synth(expr)?.recover_effects().1,
                         ))
                     })
                     .collect::<Result<Vec<_>, _>>()?
                     .into_iter()
                     .unzip();
+                let cases: Vec<_> = cases.iter().zip(ty.iter().cloned()).collect();
                 let ty = sum_all(self, ty);
                 let out = sum_all(self, out);
-                self.check(of, &ty)?.recover_effects().with_type(out)
+                // A hole expects the cases.
+                let ctx = if matches!(of.value, Expr::Hole) {
+                    self.check(of, &ty)?.recover_effects()
+                } else {
+                    // Synthesized instead of checked to know the variants of the matched value.
+                    let WithType(ctx, of_ty) = self.synth(of)?.recover_effects();
+                    ctx.check_exhaustiveness(&of_ty, &cases)
+                        .map_err(|error| to_expr_type_error(expr, error))?;
+                    ctx.subtype(
+                        &ctx.substitute_from_ctx(&of_ty),
+                        &ctx.substitute_from_ctx(&ty),
+                    )
+                    .map_err(|error| to_expr_type_error(of, error))?
+                    .ctx
+                };
+                ctx.with_type(out)
             }
             Expr::Label { label, item: body } => {
                 let WithType(ctx, ty) = self.synth(body)?.recover_effects();
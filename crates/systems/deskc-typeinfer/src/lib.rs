@@ -78,9 +78,12 @@ pub fn synth(next_id: usize, expr: &WithMeta<Expr>) -> Result<TypeConclusions, E
             Some((type_to_type, cast_strategy))
         })
         .collect();
+    let warnings = ctx.warnings.borrow().clone();
     Ok(TypeConclusions {
         types,
         cast_strategies,
+        holes: ctx.hole_conclusions(),
+        warnings,
     })
 }
 
@@ -97,11 +100,11 @@ mod tests {
 
     use ariadne::{Label, Report, ReportKind, Source};
     use errors::{textual_diagnostics::TextualDiagnostics, typeinfer::TypeOrString};
-    use hir::visitor::HirVisitor;
+    use hir::{expr::MatchCase, visitor::HirVisitor};
     use ids::{Entrypoint, FileId, NodeId};
     use pretty_assertions::assert_eq;
     use ty::{
-        conclusion::{CastStrategy, TypeToType},
+        conclusion::{CastStrategy, HoleConclusion, TypeToType, TypeWarning},
         Effect, EffectExpr, Function, Type,
     };
 
@@ -246,6 +249,277 @@ mod tests {
         assert_eq!(get_types(&expr, &conclusion), [(1, Type::Integer)]);
     }
 
+    #[test]
+    fn check_function() {
+        let expr = parse(
+            r#"
+                #1 <\ 'integer -> 'integer> \ x -> #2 &x
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            get_types(&expr, &conclusion),
+            [
+                (
+                    1,
+                    Type::Function(Box::new(Function {
+                        parameter: Type::Integer,
+                        body: Type::Integer,
+                    })),
+                ),
+                (2, Type::Integer),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_function_with_subtype_parameter() {
+        let expr = parse(
+            r#"
+                #1 <\ 'integer -> 'real> \ 'real -> &'real
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            get_types(&expr, &conclusion),
+            [(
+                1,
+                Type::Function(Box::new(Function {
+                    parameter: Type::Integer,
+                    body: Type::Real,
+                })),
+            )]
+        );
+    }
+
+    #[test]
+    fn check_function_rejects_parameter() {
+        let expr = parse(
+            r#"
+                <\ 'string -> 'integer> \ 'integer -> 1
+            "#,
+        );
+        assert!(matches!(
+            crate::synth(100, &expr),
+            Err(ExprTypeError {
+                meta: _,
+                error: TypeError::NotSubtype {
+                    sub: TypeOrString::Type(ty::Type::String),
+                    ty: TypeOrString::Type(ty::Type::Integer),
+                },
+            })
+        ));
+    }
+
+    #[test]
+    fn check_effectful_function() {
+        let expr = parse(
+            r#"
+                #1 <\ 'integer -> ! { 'string ~> 'integer } 'integer> \ x -> ! "a" ~> 'integer
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            get_types(&expr, &conclusion),
+            [(
+                1,
+                Type::Function(Box::new(Function {
+                    parameter: Type::Integer,
+                    body: Type::Effectful {
+                        ty: Box::new(Type::Integer),
+                        effects: EffectExpr::Effects(vec![Effect {
+                            input: Type::String,
+                            output: Type::Integer,
+                        }]),
+                    },
+                })),
+            )]
+        );
+    }
+
+    #[test]
+    fn check_polymorphic_function() {
+        let expr = parse(
+            r#"
+                #1 <'forall a, \ a -> a> \ x -> &x
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            get_types(&expr, &conclusion),
+            [(
+                1,
+                Type::ForAll {
+                    variable: "a".into(),
+                    bound: None,
+                    body: Box::new(Type::Function(Box::new(Function {
+                        parameter: Type::Variable("a".into()),
+                        body: Type::Variable("a".into()),
+                    }))),
+                },
+            )]
+        );
+    }
+
+    #[test]
+    fn type_let() {
+        let expr = parse(
+            r#"
+                #1 <$ a 'integer; \ a -> a> \ x -> &x
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            get_types(&expr, &conclusion),
+            [(
+                1,
+                Type::Function(Box::new(Function {
+                    parameter: Type::Integer,
+                    body: Type::Integer,
+                })),
+            )]
+        );
+    }
+
+    #[test]
+    fn type_exists() {
+        let expr = parse(
+            r#"
+                #1 <'exists a, \ 'integer -> a> \ 'integer -> "a"
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            get_types(&expr, &conclusion),
+            [(
+                1,
+                Type::Function(Box::new(Function {
+                    parameter: Type::Integer,
+                    body: Type::String,
+                })),
+            )]
+        );
+    }
+
+    #[test]
+    fn type_exists_bound() {
+        let expr = parse(
+            r#"
+                #1 <'exists a: 'integer, \ 'integer -> a> \ 'integer -> 1
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            get_types(&expr, &conclusion),
+            [(
+                1,
+                Type::Function(Box::new(Function {
+                    parameter: Type::Integer,
+                    body: Type::Integer,
+                })),
+            )]
+        );
+
+        let expr = parse(
+            r#"
+                <'exists a: 'integer, \ 'integer -> a> \ 'integer -> "a"
+            "#,
+        );
+        assert!(matches!(
+            synth(&expr).map(|_| ()).unwrap_err().error,
+            TypeError::NotSubtype { .. }
+        ));
+    }
+
+    fn single_hole(conclusion: &TypeConclusions) -> HoleConclusion {
+        assert_eq!(conclusion.holes.len(), 1);
+        conclusion.holes.values().next().unwrap().clone()
+    }
+
+    #[test]
+    fn hole_references() {
+        let expr = parse(
+            r#"
+                $ 1;
+                $ "a";
+                $ @sum 2;
+                <@sum 'integer> ?
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        let sum = Type::Label {
+            label: "sum".into(),
+            item: Box::new(Type::Integer),
+        };
+        assert_eq!(
+            single_hole(&conclusion),
+            HoleConclusion {
+                expected: Some(sum.clone()),
+                references: vec![sum, Type::Integer],
+                functions: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn hole_functions() {
+        let expr = parse(
+            r#"
+                $ \ 'string -> 1;
+                \ 'real -> <'integer> ?
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            single_hole(&conclusion),
+            HoleConclusion {
+                expected: Some(Type::Integer),
+                references: vec![],
+                functions: vec![Type::Function(Box::new(Function {
+                    parameter: Type::String,
+                    body: Type::Integer,
+                }))],
+            }
+        );
+    }
+
+    #[test]
+    fn hole_typed_variable() {
+        let expr = parse(
+            r#"
+                \ x -> ^ \ 'integer -> 'string (?)
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            single_hole(&conclusion),
+            HoleConclusion {
+                expected: Some(Type::Integer),
+                references: vec![Type::Variable("x".into())],
+                functions: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn hole_inferred_by_usage() {
+        let expr = parse(
+            r#"
+                $ "a";
+                <@a 'string> @a ?
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(
+            single_hole(&conclusion),
+            HoleConclusion {
+                expected: Some(Type::String),
+                references: vec![Type::String],
+                functions: vec![],
+            }
+        );
+    }
+
     #[test]
     fn typing_expressions() {
         let input = &r#"
@@ -271,7 +545,7 @@ mod tests {
                         })))
                     },
                 ),
-                (3, Type::Variable("b".into())),
+                (3, Type::Variable("a".into())),
                 (4, Type::Integer),
                 (5, Type::Integer),
                 (6, Type::String),
@@ -324,7 +598,7 @@ mod tests {
                     Type::Effectful {
                         ty: Box::new(Type::Integer),
                         effects: EffectExpr::Effects(vec![Effect {
-                            input: Type::Variable("b".into()),
+                            input: Type::Variable("a".into()),
                             output: Type::Integer,
                         }]),
                     },
@@ -334,7 +608,7 @@ mod tests {
                     Type::Effectful {
                         ty: Box::new(Type::String),
                         effects: EffectExpr::Effects(vec![Effect {
-                            input: Type::Variable("b".into()),
+                            input: Type::Variable("a".into()),
                             output: Type::Integer,
                         }]),
                     },
@@ -651,6 +925,136 @@ mod tests {
         );
     }
 
+    fn match_case_ids(expr: &WithMeta<Expr>) -> Vec<NodeId> {
+        #[derive(Default)]
+        struct CaseIds {
+            ids: Vec<NodeId>,
+        }
+        impl HirVisitor for CaseIds {
+            fn visit_match_case(&mut self, case: &WithMeta<MatchCase>) {
+                self.ids.push(case.meta.id);
+                self.visit_expr(&case.value.expr);
+            }
+        }
+        let mut case_ids = CaseIds::default();
+        case_ids.visit_expr(expr);
+        case_ids.ids
+    }
+
+    #[test]
+    fn exhaustive_match() {
+        let expr = parse(
+            r#"
+            \ +<'integer, @a 'integer> ->
+              'match &+<'integer, @a 'integer> '{
+                'integer => 1,
+                @a 'integer => 2,
+              }'
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(conclusion.warnings, vec![]);
+    }
+
+    #[test]
+    fn non_exhaustive_match() {
+        let expr = parse(
+            r#"
+            \ +<'integer, 'string, @a 'integer> ->
+              'match &+<'integer, 'string, @a 'integer> '{
+                'integer => 1,
+                'string => 2,
+              }'
+            "#,
+        );
+        assert_eq!(
+            synth(&expr).map_err(|error| error.error),
+            Err(TypeError::NonExhaustiveMatch {
+                missing: vec![TypeOrString::Type(Type::Label {
+                    label: "a".into(),
+                    item: Box::new(Type::Integer)
+                })]
+            })
+        );
+    }
+
+    #[test]
+    fn unreachable_and_duplicate_cases() {
+        let expr = parse(
+            r#"
+            \ +<'integer, 'string> ->
+              'match &+<'integer, 'string> '{
+                'integer => 1,
+                'string => 2,
+                @a 'integer => 3,
+                'string => 4,
+              }'
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        let ids = match_case_ids(&expr);
+        assert_eq!(
+            conclusion.warnings,
+            vec![
+                TypeWarning::UnreachableCase { case_id: ids[2] },
+                TypeWarning::DuplicateCase {
+                    case_id: ids[3],
+                    former_id: ids[1]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_matches_synthesize_scrutinee_once() {
+        // Synthesizing the scrutinee twice per match would take 2^20 steps.
+        let source = (0..20).fold("1".to_string(), |of, _| {
+            format!("'match {of} '{{ 'integer => 1 }}'")
+        });
+        let expr = parse(&source);
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(conclusion.get_type(&expr.meta.id), Some(&Type::Integer));
+    }
+
+    #[test]
+    fn match_case_covering_variants() {
+        let expr = parse(
+            r#"
+            \ +<'integer, 'string, 'real> ->
+              'match &+<'integer, 'string, 'real> '{
+                'real => 1,
+                +<'integer, 'string> => 2,
+                'integer => 3,
+                'string => 4,
+              }'
+            "#,
+        );
+        let conclusion = synth(&expr).unwrap();
+        // Each variant goes to the most similar case.
+        assert_eq!(
+            conclusion.warnings,
+            vec![TypeWarning::UnreachableCase {
+                case_id: match_case_ids(&expr)[1]
+            }]
+        );
+    }
+
+    #[test]
+    fn match_case_taken_by_more_similar_variant() {
+        let expr = parse(
+            r#"
+            \ +<'integer, 'string> ->
+              'match &+<'integer, 'string> '{
+                +<'integer, 'string> => 1,
+                'real => 2,
+              }'
+            "#,
+        );
+        // 'integer is more similar to the first case, which is the only case for 'string.
+        let conclusion = synth(&expr).unwrap();
+        assert_eq!(conclusion.warnings, vec![]);
+    }
+
     #[test]
     fn test_numbers() {
         init();
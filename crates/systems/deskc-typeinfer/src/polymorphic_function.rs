@@ -3,14 +3,24 @@ use std::collections::HashMap;
 use hir::{expr::Expr, meta::WithMeta};
 
 use crate::{
-    ctx::{Ctx, Id},
+    ctx::{Ctx, Id, Log},
     internal_type::{Type, TypeVisitorMut},
 };
 
 impl Ctx {
-    pub(crate) fn make_polymorphic(self, expr: &WithMeta<Expr>, ty: Type) -> Self {
+    /// Generalizes a let-bound function over existentials that are not in `outer`.
+    pub(crate) fn make_polymorphic(self, outer: &Ctx, expr: &WithMeta<Expr>, ty: Type) -> Self {
         if let Type::Function { .. } = ty {
-            let ty = self.to_polymorphic_function(ty);
+            let outer: Vec<_> = outer
+                .logs
+                .borrow()
+                .iter()
+                .filter_map(|log| match log {
+                    Log::Existential(id) => Some(*id),
+                    _ => None,
+                })
+                .collect();
+            let ty = self.to_polymorphic_function(ty, &outer);
             self.replace_type(expr, ty)
         } else {
             self
@@ -22,9 +32,10 @@ impl Ctx {
         self
     }
 
-    fn to_polymorphic_function(&self, mut ty: Type) -> Type {
+    fn to_polymorphic_function(&self, mut ty: Type, outer: &[Id]) -> Type {
         let mut visitor = Visitor {
             ctx: self,
+            outer,
             ids: Default::default(),
         };
         visitor.visit(&mut ty);
@@ -40,6 +51,7 @@ impl Ctx {
 
 struct Visitor<'a> {
     ctx: &'a Ctx,
+    outer: &'a [Id],
     ids: HashMap<Id, Id>,
 }
 
@@ -47,15 +59,30 @@ impl<'a> TypeVisitorMut for Visitor<'a> {
     fn visit(&mut self, ty: &mut Type) {
         match ty {
             Type::Existential(id) => {
-                if let Some(solved) = self.ctx.types.borrow().get(id) {
-                    *ty = solved.clone();
+                if let Some(variable) = self.ids.get(id) {
+                    *id = *variable;
+                    return;
+                }
+                let solved = self.ctx.types.borrow().get(id).cloned();
+                if let Some(solved) = solved {
+                    *ty = solved;
+                    self.visit(ty);
+                    return;
                 }
-                if let Type::Existential(id) = ty {
-                    *id = *self
-                        .ids
-                        .entry(*id)
-                        .or_insert_with(|| self.ctx.fresh_existential());
+                if self.outer.contains(id) {
+                    return;
                 }
+                let variable = *self.ids.entry(*id).or_insert_with(|| {
+                    let variable = self.ctx.fresh_existential();
+                    // Types of the definition's body refer to the same variable.
+                    self.ctx.store_solved_type_and_effects(
+                        *id,
+                        Type::Existential(variable),
+                        Default::default(),
+                    );
+                    variable
+                });
+                *id = variable;
             }
             ty => self.visit_inner(ty),
         }
@@ -69,10 +96,13 @@ mod tests {
     #[test]
     fn function() {
         assert_eq!(
-            Ctx::default().to_polymorphic_function(Type::Function {
-                parameter: Box::new(Type::Real),
-                body: Box::new(Type::Real)
-            }),
+            Ctx::default().to_polymorphic_function(
+                Type::Function {
+                    parameter: Box::new(Type::Real),
+                    body: Box::new(Type::Real)
+                },
+                &[]
+            ),
             Type::Function {
                 parameter: Box::new(Type::Real),
                 body: Box::new(Type::Real)
@@ -83,10 +113,13 @@ mod tests {
     #[test]
     fn function_existential() {
         assert_eq!(
-            Ctx::default().to_polymorphic_function(Type::Function {
-                parameter: Box::new(Type::Existential(1)),
-                body: Box::new(Type::Existential(2))
-            }),
+            Ctx::default().to_polymorphic_function(
+                Type::Function {
+                    parameter: Box::new(Type::Existential(1)),
+                    body: Box::new(Type::Existential(2))
+                },
+                &[]
+            ),
             Type::ForAll {
                 variable: 0,
                 bound: None,
@@ -108,10 +141,13 @@ mod tests {
         ctx.store_solved_type_and_effects(1, Type::Real, Default::default());
         ctx.store_solved_type_and_effects(2, Type::String, Default::default());
         assert_eq!(
-            ctx.to_polymorphic_function(Type::Function {
-                parameter: Box::new(Type::Existential(1)),
-                body: Box::new(Type::Existential(2))
-            }),
+            ctx.to_polymorphic_function(
+                Type::Function {
+                    parameter: Box::new(Type::Existential(1)),
+                    body: Box::new(Type::Existential(2))
+                },
+                &[]
+            ),
             Type::Function {
                 parameter: Box::new(Type::Real),
                 body: Box::new(Type::String)
@@ -125,10 +161,13 @@ mod tests {
         ctx.store_solved_type_and_effects(1, Type::Existential(3), Default::default());
         ctx.store_solved_type_and_effects(2, Type::Existential(4), Default::default());
         assert_eq!(
-            ctx.to_polymorphic_function(Type::Function {
-                parameter: Box::new(Type::Existential(1)),
-                body: Box::new(Type::Existential(2))
-            }),
+            ctx.to_polymorphic_function(
+                Type::Function {
+                    parameter: Box::new(Type::Existential(1)),
+                    body: Box::new(Type::Existential(2))
+                },
+                &[]
+            ),
             Type::ForAll {
                 variable: 0,
                 bound: None,
@@ -143,4 +182,28 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn outer_existential_is_not_generalized() {
+        let ctx = Ctx::default();
+        assert_eq!(
+            ctx.to_polymorphic_function(
+                Type::Function {
+                    parameter: Box::new(Type::Existential(1)),
+                    body: Box::new(Type::Existential(2))
+                },
+                &[2]
+            ),
+            Type::ForAll {
+                variable: 0,
+                bound: None,
+                body: Box::new(Type::Function {
+                    parameter: Box::new(Type::Existential(0)),
+                    body: Box::new(Type::Existential(2))
+                })
+            }
+        );
+        // The generalized existential is solved with the variable.
+        assert_eq!(ctx.types.borrow().get(&1), Some(&Type::Existential(0)));
+    }
 }
@@ -0,0 +1,38 @@
+use std::collections::HashSet;
+
+use ast::parser::Parser;
+use minimalist::MinimalistSyntaxParser;
+use miri::operators::OPERATORS;
+use ty::Type;
+
+/// Operators that the prelude declares as type aliases.
+const OPERATORS_IN_PRELUDE: [&str; 7] = ["add", "sub", "mul", "div", "rem", "eq", "cmp"];
+
+// The prelude must declare the operators with the types that miri implements them with.
+fn main() {
+    println!("cargo:rerun-if-changed=src/prelude.dk");
+    let prelude = std::fs::read_to_string("src/prelude.dk").unwrap();
+    let declarations = prelude
+        .trim_end()
+        .strip_suffix("*<>")
+        .expect("prelude should end with `*<>`");
+    let references: Vec<_> = OPERATORS_IN_PRELUDE
+        .iter()
+        .map(|operator| format!("&{operator}"))
+        .collect();
+    let source = format!("{declarations}*<{}>", references.join(", "));
+
+    let parsed = MinimalistSyntaxParser::parse(&source).expect("prelude should be parsed");
+    let (genhir, cards) = hirgen::gen_cards(&parsed.expr).expect("prelude should be lowered");
+    let conclusion =
+        typeinfer::synth(genhir.next_id(), &cards.file).expect("prelude should be typed");
+    let Some(Type::Product(declared)) = conclusion.get_type(&cards.file.meta.id) else {
+        panic!("operators in the prelude should be typed as a product");
+    };
+    let declared: HashSet<_> = declared.iter().cloned().collect();
+    let implemented: HashSet<_> = OPERATORS.keys().cloned().collect();
+    assert_eq!(
+        declared, implemented,
+        "operators in the prelude differ from the ones of miri"
+    );
+}
@@ -1,16 +1,19 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use ast::parser::{DummySpanStorage, ParseResult};
-use codebase::code::Code;
+use codebase::code::{Code, SyntaxKind};
 use hir::meta::WithMeta;
+use hirgen::TypeAliases;
 use ids::{Entrypoint, FileId};
 use mir::mir::Mir;
-use ty::conclusion::TypeConclusions;
+use ty::{conclusion::TypeConclusions, EffectExpr, Type};
 
 use crate::{
-    error::DeskcError,
+    effects::{unhandled_effects, AllowedEffects},
+    error::{DeskcError, UnfilledHole},
     hir_result::CardsResult,
     parse_source_code,
+    prelude::{PRELUDE_FILE_ID, PRELUDE_SOURCE},
     query_result::{QueryError, QueryResult},
 };
 
@@ -18,21 +21,62 @@ use crate::{
 pub trait DeskcQueries {
     #[salsa::input]
     fn code(&self, id: FileId) -> Code;
+    #[salsa::input]
+    fn allowed_effects(&self) -> AllowedEffects;
+    /// Files whose type aliases and brands are visible in each file.
+    #[salsa::input]
+    fn file_imports(&self) -> Arc<HashMap<FileId, Vec<FileId>>>;
+    fn imports(&self, id: FileId) -> Vec<FileId>;
     fn ast(&self, id: FileId) -> Result<ParseResult, QueryError>;
+    #[salsa::cycle(recover_cards)]
     fn cards(&self, id: FileId) -> QueryResult<CardsResult>;
+    /// Type aliases and brands that the file exports.
+    #[salsa::cycle(recover_type_aliases)]
+    fn type_aliases(&self, id: FileId) -> QueryResult<TypeAliases>;
     fn hir(&self, entrypoint: Entrypoint) -> QueryResult<WithMeta<hir::expr::Expr>>;
     fn typeinfer(&self, entrypoint: Entrypoint) -> QueryResult<TypeConclusions>;
+    /// Effects that the entrypoint performs without handling.
+    fn effects(&self, entrypoint: Entrypoint) -> QueryResult<EffectExpr>;
     fn mir(&self, entrypoint: Entrypoint) -> QueryResult<Mir>;
+    /// MIR with constants folded and unreachable blocks removed.
+    fn optimized_mir(&self, entrypoint: Entrypoint) -> QueryResult<Mir>;
 }
 
 #[salsa::database(CardStorage)]
-#[derive(Default)]
 pub struct DeskCompiler {
     storage: salsa::Storage<Self>,
 }
 
+impl Default for DeskCompiler {
+    fn default() -> Self {
+        let mut compiler = Self {
+            storage: Default::default(),
+        };
+        compiler.set_allowed_effects(AllowedEffects::default());
+        compiler.set_file_imports(Default::default());
+        compiler.set_code(
+            PRELUDE_FILE_ID,
+            Code::SourceCode {
+                syntax: SyntaxKind::Minimalist,
+                source: Arc::new(PRELUDE_SOURCE.into()),
+            },
+        );
+        compiler
+    }
+}
+
 impl salsa::Database for DeskCompiler {}
 
+fn imports(db: &dyn DeskcQueries, id: FileId) -> Vec<FileId> {
+    let imports = db.file_imports().get(&id).cloned().unwrap_or_default();
+    if id == PRELUDE_FILE_ID {
+        imports
+    } else {
+        // The prelude comes first so that files can shadow it.
+        [PRELUDE_FILE_ID].into_iter().chain(imports).collect()
+    }
+}
+
 fn ast(db: &dyn DeskcQueries, id: FileId) -> Result<ParseResult, QueryError> {
     let code = db.code(id);
     match code {
@@ -42,14 +86,40 @@ fn ast(db: &dyn DeskcQueries, id: FileId) -> Result<ParseResult, QueryError> {
 }
 
 fn cards(db: &dyn DeskcQueries, id: FileId) -> QueryResult<CardsResult> {
+    let mut imports = TypeAliases::default();
+    for import in db.imports(id) {
+        imports.extend(&*db.type_aliases(import)?);
+    }
     let parsed = db.ast(id)?;
-    let (genhir, hir) = hirgen::gen_cards(&parsed.expr)?;
+    let (genhir, hir) = hirgen::gen_cards_with_imports(&parsed.expr, &imports)?;
     Ok(Arc::new(CardsResult {
         cards: hir,
+        type_aliases: genhir.exports(),
         next_id: genhir.next_id(),
     }))
 }
 
+fn recover_cards(
+    _db: &dyn DeskcQueries,
+    _cycle: &[String],
+    id: &FileId,
+) -> QueryResult<CardsResult> {
+    Err(DeskcError::ImportCycle { file_id: *id }.into())
+}
+
+fn type_aliases(db: &dyn DeskcQueries, id: FileId) -> QueryResult<TypeAliases> {
+    // Dependents are not recomputed unless the aliases change.
+    Ok(Arc::new(db.cards(id)?.type_aliases.clone()))
+}
+
+fn recover_type_aliases(
+    _db: &dyn DeskcQueries,
+    _cycle: &[String],
+    id: &FileId,
+) -> QueryResult<TypeAliases> {
+    Err(DeskcError::ImportCycle { file_id: *id }.into())
+}
+
 fn hir(db: &dyn DeskcQueries, entrypoint: Entrypoint) -> QueryResult<WithMeta<hir::expr::Expr>> {
     let cards_result = db.cards(entrypoint.file_id().clone())?;
     let hir = match entrypoint {
@@ -72,9 +142,199 @@ fn typeinfer(db: &dyn DeskcQueries, entrypoint: Entrypoint) -> QueryResult<TypeC
     Ok(Arc::new(conclusion))
 }
 
-fn mir(db: &dyn DeskcQueries, entrypoint: Entrypoint) -> QueryResult<Mir> {
+fn effects(db: &dyn DeskcQueries, entrypoint: Entrypoint) -> QueryResult<EffectExpr> {
     let hir = db.hir(entrypoint.clone())?;
     let conclusion = db.typeinfer(entrypoint)?;
+    let effects = match conclusion.get_type(&hir.meta.id) {
+        Some(Type::Effectful { ty: _, effects }) => effects.clone(),
+        _ => EffectExpr::Effects(vec![]),
+    };
+    Ok(Arc::new(effects))
+}
+
+fn mir(db: &dyn DeskcQueries, entrypoint: Entrypoint) -> QueryResult<Mir> {
+    let hir = db.hir(entrypoint)?;
+    let conclusion = db.typeinfer(entrypoint)?;
+    let parsed = db.ast(*entrypoint.file_id())?;
+    let span_storage = parsed.span_storage.as_ref().as_ref();
+    // A program with holes is not run; the conclusions tell how to fill them.
+    let mut holes: Vec<_> = conclusion
+        .holes
+        .iter()
+        .map(|(id, conclusion)| UnfilledHole {
+            id: *id,
+            span: span_storage
+                .calculate_span(id)
+                .or_else(|| span_storage.calculate_span(&hir.meta.id)),
+            conclusion: conclusion.clone(),
+        })
+        .collect();
+    if !holes.is_empty() {
+        holes.sort_by_key(|hole| hole.span.as_ref().map(|span| (span.start, span.end)));
+        return Err(DeskcError::UnfilledHoles { holes }.into());
+    }
+    let effects = unhandled_effects(&hir, &conclusion, &db.allowed_effects(), span_storage);
+    if !effects.is_empty() {
+        return Err(DeskcError::UnhandledEffects { effects }.into());
+    }
     let mir = mirgen::gen_mir(&hir, &conclusion)?;
     Ok(Arc::new(mir))
 }
+
+fn optimized_mir(db: &dyn DeskcQueries, entrypoint: Entrypoint) -> QueryResult<Mir> {
+    let mir = db.mir(entrypoint)?;
+    let conclusion = db.typeinfer(entrypoint)?;
+    Ok(Arc::new(mir_optimizer::optimize(&mir, conclusion)))
+}
+
+#[cfg(test)]
+mod tests {
+    use mir::stmt::{Const, Stmt, StmtBind};
+
+    use crate::prelude::PRELUDE_VERSION;
+
+    use super::*;
+
+    fn set_source(compiler: &mut DeskCompiler, file_id: &FileId, source: &str) {
+        compiler.set_code(
+            *file_id,
+            Code::SourceCode {
+                syntax: SyntaxKind::Minimalist,
+                source: Arc::new(source.into()),
+            },
+        );
+    }
+
+    fn output(compiler: &DeskCompiler, file_id: &FileId) -> Type {
+        compiler.mir(Entrypoint::File(*file_id)).unwrap().cfgs[0]
+            .output
+            .clone()
+    }
+
+    #[test]
+    fn imports_type_aliases() {
+        let mut compiler = DeskCompiler::default();
+        let prelude = FileId::new();
+        let file = FileId::new();
+        set_source(
+            &mut compiler,
+            &prelude,
+            "'type add \\ *<@l 'integer, @r 'integer> -> @sum 'integer; *<>",
+        );
+        set_source(&mut compiler, &file, "^add *<@l 1, @r 2>");
+        compiler.set_file_imports(Arc::new([(file, vec![prelude])].into()));
+        assert_eq!(
+            output(&compiler, &file),
+            Type::Label {
+                label: "sum".into(),
+                item: Box::new(Type::Integer),
+            }
+        );
+
+        let other = FileId::new();
+        set_source(&mut compiler, &other, "1");
+        let other_cards = compiler.cards(other).unwrap();
+
+        // The importer sees edits of the prelude.
+        set_source(
+            &mut compiler,
+            &prelude,
+            "'type add \\ *<@l 'integer, @r 'integer> -> @total 'integer; *<>",
+        );
+        assert_eq!(
+            output(&compiler, &file),
+            Type::Label {
+                label: "total".into(),
+                item: Box::new(Type::Integer),
+            }
+        );
+        // Files that do not import the prelude are not recomputed.
+        assert!(Arc::ptr_eq(&other_cards, &compiler.cards(other).unwrap()));
+    }
+
+    #[test]
+    fn optimized_mir_folds_constants() {
+        let mut compiler = DeskCompiler::default();
+        let file = FileId::new();
+        set_source(&mut compiler, &file, "^add *<@l 1, @r 2>");
+        let mir = compiler.optimized_mir(Entrypoint::File(file)).unwrap();
+        let cfg = &mir.cfgs[mir.entrypoint.0];
+        assert_eq!(cfg.blocks.len(), 1);
+        assert!(matches!(
+            cfg.blocks[0].stmts.as_slice(),
+            [StmtBind {
+                var: _,
+                stmt: Stmt::Const(Const::Int(3)),
+            }]
+        ));
+    }
+
+    #[test]
+    fn prelude_is_imported_by_default() {
+        let mut compiler = DeskCompiler::default();
+        let file = FileId::new();
+        set_source(&mut compiler, &file, "^add *<@l 1, @r 2>");
+        assert_eq!(
+            output(&compiler, &file),
+            Type::Label {
+                label: "sum".into(),
+                item: Box::new(Type::Integer),
+            }
+        );
+    }
+
+    #[test]
+    fn prelude_file_is_of_its_version() {
+        assert!(PRELUDE_SOURCE.starts_with(&format!("~~ Desk-lang prelude v{PRELUDE_VERSION}\n")));
+        assert_eq!(PRELUDE_FILE_ID.0.as_u128() as u32, PRELUDE_VERSION);
+    }
+
+    #[test]
+    fn type_aliases_are_not_visible_without_import() {
+        let mut compiler = DeskCompiler::default();
+        let file = FileId::new();
+        set_source(&mut compiler, &file, "'type a 'integer; 1");
+        assert!(compiler
+            .type_aliases(file)
+            .unwrap()
+            .aliases
+            .contains_key("a"));
+        let other = FileId::new();
+        set_source(&mut compiler, &other, "&a");
+        let hir::expr::Expr::Apply { function, .. } =
+            &compiler.hir(Entrypoint::File(other)).unwrap().value
+        else {
+            panic!("expected apply");
+        };
+        assert_eq!(function.value, hir::ty::Type::Variable("a".into()));
+    }
+
+    #[test]
+    fn import_cycle_is_error() {
+        let mut compiler = DeskCompiler::default();
+        let a = FileId::new();
+        let b = FileId::new();
+        set_source(&mut compiler, &a, "1");
+        set_source(&mut compiler, &b, "2");
+        compiler.set_file_imports(Arc::new([(a, vec![b]), (b, vec![a])].into()));
+        let error = compiler.cards(a).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DeskcError>(),
+            Some(DeskcError::ImportCycle { .. })
+        ));
+    }
+
+    #[test]
+    fn unfilled_holes_are_errors() {
+        let mut compiler = DeskCompiler::default();
+        let file = FileId::new();
+        set_source(&mut compiler, &file, "<'integer> ?");
+        let error = compiler.mir(Entrypoint::File(file)).unwrap_err();
+        let Some(DeskcError::UnfilledHoles { holes }) = error.downcast_ref::<DeskcError>() else {
+            panic!("expected unfilled holes: {error:?}");
+        };
+        assert_eq!(holes.len(), 1);
+        assert!(holes[0].span.is_some());
+        assert_eq!(holes[0].conclusion.expected, Some(Type::Integer));
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::BTreeSet;
+
+use ast::{meta::Span, parser::SpanStorage};
+use hir::{expr::Expr, meta::WithMeta, visitor::HirVisitor};
+use ids::NodeId;
+use ty::{conclusion::TypeConclusions, Effect, EffectExpr, Type};
+
+/// Effects an entrypoint may leave unhandled.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub enum AllowedEffects {
+    #[default]
+    All,
+    /// e.g. effects that effect handlers of a d-process can service.
+    Only(BTreeSet<Effect>),
+}
+
+impl AllowedEffects {
+    pub fn allows(&self, effect: &ResidualEffect) -> bool {
+        match (self, effect) {
+            (AllowedEffects::All, _) => true,
+            (AllowedEffects::Only(effects), ResidualEffect::Effect(effect)) => {
+                effects.contains(effect)
+            }
+            // Nothing tells that the application performs only allowed effects.
+            (AllowedEffects::Only(_), ResidualEffect::Unresolved(_)) => false,
+        }
+    }
+}
+
+impl FromIterator<Effect> for AllowedEffects {
+    fn from_iter<T: IntoIterator<Item = Effect>>(iter: T) -> Self {
+        AllowedEffects::Only(iter.into_iter().collect())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResidualEffect {
+    Effect(Effect),
+    /// Effects of an application of a polymorphic function that are not resolved.
+    Unresolved(EffectExpr),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnhandledEffect {
+    pub effect: ResidualEffect,
+    /// The expression that performs the effect.
+    pub id: NodeId,
+    /// The span of the expression or its nearest ancestor.
+    pub span: Option<Span>,
+}
+
+/// Effects of a type that may be performed.
+pub fn residual_effects(ty: &Type) -> BTreeSet<ResidualEffect> {
+    match ty {
+        Type::Effectful { ty: _, effects } => effects_of(effects),
+        _ => BTreeSet::new(),
+    }
+}
+
+fn effects_of(effects: &EffectExpr) -> BTreeSet<ResidualEffect> {
+    match effects {
+        EffectExpr::Effects(effects) => effects
+            .iter()
+            .cloned()
+            .map(ResidualEffect::Effect)
+            .collect(),
+        EffectExpr::Add(effects) => effects.iter().flat_map(effects_of).collect(),
+        // An unresolved subtrahend handles nothing for sure.
+        EffectExpr::Sub {
+            minuend,
+            subtrahend,
+        } => &effects_of(minuend) - &effects_of(subtrahend),
+        EffectExpr::Apply { .. } => [ResidualEffect::Unresolved(effects.clone())].into(),
+    }
+}
+
+/// Finds effects of `hir` that are not allowed, with the innermost expressions that perform them.
+pub fn unhandled_effects(
+    hir: &WithMeta<Expr>,
+    conclusions: &TypeConclusions,
+    allowed: &AllowedEffects,
+    span_storage: &dyn SpanStorage,
+) -> Vec<UnhandledEffect> {
+    let Some(ty) = conclusions.get_type(&hir.meta.id) else {
+        return vec![];
+    };
+    residual_effects(ty)
+        .into_iter()
+        .filter(|effect| !allowed.allows(effect))
+        .flat_map(|effect| {
+            let mut origins = Origins {
+                conclusions,
+                span_storage,
+                effect: &effect,
+                path: vec![],
+                origins: vec![],
+            };
+            origins.visit_expr(hir);
+            origins.origins
+        })
+        .collect()
+}
+
+struct Origins<'a> {
+    conclusions: &'a TypeConclusions,
+    span_storage: &'a dyn SpanStorage,
+    effect: &'a ResidualEffect,
+    /// Ancestors of the current expression.
+    path: Vec<NodeId>,
+    origins: Vec<UnhandledEffect>,
+}
+
+impl HirVisitor for Origins<'_> {
+    fn visit_expr(&mut self, expr: &WithMeta<Expr>) {
+        let performs = self
+            .conclusions
+            .get_type(&expr.meta.id)
+            .map(|ty| residual_effects(ty).contains(self.effect))
+            .unwrap_or(false);
+        if !performs {
+            return;
+        }
+        let found = self.origins.len();
+        self.path.push(expr.meta.id);
+        self.super_visit_expr(expr);
+        // No sub-expression performs the effect.
+        if self.origins.len() == found {
+            // Falls back to the nearest ancestor if the syntax does not store the span.
+            let span = self
+                .path
+                .iter()
+                .rev()
+                .find_map(|id| self.span_storage.calculate_span(id));
+            self.origins.push(UnhandledEffect {
+                effect: self.effect.clone(),
+                id: expr.meta.id,
+                span,
+            });
+        }
+        self.path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ast::parser::DummySpanStorage;
+    use codebase::code::{Code, SyntaxKind};
+    use ids::{Entrypoint, FileId};
+
+    use crate::{
+        card::{DeskCompiler, DeskcQueries},
+        error::DeskcError,
+    };
+
+    use super::*;
+
+    fn compiler(source: &str) -> (DeskCompiler, Entrypoint) {
+        let mut compiler = DeskCompiler::default();
+        let file_id = FileId::new();
+        compiler.set_code(
+            file_id,
+            Code::SourceCode {
+                syntax: SyntaxKind::Minimalist,
+                source: Arc::new(source.into()),
+            },
+        );
+        (compiler, Entrypoint::File(file_id))
+    }
+
+    fn effect(input: Type, output: Type) -> Effect {
+        Effect { input, output }
+    }
+
+    #[test]
+    fn residual_effects_of_entrypoint() {
+        let (compiler, entrypoint) = compiler(
+            r#"
+            'handle *<! 1 ~> 'string, ! "a" ~> 'integer> '{
+              'integer ~> 'string => ! "b" ~> 'string
+            }'
+            "#,
+        );
+        assert_eq!(
+            residual_effects(&Type::Effectful {
+                ty: Box::new(Type::Integer),
+                effects: compiler.effects(entrypoint).unwrap().as_ref().clone(),
+            }),
+            [
+                effect(Type::String, Type::Integer),
+                effect(Type::String, Type::String)
+            ]
+            .into_iter()
+            .map(ResidualEffect::Effect)
+            .collect()
+        );
+    }
+
+    #[test]
+    fn pure_entrypoint_has_no_effects() {
+        let (compiler, entrypoint) = compiler("1");
+        assert_eq!(
+            compiler.effects(entrypoint).unwrap().as_ref(),
+            &EffectExpr::Effects(vec![])
+        );
+    }
+
+    #[test]
+    fn all_effects_are_allowed_by_default() {
+        let (compiler, entrypoint) = compiler(r#"! "a" ~> 'integer"#);
+        assert!(compiler.mir(entrypoint).is_ok());
+    }
+
+    #[test]
+    fn not_allowed_effects_are_errors() {
+        let (mut compiler, entrypoint) = compiler(r#"*<! "a" ~> 'integer, ! 1 ~> 'string>"#);
+        compiler.set_allowed_effects([effect(Type::Integer, Type::String)].into_iter().collect());
+        let error = compiler.mir(entrypoint).unwrap_err();
+        let Some(DeskcError::UnhandledEffects { effects }) = error.downcast_ref::<DeskcError>()
+        else {
+            panic!("unexpected error {error:?}");
+        };
+        assert_eq!(effects.len(), 1);
+        assert_eq!(
+            effects[0].effect,
+            ResidualEffect::Effect(effect(Type::String, Type::Integer))
+        );
+        assert!(effects[0].span.is_some());
+    }
+
+    #[test]
+    fn handled_effects_are_not_reported() {
+        let (mut compiler, entrypoint) = compiler(
+            r#"
+            'handle ! "a" ~> 'integer '{
+              'string ~> 'integer => ! 1 ~> 'integer
+            }'
+            "#,
+        );
+        compiler.set_allowed_effects(AllowedEffects::Only(Default::default()));
+        assert!(compiler.mir(entrypoint).is_ok());
+    }
+
+    #[test]
+    fn unresolved_applications_are_not_allowed() {
+        let apply = EffectExpr::Apply {
+            function: Box::new(Type::Variable("f".into())),
+            arguments: vec![Type::Integer],
+        };
+        let hir = WithMeta {
+            meta: Default::default(),
+            value: Expr::Hole,
+        };
+        let conclusions = TypeConclusions {
+            types: [(
+                hir.meta.id,
+                Type::Effectful {
+                    ty: Box::new(Type::String),
+                    effects: EffectExpr::Add(vec![
+                        EffectExpr::Effects(vec![effect(Type::Integer, Type::String)]),
+                        apply.clone(),
+                    ]),
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let allowed = [effect(Type::Integer, Type::String)].into_iter().collect();
+        let effects = unhandled_effects(&hir, &conclusions, &allowed, &DummySpanStorage);
+        assert_eq!(effects.len(), 1);
+        assert_eq!(effects[0].effect, ResidualEffect::Unresolved(apply));
+        assert!(
+            unhandled_effects(&hir, &conclusions, &AllowedEffects::All, &DummySpanStorage)
+                .is_empty()
+        );
+    }
+}
@@ -1,8 +1,26 @@
-use ids::{CardId, FileId};
+use crate::effects::UnhandledEffect;
+use ast::meta::Span;
+use ids::{CardId, FileId, NodeId};
 use thiserror::Error;
+use ty::conclusion::HoleConclusion;
 
 #[derive(Error, Debug)]
 pub enum DeskcError {
     #[error("card not found: {card_id:?} in {file_id:?}")]
     CardNotFound { card_id: CardId, file_id: FileId },
+    #[error("unhandled effects: {effects:?}")]
+    UnhandledEffects { effects: Vec<UnhandledEffect> },
+    #[error("import cycle through {file_id:?}")]
+    ImportCycle { file_id: FileId },
+    #[error("holes must be filled: {holes:?}")]
+    UnfilledHoles { holes: Vec<UnfilledHole> },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnfilledHole {
+    pub id: NodeId,
+    /// The span of the hole, or of the entrypoint if the syntax does not store it.
+    pub span: Option<Span>,
+    /// The expected type and candidates to fill the hole.
+    pub conclusion: HoleConclusion,
 }
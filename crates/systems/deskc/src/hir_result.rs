@@ -1,7 +1,10 @@
 use hir::Cards;
+use hirgen::TypeAliases;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CardsResult {
     pub cards: Cards,
+    /// Type aliases and brands visible at the end of the file.
+    pub type_aliases: TypeAliases,
     pub next_id: usize,
 }
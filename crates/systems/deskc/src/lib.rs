@@ -1,7 +1,9 @@
 pub mod card;
+pub mod effects;
 pub mod error;
 pub mod hir_result;
 pub mod parse_source_code;
+pub mod prelude;
 pub mod query_result;
 
 pub use parse_source_code::*;
@@ -0,0 +1,13 @@
+use ids::FileId;
+use uuid::Uuid;
+
+/// Bumped on every incompatible change of the prelude.
+pub const PRELUDE_VERSION: u32 = 1;
+
+/// Type aliases of built-in operators, common effects and sums.
+pub const PRELUDE_SOURCE: &str = include_str!("prelude.dk");
+
+/// Each version is a distinct file, so nothing compiled against one version is reused for another.
+pub const PRELUDE_FILE_ID: FileId = FileId(Uuid::from_u128(
+    0x0000_0000_0000_4000_8000_0000_0000_0000 | PRELUDE_VERSION as u128,
+));
@@ -168,7 +168,7 @@ impl EvalCfg {
                 Stmt::Link(_link) => {
                     todo!()
                 }
-                Stmt::MatchResult(_) => todo!(),
+                Stmt::MatchResult(var) => self.load_value(var).clone(),
                 Stmt::Cast(var) => {
                     let value = self.load_value(var);
                     let ty = self.get_var_ty(var);
@@ -206,6 +206,7 @@ impl EvalCfg {
 mod tests {
     use mir::{
         block::BasicBlock,
+        mir::Mir,
         scope::{Scope, ScopeId},
         stmt::Const,
         var::{Var, Vars},
@@ -249,4 +250,53 @@ mod tests {
         assert_eq!(eval.eval_next(), InnerOutput::Running);
         assert_eq!(eval.eval_next(), InnerOutput::Return(Value::Int(1)));
     }
+
+    #[test]
+    fn hand_written_mir() {
+        let mir: Mir = r#"
+            entrypoint cfg0
+
+            cfg0 {
+              output 'integer
+              scope0
+              bb0 {
+                %0: 'integer = const 1
+                goto bb2
+              }
+              bb1 {
+                %0: 'integer = const 2
+                goto bb3
+              }
+              bb2 {
+                goto bb3
+              }
+              bb3 {
+                %1: 'integer = match_result %0
+                return %1
+              }
+            }
+        "#
+        .parse()
+        .unwrap();
+
+        let mut eval = EvalCfg {
+            cfg: mir.cfgs[0].clone(),
+            type_conclusion: Default::default(),
+            pc_block: BlockId(0),
+            pc_stmt_idx: 0,
+            registers: HashMap::new(),
+            parameters: HashMap::new(),
+            captured: HashMap::new(),
+            return_register: None,
+            handlers: HashMap::new(),
+        };
+
+        let output = loop {
+            match eval.eval_next() {
+                InnerOutput::Running => continue,
+                output => break output,
+            }
+        };
+        assert_eq!(output, InnerOutput::Return(Value::Int(1)));
+    }
 }
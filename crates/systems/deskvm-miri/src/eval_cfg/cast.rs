@@ -10,7 +10,7 @@ use crate::value::Value;
 use super::EvalCfg;
 
 impl EvalCfg {
-    pub(crate) fn cast(
+    pub fn cast(
         conclusion: Arc<TypeConclusions>,
         value: &Value,
         ty: &Type,
@@ -31,6 +31,21 @@ pub fn div(value: &Value) -> OperatorOutput {
     }
 }
 
+/// Integer operators returning `None` on overflow instead of panicking.
+pub fn checked(value: &Value, op: fn(i64, i64) -> Option<i64>) -> Option<OperatorOutput> {
+    let (l, r) = int_lr(value);
+    Some(OperatorOutput::Return(Value::Int(op(l, r)?)))
+}
+
+pub fn checked_div(value: &Value) -> Option<OperatorOutput> {
+    let (_, r) = int_lr(value);
+    if r == 0 {
+        Some(div(value))
+    } else {
+        checked(value, i64::checked_div)
+    }
+}
+
 pub fn int_lr(value: &Value) -> (i64, i64) {
     let (l, r) = lr(value);
     let Value::Int(l) = l else { panic!("left operand of integer operator not an integer")};
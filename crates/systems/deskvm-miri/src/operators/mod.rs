@@ -70,4 +70,16 @@ impl Operator {
             Operator::RealCmp => cmp::real_cmp(value),
         }
     }
+
+    /// Like `call`, but `None` on integer overflow and for operators not implemented yet.
+    pub fn checked_call(&self, value: &Value) -> Option<OperatorOutput> {
+        match self {
+            Operator::IntAdd => int::checked(value, i64::checked_add),
+            Operator::IntSub => int::checked(value, i64::checked_sub),
+            Operator::IntMul => int::checked(value, i64::checked_mul),
+            Operator::IntDiv => int::checked_div(value),
+            Operator::Rem => None,
+            Operator::RealEq | Operator::RealCmp => Some(self.call(value)),
+        }
+    }
 }
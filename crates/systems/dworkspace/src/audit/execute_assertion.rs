@@ -4,12 +4,14 @@ use components::{
     user::UserId,
 };
 use deskc_ids::NodeId;
+use deskc_ty::Type;
+use dson::Dson;
 
 use crate::{references::ReferencesQueries, Workspace};
 
 use super::assertion::Assertion;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum AssertionError {
     SpaceDenied(SpaceOperation),
     NodeDenied {
@@ -46,6 +48,10 @@ pub enum AssertionError {
     MovingItself {
         node_id: NodeId,
     },
+    AttributeTypeMismatch {
+        key: Type,
+        value: Dson,
+    },
 }
 
 impl Workspace {
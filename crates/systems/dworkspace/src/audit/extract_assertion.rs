@@ -5,6 +5,8 @@ use components::{
     rules::{NodeOperation, SpaceOperation},
 };
 
+use deskc_ty::DsonTypeDeduction;
+
 use crate::audit::execute_assertion::AssertionError;
 
 use super::assertion::Assertion;
@@ -161,6 +163,12 @@ pub fn extract_assertion(event: &Event) -> Assertion {
         },
         EventPayload::PatchAttribute { node_id, ref patch } => {
             let operation = match patch {
+                AttributePatch::Update { key, value } if !value.conforms_to(key) => {
+                    return Assertion::Contradiction(AssertionError::AttributeTypeMismatch {
+                        key: key.clone(),
+                        value: value.clone(),
+                    })
+                }
                 AttributePatch::Update { key, value: _ } => UpdateAttribute(key.clone()),
                 AttributePatch::Remove { key } => RemoveAttribute(key.clone()),
             };
@@ -472,7 +480,6 @@ mod tests {
         let event = EventPayload::PatchContent {
             node_id: node_id.clone(),
             patch: ContentPatch::UpdateApply {
-                ty: Type::Real,
                 link_name: LinkName::None,
             },
         };
@@ -882,6 +889,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn extract_assertion_for_update_attribute_with_mismatched_value() {
+        let event = EventPayload::PatchAttribute {
+            node_id: NodeId::new(),
+            patch: AttributePatch::Update {
+                key: Type::Integer,
+                value: "a".into(),
+            },
+        };
+        assert_eq!(
+            extract_assertion(&e(event)),
+            Assertion::Contradiction(AssertionError::AttributeTypeMismatch {
+                key: Type::Integer,
+                value: "a".into(),
+            })
+        );
+    }
+
     #[test]
     fn extract_assertion_for_remove_attribute() {
         let node_id = NodeId::new();
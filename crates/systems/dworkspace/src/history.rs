@@ -2,58 +2,210 @@ use std::collections::VecDeque;
 
 use components::{
     event::{Event, EventPayload},
+    patch::{AttributePatch, ContentPatch, OperandPatch, OperandPosition},
     projection::Projection,
 };
 
 use crate::state::State;
 
 pub struct History {
-    size: usize,
-    undo_stack: VecDeque<EventPayload>,
+    /// `None` for no limit.
+    size: Option<usize>,
+    /// Each entry is the inverse of one event, in the order to be applied.
+    undo_stack: VecDeque<Vec<EventPayload>>,
 }
 
 impl History {
     pub fn new(size: usize) -> Self {
         Self {
-            size,
+            size: Some(size),
             undo_stack: VecDeque::new(),
         }
     }
-    fn push(&mut self, payload: EventPayload) {
-        self.undo_stack.push_back(payload);
-        if self.undo_stack.len() > self.size {
+    /// A history that never drops entries, for events that must be fully reverted.
+    pub fn unbounded() -> Self {
+        Self {
+            size: None,
+            undo_stack: VecDeque::new(),
+        }
+    }
+    fn push(&mut self, payloads: Vec<EventPayload>) {
+        self.undo_stack.push_back(payloads);
+        if self.size.is_some_and(|size| self.undo_stack.len() > size) {
             self.undo_stack.pop_front();
         }
     }
     pub fn is_empty(&self) -> bool {
         self.undo_stack.is_empty()
     }
+    /// Returns payloads that undo all recorded events, newest first.
     pub fn undo_all(&mut self) -> Vec<EventPayload> {
-        self.undo_stack.drain(..).collect()
+        self.undo_stack.drain(..).rev().flatten().collect()
+    }
+}
+
+/// Payloads that restore `projection` after `payload` is applied to it.
+///
+/// `projection` must be the one before `payload` is applied.
+pub fn inverse(projection: &Projection, payload: &EventPayload) -> Vec<EventPayload> {
+    match payload {
+        EventPayload::AddOwner { user_id } => {
+            if projection.owners.contains(user_id) {
+                vec![]
+            } else {
+                vec![EventPayload::RemoveOwner { user_id: *user_id }]
+            }
+        }
+        EventPayload::RemoveOwner { user_id } => {
+            if projection.owners.contains(user_id) {
+                vec![EventPayload::AddOwner { user_id: *user_id }]
+            } else {
+                vec![]
+            }
+        }
+        EventPayload::UpdateSpaceRules { .. } => vec![EventPayload::UpdateSpaceRules {
+            rules: projection.rules.clone(),
+        }],
+        EventPayload::CreateNode { node_id, .. } => {
+            vec![EventPayload::RemoveNode { node_id: *node_id }]
+        }
+        EventPayload::RemoveNode { node_id } => {
+            let Some(node) = projection.flat_nodes.get(node_id) else {
+                return vec![];
+            };
+            let node_id = *node_id;
+            [
+                EventPayload::CreateNode {
+                    node_id,
+                    content: node.content.clone(),
+                },
+                EventPayload::UpdateNodeRules {
+                    node_id,
+                    rules: node.rules.clone(),
+                },
+                EventPayload::UpdateOperandRules {
+                    node_id,
+                    rules: node.operand_rules.clone(),
+                },
+            ]
+            .into_iter()
+            .chain(
+                node.operands
+                    .iter()
+                    .map(|operand| EventPayload::PatchOperand {
+                        node_id,
+                        patch: OperandPatch::Insert {
+                            position: OperandPosition::Last,
+                            node_id: *operand,
+                        },
+                    }),
+            )
+            .chain(
+                node.attributes
+                    .iter()
+                    .map(|(key, value)| EventPayload::PatchAttribute {
+                        node_id,
+                        patch: AttributePatch::Update {
+                            key: key.clone(),
+                            value: value.clone(),
+                        },
+                    }),
+            )
+            .collect()
+        }
+        EventPayload::PatchContent { node_id, .. } => {
+            let Some(node) = projection.flat_nodes.get(node_id) else {
+                return vec![];
+            };
+            vec![EventPayload::PatchContent {
+                node_id: *node_id,
+                patch: ContentPatch::Replace(node.content.clone()),
+            }]
+        }
+        EventPayload::PatchOperand { node_id, patch } => {
+            let Some(node) = projection.flat_nodes.get(node_id) else {
+                return vec![];
+            };
+            let index_of = |operand| node.operands.iter().position(|id| id == operand);
+            let patch = match patch {
+                OperandPatch::Insert { node_id, .. } => OperandPatch::Remove { node_id: *node_id },
+                OperandPatch::Remove { node_id } => {
+                    let Some(index) = index_of(node_id) else {
+                        return vec![];
+                    };
+                    OperandPatch::Insert {
+                        position: OperandPosition::At(index),
+                        node_id: *node_id,
+                    }
+                }
+                OperandPatch::Move { node_id, .. } => {
+                    let Some(index) = index_of(node_id) else {
+                        return vec![];
+                    };
+                    OperandPatch::Move {
+                        node_id: *node_id,
+                        position: OperandPosition::At(index),
+                    }
+                }
+            };
+            vec![EventPayload::PatchOperand {
+                node_id: *node_id,
+                patch,
+            }]
+        }
+        EventPayload::PatchAttribute { node_id, patch } => {
+            let Some(node) = projection.flat_nodes.get(node_id) else {
+                return vec![];
+            };
+            let key = match patch {
+                AttributePatch::Update { key, .. } | AttributePatch::Remove { key } => key,
+            };
+            let patch = match node.attributes.get(key) {
+                Some(value) => AttributePatch::Update {
+                    key: key.clone(),
+                    value: value.clone(),
+                },
+                None => AttributePatch::Remove { key: key.clone() },
+            };
+            vec![EventPayload::PatchAttribute {
+                node_id: *node_id,
+                patch,
+            }]
+        }
+        EventPayload::UpdateNodeRules { node_id, .. } => {
+            let Some(node) = projection.flat_nodes.get(node_id) else {
+                return vec![];
+            };
+            vec![EventPayload::UpdateNodeRules {
+                node_id: *node_id,
+                rules: node.rules.clone(),
+            }]
+        }
+        EventPayload::UpdateOperandRules { node_id, .. } => {
+            let Some(node) = projection.flat_nodes.get(node_id) else {
+                return vec![];
+            };
+            vec![EventPayload::UpdateOperandRules {
+                node_id: *node_id,
+                rules: node.operand_rules.clone(),
+            }]
+        }
+        // Snapshots are not applied to the projection, so there is nothing to restore.
+        EventPayload::AddSnapshot { .. } => vec![],
     }
 }
 
 impl State for History {
     fn handle_event(&mut self, projection: &Projection, event: &Event) {
-        match &event.payload {
-            EventPayload::AddOwner { user_id } => {}
-            EventPayload::RemoveOwner { user_id } => {}
-            EventPayload::UpdateSpaceRules { rules } => {}
-            EventPayload::CreateNode { node_id, content } => {}
-            EventPayload::RemoveNode { node_id } => {}
-            EventPayload::PatchContent { node_id, patch } => {}
-            EventPayload::PatchOperand { node_id, patch } => {}
-            EventPayload::PatchAttribute { node_id, patch } => {}
-            EventPayload::UpdateNodeRules { node_id, rules } => {}
-            EventPayload::UpdateOperandRules { node_id, rules } => {}
-            EventPayload::AddSnapshot { index, snapshot } => {}
-        }
+        self.push(inverse(projection, &event.payload));
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use components::{event::EventId, user::UserId};
+    use components::{content::Content, event::EventId, user::UserId};
+    use deskc_ids::NodeId;
+    use deskc_ty::Type;
 
     use super::*;
 
@@ -65,13 +217,196 @@ mod tests {
         }
     }
 
+    fn assert_reverted(projection: &Projection, payload: EventPayload) {
+        let mut history = History::new(10);
+        let mut applied = projection.clone();
+        let event = e(payload);
+        history.handle_event(&applied, &event);
+        applied.handle_event(&event);
+        for payload in history.undo_all() {
+            applied.handle_event(&e(payload));
+        }
+        assert_eq!(&applied, projection);
+        assert!(history.is_empty());
+    }
+
+    fn projection_with_node() -> (Projection, NodeId, NodeId) {
+        let mut projection = Projection::default();
+        let node_a = NodeId::new();
+        let node_b = NodeId::new();
+        for node_id in [node_a, node_b] {
+            projection.handle_event(&e(EventPayload::CreateNode {
+                node_id,
+                content: Content::Integer(1),
+            }));
+        }
+        projection.handle_event(&e(EventPayload::PatchOperand {
+            node_id: node_a,
+            patch: OperandPatch::Insert {
+                position: OperandPosition::First,
+                node_id: node_b,
+            },
+        }));
+        projection.handle_event(&e(EventPayload::PatchAttribute {
+            node_id: node_a,
+            patch: AttributePatch::Update {
+                key: Type::Real,
+                value: 1.into(),
+            },
+        }));
+        (projection, node_a, node_b)
+    }
+
     #[test]
     fn test_history() {
         let mut history = History::new(10);
-        let mut projection = Projection::default();
+        let projection = Projection::default();
         let user_id = UserId::new();
         let event = e(EventPayload::AddOwner { user_id });
         history.handle_event(&projection, &event);
-        // assert_eq!(history.undo_stack, [
+        assert_eq!(
+            history.undo_all(),
+            vec![EventPayload::RemoveOwner { user_id }]
+        );
+    }
+
+    #[test]
+    fn undo_all_is_newest_first() {
+        let mut history = History::new(10);
+        let projection = Projection::default();
+        let node_id = NodeId::new();
+        let user_id = UserId::new();
+        history.handle_event(&projection, &e(EventPayload::AddOwner { user_id }));
+        history.handle_event(
+            &projection,
+            &e(EventPayload::CreateNode {
+                node_id,
+                content: Content::Integer(1),
+            }),
+        );
+        assert_eq!(
+            history.undo_all(),
+            vec![
+                EventPayload::RemoveNode { node_id },
+                EventPayload::RemoveOwner { user_id }
+            ]
+        );
+    }
+
+    #[test]
+    fn history_size() {
+        let mut history = History::new(1);
+        let projection = Projection::default();
+        let user_a = UserId::new();
+        let user_b = UserId::new();
+        history.handle_event(&projection, &e(EventPayload::AddOwner { user_id: user_a }));
+        history.handle_event(&projection, &e(EventPayload::AddOwner { user_id: user_b }));
+        assert_eq!(
+            history.undo_all(),
+            vec![EventPayload::RemoveOwner { user_id: user_b }]
+        );
+    }
+
+    #[test]
+    fn revert_create_node() {
+        let (projection, _, _) = projection_with_node();
+        assert_reverted(
+            &projection,
+            EventPayload::CreateNode {
+                node_id: NodeId::new(),
+                content: Content::String("a".into()),
+            },
+        );
+    }
+
+    #[test]
+    fn revert_remove_node() {
+        let (projection, node_a, _) = projection_with_node();
+        assert_reverted(&projection, EventPayload::RemoveNode { node_id: node_a });
+    }
+
+    #[test]
+    fn revert_patch_content() {
+        let (projection, node_a, _) = projection_with_node();
+        assert_reverted(
+            &projection,
+            EventPayload::PatchContent {
+                node_id: node_a,
+                patch: ContentPatch::Replace(Content::Integer(2)),
+            },
+        );
+    }
+
+    #[test]
+    fn revert_patch_operand() {
+        let (projection, node_a, node_b) = projection_with_node();
+        assert_reverted(
+            &projection,
+            EventPayload::PatchOperand {
+                node_id: node_a,
+                patch: OperandPatch::Remove { node_id: node_b },
+            },
+        );
+        assert_reverted(
+            &projection,
+            EventPayload::PatchOperand {
+                node_id: node_b,
+                patch: OperandPatch::Insert {
+                    position: OperandPosition::Last,
+                    node_id: NodeId::new(),
+                },
+            },
+        );
+    }
+
+    #[test]
+    fn revert_patch_attribute() {
+        let (projection, node_a, _) = projection_with_node();
+        assert_reverted(
+            &projection,
+            EventPayload::PatchAttribute {
+                node_id: node_a,
+                patch: AttributePatch::Update {
+                    key: Type::Real,
+                    value: 2.into(),
+                },
+            },
+        );
+        assert_reverted(
+            &projection,
+            EventPayload::PatchAttribute {
+                node_id: node_a,
+                patch: AttributePatch::Update {
+                    key: Type::String,
+                    value: 2.into(),
+                },
+            },
+        );
+        assert_reverted(
+            &projection,
+            EventPayload::PatchAttribute {
+                node_id: node_a,
+                patch: AttributePatch::Remove { key: Type::Real },
+            },
+        );
+    }
+
+    #[test]
+    fn unbounded_history_reverts_all_events() {
+        let (projection, node_a, _) = projection_with_node();
+        let mut history = History::unbounded();
+        let mut applied = projection.clone();
+        for integer in 0..2000 {
+            let event = e(EventPayload::PatchContent {
+                node_id: node_a,
+                patch: ContentPatch::UpdateInteger(integer),
+            });
+            history.handle_event(&applied, &event);
+            applied.handle_event(&event);
+        }
+        for payload in history.undo_all() {
+            applied.handle_event(&e(payload));
+        }
+        assert_eq!(applied, projection);
     }
 }
@@ -0,0 +1,400 @@
+use std::collections::BTreeMap;
+
+use components::{
+    content::Content,
+    event::EventPayload,
+    patch::{AttributePatch, ContentPatch, OperandPatch, OperandPosition, StringPatch},
+};
+use deskc_ast::{
+    diff::{diff, Edit, NodeRef},
+    expr::{Expr, Literal},
+    meta::WithMeta,
+    ty::{EffectExpr, Type},
+};
+use deskc_ids::NodeId;
+use deskc_ty::DsonTypeDeduction;
+use dson::Dson;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ImportError {
+    #[error("{kind} has no node in the node graph")]
+    Unsupported { node_id: NodeId, kind: &'static str },
+}
+
+/// Events that turn the nodes of `old` into `new`, preserving the nodes of matched AST nodes.
+pub fn import_events(
+    old: &WithMeta<Expr>,
+    new: &WithMeta<Expr>,
+) -> Result<Vec<EventPayload>, ImportError> {
+    edit_events(&diff(old, new))
+}
+
+pub fn edit_events(edits: &[Edit]) -> Result<Vec<EventPayload>, ImportError> {
+    let mut events = vec![];
+    for edit in edits {
+        match *edit {
+            Edit::Insert {
+                node_id,
+                node,
+                parent,
+                position,
+            } => {
+                events.push(EventPayload::CreateNode {
+                    node_id,
+                    content: content(node)?,
+                });
+                events.extend(insert(node_id, parent, position));
+            }
+            Edit::Delete { node_id, parent } => {
+                events.extend(remove(node_id, parent));
+                events.push(EventPayload::RemoveNode { node_id });
+            }
+            Edit::Move {
+                node_id,
+                from: Some(from),
+                parent: Some(parent),
+                position,
+            } if from == parent => events.push(EventPayload::PatchOperand {
+                node_id: parent,
+                patch: OperandPatch::Move {
+                    node_id,
+                    position: OperandPosition::At(position),
+                },
+            }),
+            Edit::Move {
+                node_id,
+                from,
+                parent,
+                position,
+            } => {
+                events.extend(remove(node_id, from));
+                events.extend(insert(node_id, parent, position));
+            }
+            Edit::UpdateLiteral { node_id, old, new } => {
+                let patch = match (old, new) {
+                    (Literal::String(_), Literal::String(string)) => {
+                        ContentPatch::PatchString(StringPatch::Replace(string.clone()))
+                    }
+                    (Literal::Integer(_), Literal::Integer(integer)) => {
+                        ContentPatch::UpdateInteger(*integer)
+                    }
+                    (Literal::Rational(_, _), Literal::Rational(a, b)) => {
+                        ContentPatch::UpdateRational(*a, *b)
+                    }
+                    (Literal::Real(_), Literal::Real(real)) => ContentPatch::UpdateReal(*real),
+                    (_, new) => ContentPatch::Replace(literal(new)),
+                };
+                events.push(EventPayload::PatchContent { node_id, patch });
+            }
+            Edit::UpdateType { node_id, ty } => events.push(EventPayload::PatchContent {
+                node_id,
+                patch: ContentPatch::Replace(type_content(node_id, ty)?),
+            }),
+            Edit::UpdateAttributes {
+                node_id,
+                ref old,
+                ref new,
+            } => events.extend(attribute_events(node_id, old, new)),
+        }
+    }
+    Ok(events)
+}
+
+fn insert(node_id: NodeId, parent: Option<NodeId>, position: usize) -> Option<EventPayload> {
+    parent.map(|parent| EventPayload::PatchOperand {
+        node_id: parent,
+        patch: OperandPatch::Insert {
+            position: OperandPosition::At(position),
+            node_id,
+        },
+    })
+}
+
+fn remove(node_id: NodeId, parent: Option<NodeId>) -> Option<EventPayload> {
+    parent.map(|parent| EventPayload::PatchOperand {
+        node_id: parent,
+        patch: OperandPatch::Remove { node_id },
+    })
+}
+
+/// Attributes are keyed by their types, so the last attribute of each type wins.
+fn attribute_events(node_id: NodeId, old: &[&Dson], new: &[&Dson]) -> Vec<EventPayload> {
+    let old: BTreeMap<_, _> = old.iter().map(|attr| (attr.deduct_type(), *attr)).collect();
+    let new: BTreeMap<_, _> = new.iter().map(|attr| (attr.deduct_type(), *attr)).collect();
+    let removes = old
+        .keys()
+        .filter(|key| !new.contains_key(*key))
+        .map(|key| AttributePatch::Remove { key: key.clone() });
+    let updates = new
+        .iter()
+        .filter(|(key, value)| old.get(*key) != Some(*value))
+        .map(|(key, value)| AttributePatch::Update {
+            key: key.clone(),
+            value: (*value).clone(),
+        });
+    removes
+        .chain(updates)
+        .map(|patch| EventPayload::PatchAttribute { node_id, patch })
+        .collect()
+}
+
+/// The reverse of `genast` for a node without its operands.
+fn content(node: NodeRef) -> Result<Content, ImportError> {
+    let node_id = node.id();
+    let unsupported = |kind| Err(ImportError::Unsupported { node_id, kind });
+    let content = match node {
+        NodeRef::Expr(expr) => match &expr.value {
+            Expr::Literal(value) => literal(value),
+            Expr::Do { .. } => Content::Do,
+            Expr::Let { .. } => Content::Let,
+            Expr::Perform { .. } => Content::Perform,
+            Expr::Continue { .. } => Content::Continue,
+            Expr::Handle { .. } => Content::Handle,
+            Expr::Apply { link_name, .. } => Content::Apply {
+                link_name: *link_name,
+            },
+            Expr::Product(_) => Content::Product,
+            Expr::Match { .. } => Content::Match,
+            Expr::Typed { .. } => Content::Typed,
+            Expr::Hole => Content::Hole,
+            Expr::Function { .. } => Content::Function,
+            Expr::Vector(_) => Content::Vector,
+            Expr::Map(_) => Content::Map,
+            Expr::DeclareBrand { brand, .. } => Content::DeclareBrand {
+                brand: brand.clone(),
+            },
+            Expr::Label { label, .. } => Content::Label {
+                label: label.clone(),
+            },
+            Expr::NewType { ident, .. } => Content::NewType {
+                ident: ident.clone(),
+            },
+            Expr::Attributed { .. } => unreachable!("attributes are diffed as part of nodes"),
+            Expr::Card { .. } => return unsupported("a card"),
+        },
+        NodeRef::Type(ty) => type_content(node_id, &ty.value)?,
+        NodeRef::EffectExpr(effects) => match &effects.value {
+            EffectExpr::Effects(_) => Content::Effects,
+            EffectExpr::Add(_) => Content::EAdd,
+            EffectExpr::Sub { .. } => Content::ESub,
+            EffectExpr::Apply { .. } => Content::EApply,
+        },
+        NodeRef::Effect(_) => Content::Effect,
+        NodeRef::Handler(_) => Content::Handler,
+        NodeRef::MatchCase(_) => Content::Case,
+        NodeRef::MapElem(_) => Content::MapElem,
+    };
+    Ok(content)
+}
+
+fn type_content(node_id: NodeId, ty: &Type) -> Result<Content, ImportError> {
+    let unsupported = |kind| Err(ImportError::Unsupported { node_id, kind });
+    let content = match ty {
+        Type::Labeled { brand, .. } => Content::TyLabeled {
+            brand: brand.clone(),
+        },
+        Type::Real => Content::TyReal,
+        Type::Rational => Content::TyRational,
+        Type::Integer => Content::TyInteger,
+        Type::String => Content::TyString,
+        Type::Effectful { .. } => Content::TyEffectful,
+        Type::Infer => Content::Infer,
+        Type::Product(_) => Content::TyProduct,
+        Type::Sum(_) => Content::Sum,
+        Type::Function(_) => Content::TyFunction,
+        Type::Vector(_) => Content::TyVector,
+        Type::Map { .. } => Content::TyMap,
+        Type::Let { variable, .. } => Content::TyLet {
+            ident: variable.clone(),
+        },
+        Type::Variable(ident) => Content::Variable {
+            ident: ident.clone(),
+        },
+        Type::Attributed { .. } => unreachable!("attributes are diffed as part of nodes"),
+        Type::Forall { .. } => return unsupported("a universal type"),
+        Type::Exists { .. } => return unsupported("an existential type"),
+    };
+    Ok(content)
+}
+
+fn literal(literal: &Literal) -> Content {
+    match literal {
+        Literal::String(string) => Content::String(string.clone()),
+        Literal::Integer(integer) => Content::Integer(*integer),
+        Literal::Rational(a, b) => Content::Rational(*a, *b),
+        Literal::Real(real) => Content::Real(*real),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use components::{code::SyntaxKind, event::Event, event::EventId, user::UserId};
+    use deskc::parse_source_code;
+    use deskc_ast::remove_span::replace_node_id_to_default;
+    use deskc_ids::CardId;
+
+    use crate::{nodes::NodeQueries, repository::TestRepository, Workspace};
+
+    use super::*;
+
+    fn parse(source: &str) -> WithMeta<Expr> {
+        parse_source_code(&SyntaxKind::Minimalist, source)
+            .unwrap()
+            .expr
+            .as_ref()
+            .clone()
+    }
+
+    fn handle(workspace: &mut Workspace, events: Vec<EventPayload>) {
+        let user_id = *workspace.projection.owners.iter().next().unwrap();
+        for payload in events {
+            workspace
+                .audit_and_handle(&Event {
+                    id: EventId::new(),
+                    user_id,
+                    payload,
+                })
+                .unwrap();
+        }
+    }
+
+    fn ast(workspace: &Workspace, node_id: NodeId) -> WithMeta<Expr> {
+        let components::code::Code::Ast(ast) = workspace.nodes.lock().ast(node_id).unwrap() else {
+            panic!()
+        };
+        ast.as_ref().clone()
+    }
+
+    fn without_ids(mut expr: WithMeta<Expr>) -> WithMeta<Expr> {
+        replace_node_id_to_default(&mut expr);
+        expr
+    }
+
+    #[test]
+    fn importing_edited_source_preserves_nodes() {
+        let mut workspace = Workspace::new(TestRepository::default());
+        workspace.projection.owners.insert(UserId::new());
+        let hole = WithMeta {
+            meta: NodeId::new().into(),
+            value: Expr::Hole,
+        };
+        let old = parse(r#"*<1, "a", [<'integer> 2]>"#);
+        let mut events = vec![EventPayload::CreateNode {
+            node_id: hole.meta.id,
+            content: Content::Hole,
+        }];
+        events.extend(import_events(&hole, &old).unwrap());
+        handle(&mut workspace, events);
+        assert_eq!(ast(&workspace, old.meta.id), old);
+        assert!(!workspace.projection.flat_nodes.contains_key(&hole.meta.id));
+
+        let new = parse(r#"*<"a", 3, [<'string> 2, 4]>"#);
+        handle(&mut workspace, import_events(&old, &new).unwrap());
+        let imported = ast(&workspace, old.meta.id);
+        assert_eq!(without_ids(imported.clone()), without_ids(new));
+
+        let Expr::Product(old_items) = &old.value else {
+            panic!()
+        };
+        let Expr::Product(items) = &imported.value else {
+            panic!()
+        };
+        // "a" moved, 1 updated to 3
+        assert_eq!(items[0].meta.id, old_items[1].meta.id);
+        assert_eq!(items[1].meta.id, old_items[0].meta.id);
+        let (Expr::Vector(old_vector), Expr::Vector(vector)) =
+            (&old_items[2].value, &items[2].value)
+        else {
+            panic!()
+        };
+        assert_eq!(items[2].meta.id, old_items[2].meta.id);
+        assert_eq!(vector[0].meta.id, old_vector[0].meta.id);
+        let (Expr::Typed { ty: old_ty, .. }, Expr::Typed { ty, .. }) =
+            (&old_vector[0].value, &vector[0].value)
+        else {
+            panic!()
+        };
+        assert_eq!(ty.meta.id, old_ty.meta.id);
+        assert_eq!(workspace.projection.flat_nodes.len(), 8);
+    }
+
+    #[test]
+    fn importing_attributes_patches_wrapped_nodes() {
+        let mut workspace = Workspace::new(TestRepository::default());
+        workspace.projection.owners.insert(UserId::new());
+        let hole = WithMeta {
+            meta: NodeId::new().into(),
+            value: Expr::Hole,
+        };
+        let old = parse(r#"*<# 1 "a", 2>"#);
+        let mut events = vec![EventPayload::CreateNode {
+            node_id: hole.meta.id,
+            content: Content::Hole,
+        }];
+        events.extend(import_events(&hole, &old).unwrap());
+        handle(&mut workspace, events);
+        assert_eq!(
+            without_ids(ast(&workspace, old.meta.id)),
+            without_ids(old.clone())
+        );
+
+        let Expr::Product(old_items) = &old.value else {
+            panic!()
+        };
+        let Expr::Attributed { item: a, .. } = &old_items[0].value else {
+            panic!()
+        };
+        let attributes = |workspace: &Workspace, node_id| {
+            workspace.projection.flat_nodes[&node_id].attributes.clone()
+        };
+        let integer = |integer| Dson::Literal(dson::Literal::Integer(integer));
+        let string = |string: &str| Dson::Literal(dson::Literal::String(string.into()));
+        assert_eq!(
+            attributes(&workspace, a.meta.id),
+            [(deskc_ty::Type::Integer, integer(1))].into()
+        );
+
+        let new = parse(r#"*<# "b" # 2 "a", 2>"#);
+        handle(&mut workspace, import_events(&old, &new).unwrap());
+        assert_eq!(
+            attributes(&workspace, a.meta.id),
+            [
+                (deskc_ty::Type::Integer, integer(2)),
+                (deskc_ty::Type::String, string("b")),
+            ]
+            .into()
+        );
+
+        // the old AST is generated by genast this time
+        let old = ast(&workspace, old.meta.id);
+        let new = parse(r#"*<"a", # 1 2>"#);
+        handle(&mut workspace, import_events(&old, &new).unwrap());
+        assert_eq!(without_ids(ast(&workspace, old.meta.id)), without_ids(new));
+        assert!(attributes(&workspace, a.meta.id).is_empty());
+        assert_eq!(workspace.projection.flat_nodes.len(), 3);
+    }
+
+    #[test]
+    fn unsupported_nodes_are_errors() {
+        let hole = WithMeta {
+            meta: NodeId::new().into(),
+            value: Expr::Hole,
+        };
+        let card = WithMeta {
+            meta: NodeId::new().into(),
+            value: Expr::Card {
+                id: CardId::new(),
+                item: Box::new(hole.clone()),
+                next: Box::new(hole.clone()),
+            },
+        };
+        assert_eq!(
+            import_events(&hole, &card),
+            Err(ImportError::Unsupported {
+                node_id: card.meta.id,
+                kind: "a card"
+            })
+        );
+    }
+}
@@ -2,11 +2,14 @@ mod audit;
 mod descendants;
 mod error;
 mod history;
+pub mod import;
 mod loop_detector;
 mod nodes;
 pub mod prelude;
 pub mod query_error;
+mod rebase;
 mod references;
+pub mod rejection;
 pub mod repository;
 pub mod state;
 
@@ -14,12 +17,19 @@ use std::{any::TypeId, collections::BTreeMap, sync::Arc};
 
 use audit::execute_assertion::AssertionError;
 use bevy_ecs::prelude::Component;
-use components::{event::Event, node::Node, projection::Projection, user::UserId};
+use components::{
+    event::{Event, EventId},
+    node::Node,
+    projection::Projection,
+    user::UserId,
+};
 use deskc_ids::NodeId;
 use history::History;
 use loop_detector::LoopDetector;
 use nodes::{NodeQueries, Nodes};
 use parking_lot::Mutex;
+use rebase::Rebaser;
+use rejection::Rejection;
 use repository::Repository;
 use state::State;
 
@@ -31,9 +41,11 @@ pub struct Workspace {
     // salsa database is not Sync
     references: Mutex<references::References>,
     loop_detector: LoopDetector,
+    rebaser: Rebaser,
     pub projection: Projection,
     history: History,
     ephemeral_history: History,
+    rejections: Vec<Rejection>,
     states: BTreeMap<TypeId, Box<dyn State + Send + Sync + 'static>>,
 }
 
@@ -44,9 +56,11 @@ impl Workspace {
             nodes: Default::default(),
             references: Default::default(),
             loop_detector: Default::default(),
+            rebaser: Default::default(),
             projection: Default::default(),
             history: History::new(100),
-            ephemeral_history: History::new(1000),
+            ephemeral_history: History::unbounded(),
+            rejections: Default::default(),
             states: Default::default(),
         }
     }
@@ -62,14 +76,65 @@ impl Workspace {
         );
         let events = self.repository.poll();
         for event in events {
-            // TODO: handle assertion error.
-            if let Err(_err) = self.audit_and_handle(&event) {
+            let event = self.rebaser.rebase(&self.projection, event);
+            if let Err(error) = self.audit(&event) {
+                self.handle_rejection(Rejection {
+                    event_id: event.id,
+                    user_id: event.user_id,
+                    error,
+                });
             } else {
                 self.history.handle_event(&self.projection, &event);
+                self.rebaser.handle_event(&self.projection, &event);
+                self.handle_event(&event);
             }
         }
     }
 
+    fn handle_rejection(&mut self, rejection: Rejection) {
+        for state in self.states.values_mut() {
+            state.handle_rejection(&self.projection, &rejection);
+        }
+        self.rejections.push(rejection);
+    }
+
+    /// Rejections not drained yet.
+    pub fn rejections(&self) -> &[Rejection] {
+        &self.rejections
+    }
+
+    /// Takes rejections since the last call of this.
+    pub fn drain_rejections(&mut self) -> Vec<Rejection> {
+        std::mem::take(&mut self.rejections)
+    }
+
+    /// Applies an event locally without committing it.
+    ///
+    /// The event must be reverted by `revert_ephemeral` before the next `process`.
+    pub fn apply_ephemeral(&mut self, event: Event) -> Result<(), AssertionError> {
+        self.audit(&event)?;
+        self.ephemeral_history
+            .handle_event(&self.projection, &event);
+        self.handle_event(&event);
+        Ok(())
+    }
+
+    /// Reverts all events applied by `apply_ephemeral`, newest first.
+    pub fn revert_ephemeral(&mut self) {
+        let user_id = self.user_id();
+        for payload in self.ephemeral_history.undo_all() {
+            self.handle_event(&Event {
+                id: EventId::new(),
+                user_id,
+                payload,
+            });
+        }
+    }
+
+    pub fn has_ephemeral(&self) -> bool {
+        !self.ephemeral_history.is_empty()
+    }
+
     pub fn audit_and_handle(&mut self, event: &Event) -> Result<(), AssertionError> {
         self.audit(event)?;
         self.handle_event(event);
@@ -111,6 +176,11 @@ impl Workspace {
         self.repository.user_id()
     }
 
+    /// The revision to make `StringPatch::DiffMatchPatch` against.
+    pub fn content_revision(&self, node_id: NodeId) -> usize {
+        self.rebaser.revision(&node_id)
+    }
+
     pub fn node(&self, node_id: NodeId) -> Arc<Node> {
         self.nodes.lock().node(node_id)
     }
@@ -123,7 +193,10 @@ mod tests {
     use components::patch::OperandPosition;
     use components::rules::{NodeOperation, Rules, SpaceOperation};
     use components::user::UserId;
-    use components::{content::Content, patch::OperandPatch};
+    use components::{
+        content::Content,
+        patch::{ContentPatch, OperandPatch},
+    };
     use deskc_ast::remove_span::replace_node_id_to_default;
     use deskc_ast::ty::Function;
     use deskc_ast::{
@@ -147,6 +220,9 @@ mod tests {
         fn handle_event(&mut self, _snapshot: &Projection, _: &Event) {
             panic!()
         }
+        fn handle_rejection(&mut self, _snapshot: &Projection, _: &Rejection) {
+            panic!()
+        }
     }
 
     #[test]
@@ -157,6 +233,9 @@ mod tests {
         let user_b = UserId::new();
         let node_a = NodeId::new();
         let node_b = NodeId::new();
+        let ty_function = NodeId::new();
+        let ty_string = NodeId::new();
+        let ty_real = NodeId::new();
 
         let add_owner = Event {
             id: EventId::new(),
@@ -195,6 +274,63 @@ mod tests {
                     },
                 },
             },
+            Event {
+                id: EventId::new(),
+                user_id: user_a,
+                payload: EventPayload::CreateNode {
+                    node_id: ty_function,
+                    content: Content::TyFunction,
+                },
+            },
+            Event {
+                id: EventId::new(),
+                user_id: user_a,
+                payload: EventPayload::CreateNode {
+                    node_id: ty_string,
+                    content: Content::TyString,
+                },
+            },
+            Event {
+                id: EventId::new(),
+                user_id: user_a,
+                payload: EventPayload::CreateNode {
+                    node_id: ty_real,
+                    content: Content::TyReal,
+                },
+            },
+            Event {
+                id: EventId::new(),
+                user_id: user_a,
+                payload: EventPayload::PatchOperand {
+                    node_id: ty_function,
+                    patch: OperandPatch::Insert {
+                        position: OperandPosition::Last,
+                        node_id: ty_string,
+                    },
+                },
+            },
+            Event {
+                id: EventId::new(),
+                user_id: user_a,
+                payload: EventPayload::PatchOperand {
+                    node_id: ty_function,
+                    patch: OperandPatch::Insert {
+                        position: OperandPosition::Last,
+                        node_id: ty_real,
+                    },
+                },
+            },
+            Event {
+                id: EventId::new(),
+                user_id: user_a,
+                payload: EventPayload::PatchOperand {
+                    node_id: node_a,
+                    patch: OperandPatch::Insert {
+                        position: OperandPosition::First,
+                        node_id: ty_function,
+                    },
+                },
+            },
             Event {
                 id: EventId::new(),
                 user_id: user_a.clone(),
@@ -220,7 +356,7 @@ mod tests {
                 payload: EventPayload::PatchOperand {
                     node_id: node_a.clone(),
                     patch: OperandPatch::Insert {
-                        position: OperandPosition::First,
+                        position: OperandPosition::Last,
                         node_id: node_b,
                     },
                 },
@@ -229,12 +365,15 @@ mod tests {
 
         let mut test_state = TestState::default();
         test_state.mock_handle_event(mry::Any, mry::Any).returns(());
+        test_state
+            .mock_handle_rejection(mry::Any, mry::Any)
+            .returns(());
 
         let mut kernel = Workspace::new(repository);
         kernel.add_state(test_state);
         kernel.process();
 
-        assert_eq!(kernel.projection.flat_nodes.len(), 2);
+        assert_eq!(kernel.projection.flat_nodes.len(), 5);
         assert_eq!(kernel.projection.owners.len(), 1);
         let mut ast = if let Code::Ast(ast) = kernel.nodes.lock().ast(node_a).unwrap() {
             ast.as_ref().clone()
@@ -273,7 +412,7 @@ mod tests {
             .get_state_mut::<TestState>()
             .unwrap()
             .mock_handle_event(mry::Any, mry::Any)
-            .assert_called(6);
+            .assert_called(12);
 
         // asserts handle_event was called with unprocessed snapshot
         kernel
@@ -282,4 +421,140 @@ mod tests {
             .mock_handle_event(Projection::default(), add_owner)
             .assert_called(1);
     }
+
+    #[test]
+    fn ephemeral_events_are_reverted() {
+        let mut repository = TestRepository::default();
+        let user_a = UserId::new();
+        let node_a = NodeId::new();
+        let node_b = NodeId::new();
+        repository.mock_user_id().returns(user_a);
+        repository.mock_poll().returns(vec![
+            Event {
+                id: EventId::new(),
+                user_id: user_a,
+                payload: EventPayload::AddOwner { user_id: user_a },
+            },
+            Event {
+                id: EventId::new(),
+                user_id: user_a,
+                payload: EventPayload::CreateNode {
+                    node_id: node_a,
+                    content: Content::String("a".into()),
+                },
+            },
+        ]);
+        let mut kernel = Workspace::new(repository);
+        kernel.process();
+        let projection = kernel.projection.clone();
+
+        let ephemeral = |payload| Event {
+            id: EventId::new(),
+            user_id: user_a,
+            payload,
+        };
+        kernel
+            .apply_ephemeral(ephemeral(EventPayload::PatchContent {
+                node_id: node_a,
+                patch: ContentPatch::Replace(Content::String("b".into())),
+            }))
+            .unwrap();
+        kernel
+            .apply_ephemeral(ephemeral(EventPayload::CreateNode {
+                node_id: node_b,
+                content: Content::Integer(1),
+            }))
+            .unwrap();
+        kernel
+            .apply_ephemeral(ephemeral(EventPayload::PatchOperand {
+                node_id: node_a,
+                patch: OperandPatch::Insert {
+                    position: OperandPosition::First,
+                    node_id: node_b,
+                },
+            }))
+            .unwrap();
+        assert!(kernel.has_ephemeral());
+        assert_eq!(kernel.node(node_a).content, Content::String("b".into()));
+        assert_eq!(kernel.top_level_nodes(), vec![node_a]);
+
+        kernel.revert_ephemeral();
+        assert!(!kernel.has_ephemeral());
+        assert_eq!(kernel.projection, projection);
+        assert_eq!(kernel.node(node_a).content, Content::String("a".into()));
+        assert_eq!(kernel.node(node_a).operands, vec![]);
+        assert_eq!(kernel.top_level_nodes(), vec![node_a]);
+        // next process does not panic
+        kernel.process();
+    }
+
+    #[test]
+    fn rejected_ephemeral_event_is_not_applied() {
+        let mut kernel = Workspace::new(TestRepository::default());
+        let node_a = NodeId::new();
+        kernel.projection.owners.insert(UserId::new());
+        assert!(kernel
+            .apply_ephemeral(Event {
+                id: EventId::new(),
+                user_id: UserId::new(),
+                payload: EventPayload::CreateNode {
+                    node_id: node_a,
+                    content: Content::Integer(1),
+                },
+            })
+            .is_err());
+        assert!(!kernel.has_ephemeral());
+        assert!(kernel.projection.flat_nodes.is_empty());
+    }
+
+    #[test]
+    fn rejected_events_are_logged() {
+        let mut repository = TestRepository::default();
+        let user_a = UserId::new();
+        let user_b = UserId::new();
+        let node_a = NodeId::new();
+        let rejected = Event {
+            id: EventId::new(),
+            user_id: user_b,
+            payload: EventPayload::CreateNode {
+                node_id: node_a,
+                content: Content::Integer(1),
+            },
+        };
+        repository.mock_poll().returns(vec![
+            Event {
+                id: EventId::new(),
+                user_id: user_a,
+                payload: EventPayload::AddOwner { user_id: user_a },
+            },
+            rejected.clone(),
+        ]);
+        let mut test_state = TestState::default();
+        test_state.mock_handle_event(mry::Any, mry::Any).returns(());
+        test_state
+            .mock_handle_rejection(mry::Any, mry::Any)
+            .returns(());
+
+        let mut kernel = Workspace::new(repository);
+        kernel.add_state(test_state);
+        kernel.process();
+
+        let rejection = Rejection {
+            event_id: rejected.id,
+            user_id: user_b,
+            error: AssertionError::Any(vec![
+                AssertionError::NotOwner,
+                AssertionError::SpaceDenied(SpaceOperation::CreateNode),
+            ]),
+        };
+        assert!(kernel.projection.flat_nodes.is_empty());
+        assert_eq!(kernel.rejections(), std::slice::from_ref(&rejection));
+        let state = kernel.get_state_mut::<TestState>().unwrap();
+        state.mock_handle_event(mry::Any, mry::Any).assert_called(1);
+        state
+            .mock_handle_rejection(mry::Any, rejection.clone())
+            .assert_called(1);
+        assert_eq!(kernel.drain_rejections(), vec![rejection]);
+        assert!(kernel.rejections().is_empty());
+    }
 }
@@ -1,6 +1,8 @@
 mod ast;
 mod node;
 
+pub use ast::{GenAstError, NodeKind, OperandCount};
+
 use std::sync::Arc;
 
 use ast::ast;
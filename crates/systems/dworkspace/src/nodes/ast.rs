@@ -3,16 +3,77 @@ use std::sync::Arc;
 use components::{code::Code, content::Content, node::Node};
 use deskc::parse_source_code;
 use deskc_ast::{
-    expr::{Expr, Literal},
+    expr::{Expr, Handler, Literal, MapElem, MatchCase},
     meta::{Meta, WithMeta},
     ty::{Effect, EffectExpr, Function, Type},
 };
 use deskc_ids::NodeId;
+use dson::Dson;
+use thiserror::Error;
 
 use crate::query_error::QueryError;
 
 use super::NodeQueries;
 
+#[derive(Error, Debug, PartialEq)]
+pub enum GenAstError {
+    #[error("{content:?} expects {expected} operands but has {actual}")]
+    OperandCount {
+        node_id: NodeId,
+        content: Content,
+        expected: OperandCount,
+        actual: usize,
+    },
+    #[error("expected {expected} but found {content:?}")]
+    UnexpectedContent {
+        node_id: NodeId,
+        expected: NodeKind,
+        content: Content,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandCount {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl std::fmt::Display for OperandCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OperandCount::Exactly(count) => write!(f, "{count}"),
+            OperandCount::AtLeast(count) => write!(f, "at least {count}"),
+        }
+    }
+}
+
+/// What a node is used as in its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Expr,
+    Type,
+    EffectExpr,
+    Effect,
+    Handler,
+    MatchCase,
+    MapElem,
+}
+
+impl std::fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            NodeKind::Expr => "an expression",
+            NodeKind::Type => "a type",
+            NodeKind::EffectExpr => "effects",
+            NodeKind::Effect => "an effect",
+            NodeKind::Handler => "a handler",
+            NodeKind::MatchCase => "a match case",
+            NodeKind::MapElem => "a map element",
+        };
+        write!(f, "{kind}")
+    }
+}
+
 pub(super) fn ast(db: &dyn NodeQueries, node_id: NodeId) -> Result<Code, QueryError> {
     let ast = db.node(node_id);
 
@@ -20,37 +81,432 @@ pub(super) fn ast(db: &dyn NodeQueries, node_id: NodeId) -> Result<Code, QueryEr
 }
 
 fn genast(node: &Node) -> Result<Code, anyhow::Error> {
-    let expr = match &node.content {
-        Content::SourceCode { syntax, source } => {
+    if let Content::SourceCode { syntax, source } = &node.content {
+        if node.attributes.is_empty() {
             return Ok(Code::SourceCode {
                 syntax: syntax.clone(),
                 source: Arc::new(source.clone()),
-            })
-        }
-        Content::String(string) => Expr::Literal(Literal::String(string.clone())),
-        Content::Integer(integer) => Expr::Literal(Literal::Integer(*integer)),
-        Content::Rational(a, b) => Expr::Literal(Literal::Rational(*a, *b)),
-        Content::Real(float) => Expr::Literal(Literal::Real(*float)),
-        Content::Apply { link_name } => Expr::Apply {
-            function: todo!(),
-            link_name: link_name.clone(),
-            arguments: node
-                .operands
+            });
+        }
+    }
+    Ok(Code::Ast(Arc::new(gen_expr(node)?)))
+}
+
+fn with_meta<T>(node: &Node, value: T) -> WithMeta<T> {
+    WithMeta {
+        meta: node.id.into(),
+        value,
+    }
+}
+
+fn unexpected(node: &Node, expected: NodeKind) -> anyhow::Error {
+    GenAstError::UnexpectedContent {
+        node_id: node.id,
+        expected,
+        content: node.content.clone(),
+    }
+    .into()
+}
+
+/// Returns operands after validating the count.
+fn operands<const N: usize>(node: &Node) -> Result<&[Node; N], GenAstError> {
+    node.operands
+        .as_slice()
+        .try_into()
+        .map_err(|_| GenAstError::OperandCount {
+            node_id: node.id,
+            content: node.content.clone(),
+            expected: OperandCount::Exactly(N),
+            actual: node.operands.len(),
+        })
+}
+
+/// Returns the first operand and the rest.
+fn split_first(node: &Node) -> Result<(&Node, &[Node]), GenAstError> {
+    node.operands
+        .split_first()
+        .ok_or_else(|| GenAstError::OperandCount {
+            node_id: node.id,
+            content: node.content.clone(),
+            expected: OperandCount::AtLeast(1),
+            actual: 0,
+        })
+}
+
+fn gen_expr(node: &Node) -> Result<WithMeta<Expr>, anyhow::Error> {
+    let expr = match &node.content {
+        Content::SourceCode { syntax, source } => {
+            let [] = operands(node)?;
+            parse_source_code(syntax, source)?.expr.as_ref().clone()
+        }
+        Content::String(string) => {
+            let [] = operands(node)?;
+            with_meta(node, Expr::Literal(Literal::String(string.clone())))
+        }
+        Content::Integer(integer) => {
+            let [] = operands(node)?;
+            with_meta(node, Expr::Literal(Literal::Integer(*integer)))
+        }
+        Content::Rational(a, b) => {
+            let [] = operands(node)?;
+            with_meta(node, Expr::Literal(Literal::Rational(*a, *b)))
+        }
+        Content::Real(float) => {
+            let [] = operands(node)?;
+            with_meta(node, Expr::Literal(Literal::Real(*float)))
+        }
+        Content::Apply { link_name } => {
+            let (function, arguments) = split_first(node)?;
+            with_meta(
+                node,
+                Expr::Apply {
+                    function: gen_type(function)?,
+                    link_name: *link_name,
+                    arguments: arguments.iter().map(gen_expr).collect::<Result<_, _>>()?,
+                },
+            )
+        }
+        Content::Do => {
+            let [stmt, expr] = operands(node)?;
+            with_meta(
+                node,
+                Expr::Do {
+                    stmt: Box::new(gen_expr(stmt)?),
+                    expr: Box::new(gen_expr(expr)?),
+                },
+            )
+        }
+        Content::Let => {
+            let [definition, body] = operands(node)?;
+            with_meta(
+                node,
+                Expr::Let {
+                    definition: Box::new(gen_expr(definition)?),
+                    body: Box::new(gen_expr(body)?),
+                },
+            )
+        }
+        Content::Perform => {
+            let [input, output] = operands(node)?;
+            with_meta(
+                node,
+                Expr::Perform {
+                    input: Box::new(gen_expr(input)?),
+                    output: gen_type(output)?,
+                },
+            )
+        }
+        Content::Continue => {
+            let [input, output] = operands(node)?;
+            with_meta(
+                node,
+                Expr::Continue {
+                    input: Box::new(gen_expr(input)?),
+                    output: gen_type(output)?,
+                },
+            )
+        }
+        Content::Handle => {
+            let (expr, handlers) = split_first(node)?;
+            with_meta(
+                node,
+                Expr::Handle {
+                    expr: Box::new(gen_expr(expr)?),
+                    handlers: handlers.iter().map(gen_handler).collect::<Result<_, _>>()?,
+                },
+            )
+        }
+        Content::Product => with_meta(
+            node,
+            Expr::Product(
+                node.operands
+                    .iter()
+                    .map(gen_expr)
+                    .collect::<Result<_, _>>()?,
+            ),
+        ),
+        Content::Match => {
+            let (of, cases) = split_first(node)?;
+            with_meta(
+                node,
+                Expr::Match {
+                    of: Box::new(gen_expr(of)?),
+                    cases: cases.iter().map(gen_case).collect::<Result<_, _>>()?,
+                },
+            )
+        }
+        Content::Typed => {
+            let [ty, item] = operands(node)?;
+            with_meta(
+                node,
+                Expr::Typed {
+                    ty: gen_type(ty)?,
+                    item: Box::new(gen_expr(item)?),
+                },
+            )
+        }
+        Content::Hole => {
+            let [] = operands(node)?;
+            with_meta(node, Expr::Hole)
+        }
+        Content::Function => {
+            let [parameter, body] = operands(node)?;
+            with_meta(
+                node,
+                Expr::Function {
+                    parameter: gen_type(parameter)?,
+                    body: Box::new(gen_expr(body)?),
+                },
+            )
+        }
+        Content::Vector => with_meta(
+            node,
+            Expr::Vector(
+                node.operands
+                    .iter()
+                    .map(gen_expr)
+                    .collect::<Result<_, _>>()?,
+            ),
+        ),
+        Content::Map => with_meta(
+            node,
+            Expr::Map(
+                node.operands
+                    .iter()
+                    .map(gen_map_elem)
+                    .collect::<Result<_, _>>()?,
+            ),
+        ),
+        Content::DeclareBrand { brand } => {
+            let [item] = operands(node)?;
+            with_meta(
+                node,
+                Expr::DeclareBrand {
+                    brand: brand.clone(),
+                    item: Box::new(gen_expr(item)?),
+                },
+            )
+        }
+        Content::Label { label } => {
+            let [item] = operands(node)?;
+            with_meta(
+                node,
+                Expr::Label {
+                    label: label.clone(),
+                    item: Box::new(gen_expr(item)?),
+                },
+            )
+        }
+        Content::NewType { ident } => {
+            let [ty, expr] = operands(node)?;
+            with_meta(
+                node,
+                Expr::NewType {
+                    ident: ident.clone(),
+                    ty: gen_type(ty)?,
+                    expr: Box::new(gen_expr(expr)?),
+                },
+            )
+        }
+        _ => return Err(unexpected(node, NodeKind::Expr)),
+    };
+    Ok(attributed(node, expr, |attr, item| Expr::Attributed {
+        attr,
+        item: Box::new(item),
+    }))
+}
+
+/// Wraps the item with attributes of the node, sorted by their keys.
+fn attributed<T>(
+    node: &Node,
+    item: WithMeta<T>,
+    wrap: impl Fn(Dson, WithMeta<T>) -> T,
+) -> WithMeta<T> {
+    let mut attributes: Vec<_> = node.attributes.iter().collect();
+    attributes.sort_by_key(|(key, _)| *key);
+    attributes
+        .into_iter()
+        .fold(item, |item, (_, attr)| WithMeta {
+            meta: Meta::new_no_comments(),
+            value: wrap(attr.clone(), item),
+        })
+}
+
+fn gen_type(node: &Node) -> Result<WithMeta<Type>, anyhow::Error> {
+    let ty = match &node.content {
+        Content::TyLabeled { brand } => {
+            let [item] = operands(node)?;
+            Type::Labeled {
+                brand: brand.clone(),
+                item: Box::new(gen_type(item)?),
+            }
+        }
+        Content::TyReal => {
+            let [] = operands(node)?;
+            Type::Real
+        }
+        Content::TyRational => {
+            let [] = operands(node)?;
+            Type::Rational
+        }
+        Content::TyInteger => {
+            let [] = operands(node)?;
+            Type::Integer
+        }
+        Content::TyString => {
+            let [] = operands(node)?;
+            Type::String
+        }
+        Content::TyEffectful => {
+            let [ty, effects] = operands(node)?;
+            Type::Effectful {
+                ty: Box::new(gen_type(ty)?),
+                effects: gen_effect_expr(effects)?,
+            }
+        }
+        Content::Infer => {
+            let [] = operands(node)?;
+            Type::Infer
+        }
+        Content::TyProduct => Type::Product(
+            node.operands
                 .iter()
-                .map(|node| match genast(node)? {
-                    Code::SourceCode { syntax, source } => {
-                        Ok(parse_source_code(&syntax, &source)?.expr.as_ref().clone())
-                    }
-                    Code::Ast(ast) => Ok(ast.as_ref().clone()),
-                })
-                .collect::<Result<Vec<_>, anyhow::Error>>()?,
+                .map(gen_type)
+                .collect::<Result<_, _>>()?,
+        ),
+        Content::Sum => Type::Sum(
+            node.operands
+                .iter()
+                .map(gen_type)
+                .collect::<Result<_, _>>()?,
+        ),
+        Content::TyFunction => {
+            let [parameter, body] = operands(node)?;
+            Type::Function(Box::new(Function {
+                parameter: gen_type(parameter)?,
+                body: gen_type(body)?,
+            }))
+        }
+        Content::TyVector => {
+            let [ty] = operands(node)?;
+            Type::Vector(Box::new(gen_type(ty)?))
+        }
+        Content::TyMap => {
+            let [key, value] = operands(node)?;
+            Type::Map {
+                key: Box::new(gen_type(key)?),
+                value: Box::new(gen_type(value)?),
+            }
+        }
+        Content::TyLet { ident } => {
+            let [definition, body] = operands(node)?;
+            Type::Let {
+                variable: ident.clone(),
+                definition: Box::new(gen_type(definition)?),
+                body: Box::new(gen_type(body)?),
+            }
+        }
+        Content::Variable { ident } => {
+            let [] = operands(node)?;
+            Type::Variable(ident.clone())
+        }
+        _ => return Err(unexpected(node, NodeKind::Type)),
+    };
+    Ok(attributed(node, with_meta(node, ty), |attr, ty| {
+        Type::Attributed {
+            attr,
+            ty: Box::new(ty),
+        }
+    }))
+}
+
+fn gen_effect_expr(node: &Node) -> Result<WithMeta<EffectExpr>, anyhow::Error> {
+    let effects = match &node.content {
+        Content::Effects => EffectExpr::Effects(
+            node.operands
+                .iter()
+                .map(gen_effect)
+                .collect::<Result<_, _>>()?,
+        ),
+        Content::EAdd => EffectExpr::Add(
+            node.operands
+                .iter()
+                .map(gen_effect_expr)
+                .collect::<Result<_, _>>()?,
+        ),
+        Content::ESub => {
+            let [minuend, subtrahend] = operands(node)?;
+            EffectExpr::Sub {
+                minuend: Box::new(gen_effect_expr(minuend)?),
+                subtrahend: Box::new(gen_effect_expr(subtrahend)?),
+            }
+        }
+        Content::EApply => {
+            let (function, arguments) = split_first(node)?;
+            EffectExpr::Apply {
+                function: Box::new(gen_type(function)?),
+                arguments: arguments.iter().map(gen_type).collect::<Result<_, _>>()?,
+            }
+        }
+        _ => return Err(unexpected(node, NodeKind::EffectExpr)),
+    };
+    Ok(with_meta(node, effects))
+}
+
+fn gen_effect(node: &Node) -> Result<WithMeta<Effect>, anyhow::Error> {
+    let Content::Effect = node.content else {
+        return Err(unexpected(node, NodeKind::Effect));
+    };
+    let [input, output] = operands(node)?;
+    Ok(with_meta(
+        node,
+        Effect {
+            input: gen_type(input)?,
+            output: gen_type(output)?,
+        },
+    ))
+}
+
+fn gen_handler(node: &Node) -> Result<WithMeta<Handler>, anyhow::Error> {
+    let Content::Handler = node.content else {
+        return Err(unexpected(node, NodeKind::Handler));
+    };
+    let [effect, handler] = operands(node)?;
+    Ok(with_meta(
+        node,
+        Handler {
+            effect: gen_effect(effect)?,
+            handler: gen_expr(handler)?,
         },
-        _ => todo!(),
+    ))
+}
+
+fn gen_case(node: &Node) -> Result<WithMeta<MatchCase>, anyhow::Error> {
+    let Content::Case = node.content else {
+        return Err(unexpected(node, NodeKind::MatchCase));
+    };
+    let [ty, expr] = operands(node)?;
+    Ok(with_meta(
+        node,
+        MatchCase {
+            ty: gen_type(ty)?,
+            expr: gen_expr(expr)?,
+        },
+    ))
+}
+
+fn gen_map_elem(node: &Node) -> Result<WithMeta<MapElem>, anyhow::Error> {
+    let Content::MapElem = node.content else {
+        return Err(unexpected(node, NodeKind::MapElem));
     };
-    Ok(Code::Ast(Arc::new(WithMeta {
-        meta: node.id.clone().into(),
-        value: expr,
-    })))
+    let [key, value] = operands(node)?;
+    Ok(with_meta(
+        node,
+        MapElem {
+            key: gen_expr(key)?,
+            value: gen_expr(value)?,
+        },
+    ))
 }
 
 fn from_types(ty: &deskc_ty::Type) -> WithMeta<Type> {
@@ -129,3 +585,233 @@ fn from_types_effects(effects: &deskc_ty::EffectExpr) -> WithMeta<EffectExpr> {
         value,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use components::{code::SyntaxKind, flat_node::Attributes};
+    use deskc_ast::remove_span::replace_node_id_to_default;
+    use deskc_ids::LinkName;
+
+    use super::*;
+
+    fn n(content: Content, operands: Vec<Node>) -> Node {
+        Node {
+            id: NodeId::new(),
+            content,
+            operands,
+            attributes: Attributes::default(),
+        }
+    }
+
+    fn assert_same_as_source(node: &Node, source: &str) {
+        let mut expr = gen_expr(node).unwrap();
+        let mut expected = parse_source_code(&SyntaxKind::Minimalist, source)
+            .unwrap()
+            .expr
+            .as_ref()
+            .clone();
+        replace_node_id_to_default(&mut expr);
+        replace_node_id_to_default(&mut expected);
+        assert_eq!(expr, expected);
+    }
+
+    fn labeled_unit(brand: &str) -> Node {
+        n(
+            Content::TyLabeled {
+                brand: brand.into(),
+            },
+            vec![n(Content::TyProduct, vec![])],
+        )
+    }
+
+    #[test]
+    fn match_expr() {
+        let node = n(
+            Content::Match,
+            vec![
+                n(
+                    Content::Apply {
+                        link_name: LinkName::None,
+                    },
+                    vec![labeled_unit("false")],
+                ),
+                n(
+                    Content::Case,
+                    vec![labeled_unit("true"), n(Content::Integer(1), vec![])],
+                ),
+                n(
+                    Content::Case,
+                    vec![labeled_unit("false"), n(Content::Integer(2), vec![])],
+                ),
+            ],
+        );
+        assert_same_as_source(
+            &node,
+            "'match & @false *<> '{ @true *<> => 1, @false *<> => 2 }'",
+        );
+    }
+
+    #[test]
+    fn let_function_and_apply() {
+        let integer = || n(Content::TyInteger, vec![]);
+        let node = n(
+            Content::Let,
+            vec![
+                n(
+                    Content::Function,
+                    vec![
+                        integer(),
+                        n(
+                            Content::Apply {
+                                link_name: LinkName::None,
+                            },
+                            vec![
+                                n(
+                                    Content::TyFunction,
+                                    vec![
+                                        n(
+                                            Content::TyProduct,
+                                            vec![
+                                                n(
+                                                    Content::TyLabeled { brand: "l".into() },
+                                                    vec![integer()],
+                                                ),
+                                                n(
+                                                    Content::TyLabeled { brand: "r".into() },
+                                                    vec![integer()],
+                                                ),
+                                            ],
+                                        ),
+                                        n(
+                                            Content::TyLabeled {
+                                                brand: "sum".into(),
+                                            },
+                                            vec![integer()],
+                                        ),
+                                    ],
+                                ),
+                                n(
+                                    Content::Product,
+                                    vec![
+                                        n(
+                                            Content::Label { label: "l".into() },
+                                            vec![n(Content::Integer(1), vec![])],
+                                        ),
+                                        n(
+                                            Content::Label { label: "r".into() },
+                                            vec![n(
+                                                Content::Apply {
+                                                    link_name: LinkName::None,
+                                                },
+                                                vec![integer()],
+                                            )],
+                                        ),
+                                    ],
+                                ),
+                            ],
+                        ),
+                    ],
+                ),
+                n(Content::Hole, vec![]),
+            ],
+        );
+        assert_same_as_source(
+            &node,
+            "$ \\ 'integer -> ^ \\ *<@l 'integer, @r 'integer> -> @sum 'integer (*<@l 1, @r &'integer>); ?",
+        );
+    }
+
+    #[test]
+    fn source_code_operand() {
+        let node = n(
+            Content::Vector,
+            vec![
+                n(Content::Integer(1), vec![]),
+                n(
+                    Content::SourceCode {
+                        syntax: SyntaxKind::Minimalist,
+                        source: "2".into(),
+                    },
+                    vec![],
+                ),
+            ],
+        );
+        assert_same_as_source(&node, "[1, 2]");
+    }
+
+    #[test]
+    fn attributes() {
+        let mut node = n(Content::Integer(1), vec![]);
+        node.attributes
+            .insert(deskc_ty::Type::String, dson::Dson::from("a"));
+        let mut expr = gen_expr(&node).unwrap();
+        replace_node_id_to_default(&mut expr);
+        assert_eq!(
+            expr.value,
+            Expr::Attributed {
+                attr: dson::Dson::from("a"),
+                item: Box::new(WithMeta {
+                    meta: Meta::default(),
+                    value: Expr::Literal(Literal::Integer(1)),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_operand_count() {
+        let node = n(Content::Do, vec![n(Content::Integer(1), vec![])]);
+        assert_eq!(
+            gen_expr(&node)
+                .unwrap_err()
+                .downcast::<GenAstError>()
+                .unwrap(),
+            GenAstError::OperandCount {
+                node_id: node.id,
+                content: Content::Do,
+                expected: OperandCount::Exactly(2),
+                actual: 1,
+            }
+        );
+        let node = n(
+            Content::Apply {
+                link_name: LinkName::None,
+            },
+            vec![],
+        );
+        assert_eq!(
+            gen_expr(&node)
+                .unwrap_err()
+                .downcast::<GenAstError>()
+                .unwrap(),
+            GenAstError::OperandCount {
+                node_id: node.id,
+                content: node.content.clone(),
+                expected: OperandCount::AtLeast(1),
+                actual: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn unexpected_content() {
+        let operand = n(Content::Integer(1), vec![]);
+        let node = n(
+            Content::Apply {
+                link_name: LinkName::None,
+            },
+            vec![operand.clone()],
+        );
+        assert_eq!(
+            gen_expr(&node)
+                .unwrap_err()
+                .downcast::<GenAstError>()
+                .unwrap(),
+            GenAstError::UnexpectedContent {
+                node_id: operand.id,
+                expected: NodeKind::Type,
+                content: Content::Integer(1),
+            }
+        );
+    }
+}
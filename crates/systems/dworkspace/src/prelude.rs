@@ -1,3 +1,7 @@
+pub use crate::audit::execute_assertion::AssertionError;
+pub use crate::import::{import_events, ImportError};
+pub use crate::nodes::{GenAstError, NodeKind, OperandCount};
+pub use crate::rejection::Rejection;
 pub use crate::state::State;
 pub use crate::Workspace;
 pub use components::content::Content;
@@ -0,0 +1,662 @@
+//! Rebasing concurrent text patches.
+//!
+//! A `StringPatch::DiffMatchPatch` is made against the content revision of a node, which counts
+//! content patches applied to the node. If other patches have been applied since the revision,
+//! the patch is transformed onto them (operational transformation) before it's applied.
+//!
+//! Conflict resolution rules:
+//!
+//! - Text inserted concurrently at the same position is ordered by the log; the text inserted
+//!   by the earlier event comes first.
+//! - Text deleted by both sides is deleted once.
+//! - Text inserted into a range deleted concurrently survives at the start of the range.
+//! - Replacing the whole content deletes the old content and inserts the new one, so text
+//!   inserted by later patches against older revisions survives at the start of the content.
+//! - If the base revision is too old to be rebased, or the patches don't fit the base text, the
+//!   patches are applied as is with their context matching.
+
+use std::collections::{HashMap, VecDeque};
+
+use components::{
+    content::Content,
+    event::{Event, EventPayload},
+    patch::{
+        diff_match_patch::{diff, Operation, Patch, StringDiff},
+        ContentPatch, StringPatch,
+    },
+    projection::Projection,
+};
+use deskc_ids::NodeId;
+
+/// How many past revisions per node can be rebased onto.
+const MAX_REBASE_REVISIONS: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Op {
+    Retain(usize),
+    Insert(Vec<char>),
+    Delete(usize),
+}
+
+/// An edit on a whole text.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct TextOperation {
+    ops: Vec<Op>,
+    base_len: usize,
+    target_len: usize,
+}
+
+impl TextOperation {
+    fn retain(&mut self, len: usize) {
+        if len == 0 {
+            return;
+        }
+        self.base_len += len;
+        self.target_len += len;
+        if let Some(Op::Retain(last)) = self.ops.last_mut() {
+            *last += len;
+        } else {
+            self.ops.push(Op::Retain(len));
+        }
+    }
+
+    fn insert(&mut self, chars: &[char]) {
+        if chars.is_empty() {
+            return;
+        }
+        self.target_len += chars.len();
+        // Inserts are placed before deletes to keep operations canonical.
+        let index = match self.ops.last() {
+            Some(Op::Delete(_)) => self.ops.len() - 1,
+            _ => self.ops.len(),
+        };
+        match index.checked_sub(1).map(|i| &mut self.ops[i]) {
+            Some(Op::Insert(last)) => last.extend(chars),
+            _ => self.ops.insert(index, Op::Insert(chars.to_vec())),
+        }
+    }
+
+    fn delete(&mut self, len: usize) {
+        if len == 0 {
+            return;
+        }
+        self.base_len += len;
+        if let Some(Op::Delete(last)) = self.ops.last_mut() {
+            *last += len;
+        } else {
+            self.ops.push(Op::Delete(len));
+        }
+    }
+
+    pub fn diff(text1: &str, text2: &str) -> Self {
+        let mut operation = Self::default();
+        for StringDiff {
+            operation: op,
+            text,
+        } in diff(text1, text2)
+        {
+            let chars: Vec<char> = text.chars().collect();
+            match op {
+                Operation::Equal => operation.retain(chars.len()),
+                Operation::Delete => operation.delete(chars.len()),
+                Operation::Insert => operation.insert(&chars),
+            }
+        }
+        operation
+    }
+
+    fn replace(old: &str, new: &str) -> Self {
+        let mut operation = Self::default();
+        operation.delete(old.chars().count());
+        operation.insert(&new.chars().collect::<Vec<_>>());
+        operation
+    }
+
+    /// The operation of a content patch that turned `old` into `new`.
+    ///
+    /// Diffs the texts only if the patches were applied with their context matching elsewhere.
+    fn from_content_patch(patch: &ContentPatch, old: &str, new: &str) -> Self {
+        match patch {
+            ContentPatch::PatchSourceCode(StringPatch::DiffMatchPatch { patches, .. })
+            | ContentPatch::PatchString(StringPatch::DiffMatchPatch { patches, .. }) => {
+                let old_chars: Vec<char> = old.chars().collect();
+                Self::from_patches(patches, old_chars.len())
+                    .filter(|operation| {
+                        operation
+                            .apply(&old_chars)
+                            .is_some_and(|patched| patched.iter().copied().eq(new.chars()))
+                    })
+                    .unwrap_or_else(|| Self::diff(old, new))
+            }
+            _ => Self::replace(old, new),
+        }
+    }
+
+    /// Reads patches made by `make_patches` against a text of `base_len` chars.
+    pub fn from_patches(patches: &[Patch], base_len: usize) -> Option<Self> {
+        let mut operation = Self::default();
+        let mut cursor = 0;
+        for patch in patches {
+            let start = usize::try_from(patch.start1).ok()?;
+            operation.retain(start.checked_sub(cursor)?);
+            for StringDiff {
+                operation: op,
+                text,
+            } in &patch.diffs
+            {
+                let chars: Vec<char> = text.chars().collect();
+                match op {
+                    Operation::Equal => operation.retain(chars.len()),
+                    Operation::Delete => operation.delete(chars.len()),
+                    Operation::Insert => operation.insert(&chars),
+                }
+            }
+            cursor = operation.base_len;
+        }
+        operation.retain(base_len.checked_sub(cursor)?);
+        Some(operation)
+    }
+
+    /// Makes patches without context that apply this operation to `text` exactly.
+    pub fn to_patches(&self, text: &[char]) -> Option<Vec<Patch>> {
+        if self.base_len != text.len() {
+            return None;
+        }
+        let mut patches: Vec<Patch> = Vec::new();
+        let mut editing = false;
+        let (mut pos1, mut pos2) = (0, 0);
+        for op in &self.ops {
+            if !editing && !matches!(op, Op::Retain(_)) {
+                patches.push(Patch {
+                    diffs: vec![],
+                    start1: pos1 as i32,
+                    start2: pos2 as i32,
+                    length1: 0,
+                    length2: 0,
+                });
+            }
+            let patch = patches.last_mut();
+            match (op, patch) {
+                (Op::Retain(len), _) => {
+                    editing = false;
+                    pos1 += len;
+                    pos2 += len;
+                }
+                (Op::Insert(chars), Some(patch)) => {
+                    editing = true;
+                    patch.length2 += chars.len() as i32;
+                    patch.diffs.push(StringDiff {
+                        operation: Operation::Insert,
+                        text: chars.iter().collect(),
+                    });
+                    pos2 += chars.len();
+                }
+                (Op::Delete(len), Some(patch)) => {
+                    editing = true;
+                    patch.length1 += *len as i32;
+                    patch.diffs.push(StringDiff {
+                        operation: Operation::Delete,
+                        text: text[pos1..pos1 + len].iter().collect(),
+                    });
+                    pos1 += len;
+                }
+                _ => return None,
+            }
+        }
+        Some(patches)
+    }
+
+    fn apply(&self, text: &[char]) -> Option<Vec<char>> {
+        if self.base_len != text.len() {
+            return None;
+        }
+        let mut ret = Vec::with_capacity(self.target_len);
+        let mut cursor = 0;
+        for op in &self.ops {
+            match op {
+                Op::Retain(len) => {
+                    ret.extend(&text[cursor..cursor + len]);
+                    cursor += len;
+                }
+                Op::Insert(chars) => ret.extend(chars),
+                Op::Delete(len) => cursor += len,
+            }
+        }
+        Some(ret)
+    }
+}
+
+/// Transforms concurrent operations `a` and `b` on the same text into `a'` and `b'` so that
+/// applying `a` then `b'` equals applying `b` then `a'`.
+///
+/// Inserts of `a` come first when both insert at the same position.
+pub(crate) fn transform(
+    a: &TextOperation,
+    b: &TextOperation,
+) -> Option<(TextOperation, TextOperation)> {
+    if a.base_len != b.base_len {
+        return None;
+    }
+    let mut a_prime = TextOperation::default();
+    let mut b_prime = TextOperation::default();
+    let mut ops_a: VecDeque<Op> = a.ops.iter().cloned().collect();
+    let mut ops_b: VecDeque<Op> = b.ops.iter().cloned().collect();
+    loop {
+        match (ops_a.pop_front(), ops_b.pop_front()) {
+            (None, None) => break,
+            (Some(Op::Insert(chars)), op_b) => {
+                a_prime.insert(&chars);
+                b_prime.retain(chars.len());
+                requeue(&mut ops_b, op_b);
+            }
+            (op_a, Some(Op::Insert(chars))) => {
+                a_prime.retain(chars.len());
+                b_prime.insert(&chars);
+                requeue(&mut ops_a, op_a);
+            }
+            (Some(op_a), Some(op_b)) => {
+                let (len_a, len_b) = (op_len(&op_a), op_len(&op_b));
+                let len = len_a.min(len_b);
+                match (&op_a, &op_b) {
+                    (Op::Retain(_), Op::Retain(_)) => {
+                        a_prime.retain(len);
+                        b_prime.retain(len);
+                    }
+                    (Op::Delete(_), Op::Retain(_)) => a_prime.delete(len),
+                    (Op::Retain(_), Op::Delete(_)) => b_prime.delete(len),
+                    (Op::Delete(_), Op::Delete(_)) => {}
+                    _ => unreachable!(),
+                }
+                requeue(&mut ops_a, remainder(op_a, len_a - len));
+                requeue(&mut ops_b, remainder(op_b, len_b - len));
+            }
+            _ => return None,
+        }
+    }
+    Some((a_prime, b_prime))
+}
+
+fn op_len(op: &Op) -> usize {
+    match op {
+        Op::Retain(len) | Op::Delete(len) => *len,
+        Op::Insert(chars) => chars.len(),
+    }
+}
+
+fn remainder(op: Op, len: usize) -> Option<Op> {
+    match (op, len) {
+        (_, 0) => None,
+        (Op::Retain(_), len) => Some(Op::Retain(len)),
+        (Op::Delete(_), len) => Some(Op::Delete(len)),
+        (Op::Insert(_), _) => unreachable!(),
+    }
+}
+
+fn requeue(ops: &mut VecDeque<Op>, op: Option<Op>) {
+    if let Some(op) = op {
+        ops.push_front(op);
+    }
+}
+
+#[derive(Default)]
+struct TextHistory {
+    revision: usize,
+    /// Operations applied to the latest revisions, oldest first.
+    /// `None` for patches that are not text edits.
+    operations: VecDeque<Option<TextOperation>>,
+}
+
+#[derive(Default)]
+pub struct Rebaser {
+    histories: HashMap<NodeId, TextHistory>,
+}
+
+fn text(content: &Content) -> Option<&str> {
+    match content {
+        Content::SourceCode { source, .. } => Some(source),
+        Content::String(string) => Some(string),
+        _ => None,
+    }
+}
+
+impl Rebaser {
+    pub fn revision(&self, node_id: &NodeId) -> usize {
+        self.histories
+            .get(node_id)
+            .map(|history| history.revision)
+            .unwrap_or_default()
+    }
+
+    /// Rebases a text patch in the event onto patches applied since its base revision.
+    pub fn rebase(&self, projection: &Projection, mut event: Event) -> Event {
+        let EventPayload::PatchContent {
+            node_id,
+            patch:
+                ContentPatch::PatchSourceCode(StringPatch::DiffMatchPatch {
+                    base_revision,
+                    patches,
+                })
+                | ContentPatch::PatchString(StringPatch::DiffMatchPatch {
+                    base_revision,
+                    patches,
+                }),
+        } = &mut event.payload
+        else {
+            return event;
+        };
+        let (Some(history), Some(node)) = (
+            self.histories.get(node_id),
+            projection.flat_nodes.get(node_id),
+        ) else {
+            return event;
+        };
+        let Some(current) = text(&node.content) else {
+            return event;
+        };
+        if let Some(rebased) = history.rebase(*base_revision, patches, current) {
+            *base_revision = history.revision;
+            *patches = rebased;
+        }
+        event
+    }
+
+    /// `projection` must be the one before the event is applied.
+    pub fn handle_event(&mut self, projection: &Projection, event: &Event) {
+        match &event.payload {
+            EventPayload::CreateNode { node_id, .. } => {
+                self.histories.insert(*node_id, TextHistory::default());
+            }
+            EventPayload::RemoveNode { node_id } => {
+                self.histories.remove(node_id);
+            }
+            EventPayload::PatchContent { node_id, patch } => {
+                let Some(node) = projection.flat_nodes.get(node_id) else {
+                    return;
+                };
+                let mut patched = node.clone();
+                patched.patch_content(patch);
+                let operation = match (text(&node.content), text(&patched.content)) {
+                    (Some(old), Some(new)) => {
+                        Some(TextOperation::from_content_patch(patch, old, new))
+                    }
+                    _ => None,
+                };
+                let history = self.histories.entry(*node_id).or_default();
+                history.revision += 1;
+                history.operations.push_back(operation);
+                if history.operations.len() > MAX_REBASE_REVISIONS {
+                    history.operations.pop_front();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl TextHistory {
+    fn rebase(&self, base_revision: usize, patches: &[Patch], current: &str) -> Option<Vec<Patch>> {
+        let count = self.revision.checked_sub(base_revision)?;
+        if count == 0 || count > self.operations.len() {
+            return None;
+        }
+        let mut concurrent = self.operations.iter().skip(self.operations.len() - count);
+        let first = concurrent.next()?.as_ref()?;
+        let mut operation = TextOperation::from_patches(patches, first.base_len)?;
+        for applied in std::iter::once(Some(first)).chain(concurrent.map(Option::as_ref)) {
+            operation = transform(applied?, &operation)?.1;
+        }
+        operation.to_patches(&current.chars().collect::<Vec<_>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use components::{
+        code::SyntaxKind,
+        event::EventId,
+        patch::diff_match_patch::{apply_patches, make_patches},
+        user::UserId,
+    };
+
+    use crate::{repository::TestRepository, Workspace};
+
+    use super::*;
+
+    /// xorshift to avoid depending on rand.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, max: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % max as u64) as usize
+        }
+    }
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    /// Deletes a random range and inserts `insert` at a random position of `base`.
+    fn random_edit(rng: &mut Rng, base: &str, insert: &str) -> String {
+        let mut chars = chars(base);
+        let start = rng.next(chars.len() + 1);
+        let end = start + rng.next(chars.len() - start + 1);
+        chars.drain(start..end);
+        let position = rng.next(chars.len() + 1);
+        chars.splice(position..position, insert.chars());
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn operation_from_patches() {
+        let base = "The quick brown fox jumps over the lazy dog";
+        let edited = "The quick red fox jumps over the dog!";
+        let operation =
+            TextOperation::from_patches(&make_patches(base, edited), base.chars().count()).unwrap();
+        assert_eq!(operation, TextOperation::diff(base, edited));
+        assert_eq!(operation.apply(&chars(base)), Some(chars(edited)));
+    }
+
+    #[test]
+    fn operation_to_patches() {
+        let base = "The quick brown fox";
+        let operation = TextOperation::diff(base, "A quick red fox!");
+        let patches = operation.to_patches(&chars(base)).unwrap();
+        let (patched, results) = apply_patches(&patches, base);
+        assert_eq!(patched, "A quick red fox!");
+        assert!(results.into_iter().all(|applied| applied));
+    }
+
+    #[test]
+    fn operation_of_content_patches() {
+        let replace = ContentPatch::Replace(Content::String("xyz".into()));
+        assert_eq!(
+            TextOperation::from_content_patch(&replace, "abc", "xyz"),
+            TextOperation {
+                ops: vec![Op::Insert(chars("xyz")), Op::Delete(3)],
+                base_len: 3,
+                target_len: 3,
+            }
+        );
+        let base = "The quick brown fox";
+        let edited = "The quick red fox";
+        let patch = ContentPatch::PatchString(StringPatch::diff(0, base, edited));
+        assert_eq!(
+            TextOperation::from_content_patch(&patch, base, edited),
+            TextOperation::diff(base, edited)
+        );
+        // applied at a shifted location by context matching
+        let shifted = "Look! The quick brown fox";
+        assert_eq!(
+            TextOperation::from_content_patch(&patch, shifted, "Look! The quick red fox"),
+            TextOperation::diff(shifted, "Look! The quick red fox")
+        );
+    }
+
+    #[test]
+    fn replacing_large_text_is_not_diffed() {
+        let old = "a".repeat(100_000);
+        let new = "b".repeat(100_000);
+        let operation = TextOperation::from_content_patch(
+            &ContentPatch::Replace(Content::String(new.clone())),
+            &old,
+            &new,
+        );
+        assert_eq!(operation.apply(&chars(&old)), Some(chars(&new)));
+    }
+
+    #[test]
+    fn transform_inserts_at_same_position() {
+        let a = TextOperation::diff("ab", "aXb");
+        let b = TextOperation::diff("ab", "aYb");
+        let (a_prime, b_prime) = transform(&a, &b).unwrap();
+        assert_eq!(b_prime.apply(&chars("aXb")), Some(chars("aXYb")));
+        assert_eq!(a_prime.apply(&chars("aYb")), Some(chars("aXYb")));
+    }
+
+    #[test]
+    fn transform_insert_into_deleted_range() {
+        let a = TextOperation::diff("abcd", "ad");
+        let b = TextOperation::diff("abcd", "abXcd");
+        let (_, b_prime) = transform(&a, &b).unwrap();
+        assert_eq!(b_prime.apply(&chars("ad")), Some(chars("aXd")));
+    }
+
+    #[test]
+    fn transform_randomized() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let base = "abcdefghijklmnopqrstuvwxyz";
+        for _ in 0..1000 {
+            let text_a = random_edit(&mut rng, base, "XY");
+            let text_b = random_edit(&mut rng, base, "Z");
+            let a = TextOperation::diff(base, &text_a);
+            let b = TextOperation::diff(base, &text_b);
+            let (a_prime, b_prime) = transform(&a, &b).unwrap();
+            let ab = b_prime.apply(&chars(&text_a)).unwrap();
+            let ba = a_prime.apply(&chars(&text_b)).unwrap();
+            assert_eq!(ab, ba, "{text_a} {text_b}");
+            let merged: String = ab.into_iter().collect();
+            assert!(merged.contains("XY"), "{merged}");
+            assert!(merged.contains('Z'), "{merged}");
+        }
+    }
+
+    fn source(source: &str) -> Content {
+        Content::SourceCode {
+            syntax: SyntaxKind::Minimalist,
+            source: source.into(),
+        }
+    }
+
+    fn merge_in_workspace(base: &str, edits: &[String]) -> (Workspace, NodeId) {
+        let mut repository = TestRepository::default();
+        let user_id = UserId::new();
+        let node_id = NodeId::new();
+        let e = |payload| Event {
+            id: EventId::new(),
+            user_id,
+            payload,
+        };
+        repository.mock_poll().returns(
+            [
+                e(EventPayload::AddOwner { user_id }),
+                e(EventPayload::CreateNode {
+                    node_id,
+                    content: source(base),
+                }),
+            ]
+            .into_iter()
+            .chain(edits.iter().map(|edited| {
+                e(EventPayload::PatchContent {
+                    node_id,
+                    patch: ContentPatch::PatchSourceCode(StringPatch::diff(0, base, edited)),
+                })
+            }))
+            .collect(),
+        );
+        let mut workspace = Workspace::new(repository);
+        workspace.process();
+        (workspace, node_id)
+    }
+
+    #[test]
+    fn merge_concurrent_patches() {
+        let base = "$ 1; ^add *<@l 1, @r 2>";
+        let (workspace, node_id) = merge_in_workspace(
+            base,
+            &[
+                "$ 10; ^add *<@l 1, @r 2>".into(),
+                "$ 1; ^add *<@l 1, @r 3>".into(),
+                "$ 1; ^sub *<@l 1, @r 2>".into(),
+            ],
+        );
+        assert_eq!(
+            workspace.projection.flat_nodes[&node_id].content,
+            source("$ 10; ^sub *<@l 1, @r 3>")
+        );
+        assert_eq!(workspace.content_revision(node_id), 3);
+    }
+
+    #[test]
+    fn merge_randomized_interleavings() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let base = "abcdefghijklmnopqrstuvwxyz";
+        let inserts = ["A", "BB", "CCC"];
+        for _ in 0..100 {
+            let mut edits: Vec<_> = inserts
+                .iter()
+                .map(|insert| (*insert, random_edit(&mut rng, base, insert)))
+                .collect();
+            for i in (1..edits.len()).rev() {
+                edits.swap(i, rng.next(i + 1));
+            }
+            let deleted: Vec<char> = base
+                .chars()
+                .filter(|c| edits.iter().any(|(_, edited)| !edited.contains(*c)))
+                .collect();
+            let (workspace, node_id) = merge_in_workspace(
+                base,
+                &edits
+                    .iter()
+                    .map(|(_, edited)| edited.clone())
+                    .collect::<Vec<_>>(),
+            );
+            let Content::SourceCode { source: merged, .. } =
+                &workspace.projection.flat_nodes[&node_id].content
+            else {
+                panic!()
+            };
+            for insert in inserts {
+                assert!(merged.contains(insert), "{merged} {edits:?}");
+            }
+            let remaining: String = merged.chars().filter(|c| c.is_lowercase()).collect();
+            let expected: String = base.chars().filter(|c| !deleted.contains(c)).collect();
+            assert_eq!(remaining, expected, "{merged} {edits:?}");
+        }
+    }
+
+    #[test]
+    fn old_revision_falls_back_to_context() {
+        let mut rebaser = Rebaser::default();
+        let mut projection = Projection::default();
+        let node_id = NodeId::new();
+        let e = |payload| Event {
+            id: EventId::new(),
+            user_id: UserId::new(),
+            payload,
+        };
+        let create = e(EventPayload::CreateNode {
+            node_id,
+            content: Content::String("abc".into()),
+        });
+        rebaser.handle_event(&projection, &create);
+        projection.handle_event(&create);
+        let patch = e(EventPayload::PatchContent {
+            node_id,
+            patch: ContentPatch::PatchString(StringPatch::diff(5, "abc", "abcd")),
+        });
+        assert_eq!(rebaser.rebase(&projection, patch.clone()), patch);
+    }
+}
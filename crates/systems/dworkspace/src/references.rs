@@ -85,6 +85,9 @@ impl References {
                 }
                 self.set_node(*removed, Arc::new(references));
             }
+            EventPayload::RemoveNode { node_id } => {
+                self.top_level_nodes.remove(node_id);
+            }
             EventPayload::UpdateOperandRules { node_id, rules } => {
                 self.set_operand_rules(node_id.clone(), Arc::new(rules.clone()));
             }
@@ -0,0 +1,11 @@
+use components::{event::EventId, user::UserId};
+
+use crate::audit::execute_assertion::AssertionError;
+
+/// An event rejected by the audit in `Workspace::process`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub event_id: EventId,
+    pub user_id: UserId,
+    pub error: AssertionError,
+}
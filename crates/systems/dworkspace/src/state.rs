@@ -1,8 +1,12 @@
 use components::{event::Event, projection::Projection};
 use downcast_rs::{impl_downcast, Downcast};
 
+use crate::rejection::Rejection;
+
 pub trait State: Downcast {
     fn handle_event(&mut self, projection: &Projection, event: &Event);
+    /// Called when an event is rejected by the audit instead of `handle_event`.
+    fn handle_rejection(&mut self, _projection: &Projection, _rejection: &Rejection) {}
 }
 
 impl_downcast!(State);
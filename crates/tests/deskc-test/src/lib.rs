@@ -5,9 +5,18 @@ macro_rules! test {
         #[test]
         fn $case() {
             let _ = env_logger::builder().is_test(true).try_init();
-            fn print_errors<T>(input: &str, error: deskc::query_result::QueryError) -> T {
+            fn print_errors<T>(
+                compiler: &deskc::card::DeskCompiler,
+                file_id: &ids::FileId,
+                error: deskc::query_result::QueryError,
+            ) -> T {
                 use ariadne::{Label, Report, ReportKind, Source};
+                use deskc::card::DeskcQueries;
                 use errors::textual_diagnostics::{Report as TDReport, TextualDiagnostics};
+                let deskc::Code::SourceCode { source: input, .. } = compiler.code(file_id.clone())
+                else {
+                    panic!("cannot get source code")
+                };
                 let diagnostics: TextualDiagnostics = if let Some(syntax_error) =
                     error.downcast_ref::<errors::syntax::SyntaxError>()
                 {
@@ -19,7 +28,10 @@ macro_rules! test {
                 } else if let Some(mirgen_error) =
                     error.downcast_ref::<errors::mirgen::GenMirError>()
                 {
-                    mirgen_error.into()
+                    let parsed = compiler
+                        .ast(file_id.clone())
+                        .expect("MIR is generated from parsed code");
+                    mirgen_error.textual_diagnostics(parsed.span_storage.as_ref().as_ref())
                 } else {
                     panic!("unexpected error: {:?}", error);
                 };
@@ -32,7 +44,7 @@ macro_rules! test {
                         report.with_label(Label::new(span).with_message(text))
                     })
                     .finish()
-                    .print(Source::from(input))
+                    .print(Source::from(input.as_str()))
                     .unwrap();
                 panic!()
             }
@@ -66,10 +78,10 @@ macro_rules! test {
             // Type check of case file
             let _ = compiler
                 .typeinfer(Entrypoint::File(case_file_id.clone()))
-                .unwrap_or_else(|err| print_errors(&input, err));
+                .unwrap_or_else(|err| print_errors(&compiler, &case_file_id, err));
             let parsed = compiler
-                .ast(case_file_id)
-                .unwrap_or_else(|err| print_errors(&input, err));
+                .ast(case_file_id.clone())
+                .unwrap_or_else(|err| print_errors(&compiler, &case_file_id, err));
             let dson = parsed
                 .expr
                 .as_ref()
@@ -81,7 +93,11 @@ macro_rules! test {
             let test_case: TestCase = from_dson(dson).unwrap();
 
             // assertions
+            let mut file_imports = std::collections::HashMap::new();
             for file in test_case.files {
+                if let Some(imports) = file.imports {
+                    file_imports.insert(file.id.clone(), imports);
+                }
                 compiler.set_code(
                     file.id,
                     Code::SourceCode {
@@ -90,12 +106,7 @@ macro_rules! test {
                     },
                 );
             }
-            let input = |compiler: &DeskCompiler, entrypoint: &Entrypoint| match compiler
-                .code(entrypoint.file_id().clone())
-            {
-                Code::SourceCode { source, .. } => source,
-                _ => panic!("cannot get source code"),
-            };
+            compiler.set_file_imports(Arc::new(file_imports));
 
             if let Some(typed_vec) = test_case.assertions.typed {
                 use dson::Dson;
@@ -123,7 +134,7 @@ macro_rules! test {
                     let hir = compiler
                         .hir(typed.entrypoint.clone())
                         .unwrap_or_else(|err| {
-                            print_errors(&input(&compiler, &typed.entrypoint), err)
+                            print_errors(&compiler, typed.entrypoint.file_id(), err)
                         });
                     hir_ids.visit_expr(&hir);
                     let attrs = hir_ids.ids.into_iter().collect::<HashMap<_, _>>();
@@ -132,7 +143,7 @@ macro_rules! test {
                         compiler
                             .typeinfer(typed.entrypoint.clone())
                             .unwrap_or_else(|err| {
-                                print_errors(&input(&compiler, &typed.entrypoint), err)
+                                print_errors(&compiler, typed.entrypoint.file_id(), err)
                             });
 
                     for (id, ty) in typed.typings {
@@ -152,39 +163,49 @@ macro_rules! test {
                         compiler
                             .typeinfer(run.entrypoint.clone())
                             .unwrap_or_else(|err| {
-                                print_errors(&input(&compiler, &run.entrypoint), err)
+                                print_errors(&compiler, run.entrypoint.file_id(), err)
                             });
                     let mir = compiler.mir(run.entrypoint.clone()).unwrap_or_else(|err| {
-                        print_errors(&input(&compiler, &run.entrypoint), err)
+                        print_errors(&compiler, run.entrypoint.file_id(), err)
                     });
-                    use dprocess::interpreter_builder::InterpreterBuilder;
-                    let mut miri = miri::try_create_miri_builder(
-                        (*mir).clone(),
-                        &Default::default(),
-                        conclusion,
-                    )
-                    .unwrap()
-                    .build();
-                    let start = std::time::Instant::now();
-                    let value = loop {
-                        match miri.reduce(&std::time::Duration::from_secs(1)).unwrap() {
-                            dprocess::interpreter_output::InterpreterOutput::Returned(ret) => {
-                                break ret
+                    let optimized_mir = compiler
+                        .optimized_mir(run.entrypoint.clone())
+                        .unwrap_or_else(|err| {
+                            print_errors(&compiler, run.entrypoint.file_id(), err)
+                        });
+                    // The optimizer must not change the result.
+                    for mir in [mir, optimized_mir] {
+                        use dprocess::interpreter_builder::InterpreterBuilder;
+                        let mut miri = miri::try_create_miri_builder(
+                            (*mir).clone(),
+                            &Default::default(),
+                            conclusion.clone(),
+                        )
+                        .unwrap()
+                        .build();
+                        let start = std::time::Instant::now();
+                        let value = loop {
+                            match miri.reduce(&std::time::Duration::from_secs(1)).unwrap() {
+                                dprocess::interpreter_output::InterpreterOutput::Returned(ret) => {
+                                    break ret
+                                }
+                                dprocess::interpreter_output::InterpreterOutput::Performed {
+                                    input,
+                                    effect,
+                                } => {
+                                    panic!("perform {:?} {:?}", input, effect)
+                                }
+                                dprocess::interpreter_output::InterpreterOutput::Running => {
+                                    continue
+                                }
                             }
-                            dprocess::interpreter_output::InterpreterOutput::Performed {
-                                input,
-                                effect,
-                            } => {
-                                panic!("perform {:?} {:?}", input, effect)
+                        };
+                        let end = std::time::Instant::now();
+                        println!("elapsed {:?}", end - start);
+                        match &run.result {
+                            RunResult::Success(result) => {
+                                assert_eq!(&value, result);
                             }
-                            dprocess::interpreter_output::InterpreterOutput::Running => continue,
-                        }
-                    };
-                    let end = std::time::Instant::now();
-                    println!("elapsed {:?}", end - start);
-                    match run.result {
-                        RunResult::Success(result) => {
-                            assert_eq!(value, result);
                         }
                     }
                 }
@@ -201,7 +222,14 @@ test!(case004_let_function);
 test!(case005_division_by_zero);
 test!(case006_continuation);
 test!(case007_fibonacci);
+test!(case009_check_function);
+test!(case010_type_let);
+test!(case011_check_effectful_function);
 // link is not implemented yet
 #[test]
 #[ignore]
 fn case008_cards() {}
+test!(case012_polymorphic_functions);
+test!(case013_imported_type_aliases);
+test!(case014_nested_matches);
+test!(case015_shadowed_operators);
@@ -15,6 +15,8 @@ pub struct TestCase {
 pub struct File {
     pub id: FileId,
     pub content: String,
+    /// Files whose type aliases and brands are visible in this file.
+    pub imports: Option<Vec<FileId>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]